@@ -7,21 +7,28 @@ use cw_controllers::{Admin, Hooks};
 use margined_common::validate::{
     validate_decimal_places, validate_eligible_collateral, validate_margin_ratios, validate_ratio,
 };
-use margined_perp::margined_engine::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use margined_perp::margined_engine::{
+    ExecuteMsg, FeeDistribution, InstantiateMsg, MigrateMsg, QueryMsg,
+};
 
 use crate::error::ContractError;
-use crate::handle::{trigger_mutiple_tp_sl, trigger_tp_sl, update_operator, update_tp_sl};
+use crate::handle::{
+    bind_referral_code, claim_referral_rewards, register_referral_code, trigger_mutiple_tp_sl,
+    trigger_tp_sl, update_fee_distribution, update_operator, update_referral_config, update_tp_sl,
+};
 use crate::query::{
     query_last_position_id, query_position_is_bad_debt, query_position_is_liquidated,
-    query_position_is_tpsl, query_positions,
+    query_position_is_tpsl, query_positions, query_referral, query_referral_config,
+    query_referral_rewards, query_referrer_stats, query_simulate_close_position,
+    query_simulate_open_position,
 };
 use crate::state::{init_last_position_id, read_position};
 use crate::tick::{query_tick, query_ticks};
 use crate::utils::{get_margin_ratio_calc_option, keccak_256};
 use crate::{
     handle::{
-        close_position, deposit_margin, liquidate, open_position, pay_funding, update_config,
-        withdraw_margin,
+        auto_repeg, close_position, deposit_margin, liquidate, open_position, pay_funding,
+        repeg_price, update_config, withdraw_margin,
     },
     query::{
         query_config, query_cumulative_premium_fraction, query_free_collateral, query_margin_ratio,
@@ -29,12 +36,13 @@ use crate::{
         query_trader_balance_with_funding_payment, query_trader_position_with_funding_payment,
     },
     reply::{
-        close_position_reply, liquidate_reply, open_position_reply, partial_close_position_reply,
-        partial_liquidation_reply, pay_funding_reply,
+        adjust_liquidity_reply, close_position_reply, liquidate_reply, open_position_reply,
+        partial_close_position_reply, partial_liquidation_reply, pay_funding_reply, repeg_reply,
     },
-    state::{store_config, store_state, Config, State},
+    state::{read_config, store_config, store_state, Config, State},
     utils::{
-        add_whitelist, parse_pay_funding, parse_swap, remove_whitelist, set_pause, update_pauser,
+        add_whitelist, parse_adjust_liquidity, parse_pay_funding, parse_repeg, parse_swap,
+        remove_whitelist, set_pause, update_pauser,
     },
 };
 
@@ -53,7 +61,10 @@ pub const PARTIAL_CLOSE_POSITION_REPLY_ID: u64 = 3;
 pub const LIQUIDATION_REPLY_ID: u64 = 4;
 pub const PARTIAL_LIQUIDATION_REPLY_ID: u64 = 5;
 pub const PAY_FUNDING_REPLY_ID: u64 = 6;
+pub const REPEG_REPLY_ID: u64 = 7;
+pub const ADJUST_LIQUIDITY_REPLY_ID: u64 = 8;
 pub const TRANSFER_FAILURE_REPLY_ID: u64 = 9;
+pub const CREDIT_MARKET_FUND_REPLY_ID: u64 = 10;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -108,6 +119,8 @@ pub fn instantiate(
         partial_liquidation_ratio: Uint128::zero(), // set as zero by default
         tp_sl_spread: msg.tp_sl_spread,
         liquidation_fee: msg.liquidation_fee,
+        // spread fee to the insurance fund and toll fee to the fee pool by default
+        fee_distribution: FeeDistribution::new(decimals),
     };
 
     // Initialize last position id
@@ -198,14 +211,16 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             vamm,
             position_id,
             take_profit,
-        } => trigger_tp_sl(deps, vamm, position_id, take_profit),
+        } => trigger_tp_sl(deps, env, vamm, position_id, take_profit),
         ExecuteMsg::TriggerMultipleTpSl {
             vamm,
             side,
             take_profit,
             limit,
-        } => trigger_mutiple_tp_sl(deps, vamm, side, take_profit, limit),
+        } => trigger_mutiple_tp_sl(deps, env, vamm, side, take_profit, limit),
         ExecuteMsg::PayFunding { vamm } => pay_funding(deps, env, info, vamm),
+        ExecuteMsg::RepegPrice { vamm, new_price } => repeg_price(deps, info, vamm, new_price),
+        ExecuteMsg::AutoRepeg { vamm } => auto_repeg(deps, vamm),
         ExecuteMsg::DepositMargin {
             vamm,
             position_id,
@@ -217,11 +232,22 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             amount,
         } => withdraw_margin(deps, env, info, vamm, position_id, amount),
         ExecuteMsg::SetPause { pause } => set_pause(deps, env, info, pause),
+        ExecuteMsg::UpdateReferralConfig {
+            reward_ratio,
+            discount_ratio,
+        } => update_referral_config(deps, info, reward_ratio, discount_ratio),
+        ExecuteMsg::RegisterReferralCode { code } => register_referral_code(deps, info, code),
+        ExecuteMsg::BindReferralCode { code } => bind_referral_code(deps, info, code),
+        ExecuteMsg::ClaimReferralRewards {} => claim_referral_rewards(deps, info),
+        ExecuteMsg::UpdateFeeDistribution {
+            spread_fee,
+            toll_fee,
+        } => update_fee_distribution(deps, info, spread_fee, toll_fee),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
@@ -326,6 +352,25 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_binary(&query_position_is_liquidated(deps, position_id, vamm)?)
         }
         QueryMsg::LastPositionId {} => to_binary(&query_last_position_id(deps)?),
+        QueryMsg::ReferralConfig {} => to_binary(&query_referral_config(deps)?),
+        QueryMsg::ReferrerStats { referrer } => to_binary(&query_referrer_stats(deps, referrer)?),
+        QueryMsg::Referral { trader } => to_binary(&query_referral(deps, trader)?),
+        QueryMsg::ReferralRewards { address } => to_binary(&query_referral_rewards(deps, address)?),
+        QueryMsg::SimulateOpenPosition {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+        } => to_binary(&query_simulate_open_position(
+            deps,
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+        )?),
+        QueryMsg::SimulateClosePosition { vamm, position_id } => to_binary(
+            &query_simulate_close_position(deps, env, vamm, position_id)?,
+        ),
     }
 }
 
@@ -359,8 +404,19 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
                 Ok(response)
             }
             PAY_FUNDING_REPLY_ID => {
-                let (premium_fraction, sender) = parse_pay_funding(response)?;
-                let response = pay_funding_reply(deps, env, premium_fraction, sender)?;
+                let (premium_fraction, continuous_funding, sender) = parse_pay_funding(response)?;
+                let response =
+                    pay_funding_reply(deps, env, premium_fraction, continuous_funding, sender)?;
+                Ok(response)
+            }
+            REPEG_REPLY_ID => {
+                let (repeg_cost, sender) = parse_repeg(response)?;
+                let response = repeg_reply(deps, env, repeg_cost, sender)?;
+                Ok(response)
+            }
+            ADJUST_LIQUIDITY_REPLY_ID => {
+                let (liquidity_cost, sender) = parse_adjust_liquidity(response)?;
+                let response = adjust_liquidity_reply(deps, env, liquidity_cost, sender)?;
                 Ok(response)
             }
             _ => Err(StdError::generic_err(format!(
@@ -397,6 +453,20 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
                 "funding payment failure - reply (id {:?})",
                 msg.id
             ))),
+            REPEG_REPLY_ID => Err(StdError::generic_err(format!(
+                "repeg failure - reply (id {:?})",
+                msg.id
+            ))),
+            // the liquidity adjustment is best effort, it must not revert the funding payment
+            ADJUST_LIQUIDITY_REPLY_ID => Ok(Response::new().add_attributes(vec![
+                ("action", "adjust_liquidity_reply"),
+                ("error", e.as_str()),
+            ])),
+            // tokens that cannot be credited to a sub-fund stay in the shared insurance pool
+            CREDIT_MARKET_FUND_REPLY_ID => Ok(Response::new().add_attributes(vec![
+                ("action", "credit_market_fund_reply"),
+                ("error", e.as_str()),
+            ])),
             _ => Err(StdError::generic_err(format!(
                 "reply (id {:?}) error {:?}",
                 msg.id, e
@@ -408,5 +478,13 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // configs stored before the fee distribution get the default one
+    let mut config = read_config(deps.storage)?;
+    if config.fee_distribution == FeeDistribution::default() {
+        config.fee_distribution = FeeDistribution::new(config.decimals);
+        store_config(deps.storage, &config)?;
+    }
+
     Ok(Response::new())
 }
@@ -9,22 +9,26 @@ use crate::{
     contract::{
         CLOSE_POSITION_REPLY_ID, INCREASE_POSITION_REPLY_ID, LIQUIDATION_REPLY_ID,
         PARTIAL_CLOSE_POSITION_REPLY_ID, PARTIAL_LIQUIDATION_REPLY_ID, PAY_FUNDING_REPLY_ID,
-        WHITELIST,
+        REPEG_REPLY_ID, WHITELIST,
     },
-    messages::{execute_transfer_from, withdraw},
+    messages::{execute_transfer, execute_transfer_from, withdraw},
     query::{query_free_collateral, query_margin_ratio, query_positions},
     state::{
-        increase_last_position_id, read_config, read_position, read_state, store_config,
-        store_position, store_sent_funds, store_state, store_tmp_liquidator, store_tmp_swap,
-        SentFunds, TmpReserveInfo, TmpSwapInfo,
+        increase_last_position_id, read_config, read_position, read_referral_code,
+        read_referral_config, read_referral_rewards, read_referrer_info, read_state,
+        read_trader_referral, remove_referral_rewards, store_config, store_position,
+        store_referral_code, store_referral_config, store_referrer_info, store_sent_funds,
+        store_state, store_tmp_liquidator, store_tmp_swap, store_trader_referral, ReferrerInfo,
+        SentFunds, TmpReserveInfo, TmpSwapInfo, TraderReferral,
     },
     tick::query_ticks,
     utils::{
-        calc_remain_margin_with_funding_payment, calculate_tp_sl_spread, check_tp_sl_price,
-        direction_to_side, get_asset, get_position_notional_unrealized_pnl, keccak_256,
-        position_to_side, require_additional_margin, require_bad_debt, require_insufficient_margin,
-        require_non_zero_input, require_not_paused, require_not_restriction_mode,
-        require_position_not_zero, require_vamm, side_to_direction, update_reserve,
+        accrue_funding, calc_remain_margin_with_funding_payment, calculate_tp_sl_spread,
+        check_tp_sl_price, direction_to_side, get_asset, get_position_notional_unrealized_pnl,
+        keccak_256, position_to_side, require_additional_margin, require_bad_debt,
+        require_insufficient_margin, require_non_zero_input, require_not_paused,
+        require_not_restriction_mode, require_position_not_zero, require_vamm, require_vamm_active,
+        side_to_direction, update_reserve, validate_fee_shares,
     },
 };
 use margined_common::{
@@ -34,7 +38,7 @@ use margined_common::{
     validate::{validate_margin_ratios, validate_ratio},
 };
 use margined_perp::margined_engine::{
-    PnlCalcOption, Position, PositionFilter, PositionUnrealizedPnlResponse, Side,
+    FeeShare, PnlCalcOption, Position, PositionFilter, PositionUnrealizedPnlResponse, Side,
 };
 use margined_perp::margined_vamm::{CalcFeeResponse, Direction, ExecuteMsg};
 
@@ -133,6 +137,36 @@ pub fn update_config(
     Ok(Response::default().add_attribute("action", "update_config"))
 }
 
+pub fn update_fee_distribution(
+    deps: DepsMut,
+    info: MessageInfo,
+    spread_fee: Option<Vec<FeeShare>>,
+    toll_fee: Option<Vec<FeeShare>>,
+) -> StdResult<Response> {
+    let mut config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // update recipients of the spread fee
+    if let Some(spread_fee) = spread_fee {
+        config.fee_distribution.spread_fee =
+            validate_fee_shares(deps.as_ref(), spread_fee, config.decimals)?;
+    }
+
+    // update recipients of the toll fee
+    if let Some(toll_fee) = toll_fee {
+        config.fee_distribution.toll_fee =
+            validate_fee_shares(deps.as_ref(), toll_fee, config.decimals)?;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_fee_distribution"))
+}
+
 // Opens a position
 #[allow(clippy::too_many_arguments)]
 pub fn open_position(
@@ -156,11 +190,15 @@ pub fn open_position(
 
     require_not_paused(state.pause)?;
     require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+    require_vamm_active(deps.as_ref(), &vamm)?;
 
     require_not_restriction_mode(&deps.as_ref(), &vamm, env.block.height, &trader)?;
     require_non_zero_input(margin_amount)?;
     require_non_zero_input(leverage)?;
 
+    // accrue continuous funding before the open interest changes
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm)?;
+
     let position_id = increase_last_position_id(deps.storage)?;
 
     if leverage < config.decimals {
@@ -191,7 +229,7 @@ pub fn open_position(
     let CalcFeeResponse {
         spread_fee,
         toll_fee,
-    } = vamm_controller.calc_fee(&deps.querier, open_notional)?;
+    } = vamm_controller.calc_fee(&deps.querier, open_notional, Some(side_to_direction(&side)))?;
 
     // calculate the new margin
     let new_margin_amount = margin_amount
@@ -395,6 +433,9 @@ pub fn close_position(
     require_position_not_zero(position.size.value)?;
     require_not_restriction_mode(&deps.as_ref(), &vamm, env.block.height, &trader)?;
 
+    // accrue continuous funding before the open interest changes
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm)?;
+
     // if it is long position, close a position means short it (which means base dir is AddToAmm) and vice versa
     let base_direction = if position.size > Integer::zero() {
         Direction::AddToAmm
@@ -494,6 +535,7 @@ pub fn close_position(
 
 pub fn trigger_tp_sl(
     deps: DepsMut,
+    env: Env,
     vamm: String,
     position_id: u64,
     do_tp: bool,
@@ -510,7 +552,7 @@ pub fn trigger_tp_sl(
     let position = read_position(deps.storage, &vamm_key, position_id)?;
 
     // check that vamm is open
-    if !vamm_state.open {
+    if !vamm_state.status.is_open() {
         return Err(StdError::generic_err("vAMM is not open"));
     }
 
@@ -519,12 +561,16 @@ pub fn trigger_tp_sl(
     // check the position isn't zero
     require_position_not_zero(position.size.value)?;
 
+    // accrue continuous funding before the open interest changes
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm_addr)?;
+
+    let curve_reserves = vamm_controller.curve_reserves(&deps.querier)?;
     let base_asset_amount = position.size.value;
     let quote_asset_amount = get_output_price_with_reserves(
         &position.direction,
         base_asset_amount,
-        vamm_state.quote_asset_reserve,
-        vamm_state.base_asset_reserve,
+        curve_reserves.quote_asset_reserve,
+        curve_reserves.base_asset_reserve,
     )?;
     let close_price = quote_asset_amount
         .checked_mul(config.decimals)?
@@ -573,6 +619,7 @@ pub fn trigger_tp_sl(
 
 pub fn trigger_mutiple_tp_sl(
     deps: DepsMut,
+    env: Env,
     vamm: String,
     side: Side,
     do_tp: bool,
@@ -586,18 +633,22 @@ pub fn trigger_mutiple_tp_sl(
     let vamm_state = vamm_controller.state(&deps.querier)?;
 
     // check that vamm is open
-    if !vamm_state.open {
+    if !vamm_state.status.is_open() {
         return Err(StdError::generic_err("vAMM is not open"));
     }
 
     let state = read_state(deps.storage)?;
     require_not_paused(state.pause)?;
 
+    // accrue continuous funding before the open interest changes
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm_addr)?;
+
     // query pool reserves of the vamm so that we can simulate it while triggering tp sl.
     // after simulating, we will know if the position is qualified to close or not
+    let curve_reserves = vamm_controller.curve_reserves(&deps.querier)?;
     let mut tmp_reserve = TmpReserveInfo {
-        quote_asset_reserve: vamm_state.quote_asset_reserve,
-        base_asset_reserve: vamm_state.base_asset_reserve,
+        quote_asset_reserve: curve_reserves.quote_asset_reserve,
+        base_asset_reserve: curve_reserves.base_asset_reserve,
     };
 
     let order_by = if do_tp == (side == Side::Buy) {
@@ -700,7 +751,7 @@ pub fn trigger_mutiple_tp_sl(
 
 pub fn liquidate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     vamm: String,
     position_id: u64,
@@ -726,6 +777,9 @@ pub fn liquidate(
     // store the liquidator
     store_tmp_liquidator(deps.storage, &info.sender)?;
 
+    // accrue continuous funding before the margin ratio is checked
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm)?;
+
     // retrieve the existing margin ratio of the position
     let margin_ratio = query_margin_ratio(deps.as_ref(), &position)?;
 
@@ -805,6 +859,57 @@ pub fn pay_funding(
         .add_attribute("vamm", vamm.to_string()))
 }
 
+/// Repegs the vamm to the new price, or the oracle price if none is given, and
+/// settles the repeg cost with the insurance fund. Only the owner can repeg
+pub fn repeg_price(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    new_price: Option<Uint128>,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    // check its a valid vamm
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+
+    let repeg_msg = SubMsg::reply_always(
+        wasm_execute(vamm.clone(), &ExecuteMsg::RepegPrice { new_price }, vec![])?,
+        REPEG_REPLY_ID,
+    );
+
+    Ok(Response::new()
+        .add_submessage(repeg_msg)
+        .add_attribute("action", "repeg_price")
+        .add_attribute("vamm", vamm.to_string()))
+}
+
+/// Repegs the vamm to the oracle price once it has diverged beyond the repeg
+/// threshold of the vamm, can be called by anyone
+pub fn auto_repeg(deps: DepsMut, vamm: String) -> StdResult<Response> {
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let config = read_config(deps.storage)?;
+    // check its a valid vamm
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+
+    let repeg_msg = SubMsg::reply_always(
+        wasm_execute(vamm.clone(), &ExecuteMsg::AutoRepeg {}, vec![])?,
+        REPEG_REPLY_ID,
+    );
+
+    Ok(Response::new()
+        .add_submessage(repeg_msg)
+        .add_attribute("action", "auto_repeg")
+        .add_attribute("vamm", vamm.to_string()))
+}
+
 /// Enables a user to directly deposit margin into their position
 pub fn deposit_margin(
     deps: DepsMut,
@@ -817,6 +922,9 @@ pub fn deposit_margin(
     let vamm = deps.api.addr_validate(&vamm)?;
     let trader = info.sender.clone();
 
+    // margin can be added to a position for as long as it can be closed
+    let config = read_config(deps.storage)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
     let state = read_state(deps.storage)?;
     require_not_paused(state.pause)?;
     require_non_zero_input(amount)?;
@@ -824,8 +932,6 @@ pub fn deposit_margin(
     // first try to execute the transfer
     let mut response = Response::new();
 
-    let config = read_config(deps.storage)?;
-
     match config.eligible_collateral.clone() {
         AssetInfo::NativeToken { .. } => {
             let token = Asset {
@@ -875,8 +981,10 @@ pub fn withdraw_margin(
     let vamm = deps.api.addr_validate(&vamm)?;
     let trader = info.sender;
 
+    // withdrawing margin adds to the exposure, which only an active vamm takes
     let config = read_config(deps.storage)?;
     require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+    require_vamm_active(deps.as_ref(), &vamm)?;
     let mut state = read_state(deps.storage)?;
     require_not_paused(state.pause)?;
     require_non_zero_input(amount)?;
@@ -889,6 +997,9 @@ pub fn withdraw_margin(
         return Err(StdError::generic_err("Unauthorized"));
     }
 
+    // accrue continuous funding before the margin is withdrawn
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm)?;
+
     let remain_margin = calc_remain_margin_with_funding_payment(
         deps.as_ref(),
         &position,
@@ -914,6 +1025,7 @@ pub fn withdraw_margin(
         deps.as_ref(),
         env,
         &mut state,
+        &vamm,
         &trader,
         config.eligible_collateral,
         amount,
@@ -942,6 +1054,142 @@ pub fn withdraw_margin(
     ]))
 }
 
+pub fn update_referral_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    reward_ratio: Option<Uint128>,
+    discount_ratio: Option<Uint128>,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    let mut referral_config = read_referral_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // update the share of the toll fee credited to the referrer
+    if let Some(reward_ratio) = reward_ratio {
+        validate_ratio(reward_ratio, config.decimals)?;
+        referral_config.reward_ratio = reward_ratio;
+    }
+
+    // update the share of the toll fee rebated to the trader
+    if let Some(discount_ratio) = discount_ratio {
+        validate_ratio(discount_ratio, config.decimals)?;
+        referral_config.discount_ratio = discount_ratio;
+    }
+
+    // both are taken from the toll fee so together they cannot exceed it
+    if referral_config
+        .reward_ratio
+        .checked_add(referral_config.discount_ratio)?
+        > config.decimals
+    {
+        return Err(StdError::generic_err(
+            "referral reward and discount exceed toll fee",
+        ));
+    }
+
+    store_referral_config(deps.storage, &referral_config)?;
+
+    Ok(Response::default().add_attribute("action", "update_referral_config"))
+}
+
+pub fn register_referral_code(
+    deps: DepsMut,
+    info: MessageInfo,
+    code: String,
+) -> StdResult<Response> {
+    if code.len() < 3
+        || code.len() > 32
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(StdError::generic_err("invalid referral code"));
+    }
+
+    if read_referrer_info(deps.storage, &info.sender)?.is_some() {
+        return Err(StdError::generic_err("referral code already registered"));
+    }
+
+    if read_referral_code(deps.storage, &code)?.is_some() {
+        return Err(StdError::generic_err("referral code already taken"));
+    }
+
+    store_referral_code(deps.storage, &code, &info.sender)?;
+    store_referrer_info(
+        deps.storage,
+        &info.sender,
+        &ReferrerInfo {
+            code: code.clone(),
+            referred_traders: 0u64,
+            total_rewards: Uint128::zero(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "register_referral_code"),
+        ("referrer", info.sender.as_ref()),
+        ("code", &code),
+    ]))
+}
+
+pub fn bind_referral_code(deps: DepsMut, info: MessageInfo, code: String) -> StdResult<Response> {
+    // a trader can only ever bind a single referral code
+    if read_trader_referral(deps.storage, &info.sender)?.is_some() {
+        return Err(StdError::generic_err("referral code already bound"));
+    }
+
+    let referrer = read_referral_code(deps.storage, &code)?
+        .ok_or_else(|| StdError::generic_err("referral code does not exist"))?;
+
+    if referrer == info.sender {
+        return Err(StdError::generic_err("cannot bind own referral code"));
+    }
+
+    store_trader_referral(
+        deps.storage,
+        &info.sender,
+        &TraderReferral {
+            referrer: referrer.clone(),
+            code: code.clone(),
+            total_discount: Uint128::zero(),
+        },
+    )?;
+
+    if let Some(mut referrer_info) = read_referrer_info(deps.storage, &referrer)? {
+        referrer_info.referred_traders += 1;
+        store_referrer_info(deps.storage, &referrer, &referrer_info)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "bind_referral_code"),
+        ("trader", info.sender.as_ref()),
+        ("referrer", referrer.as_ref()),
+        ("code", &code),
+    ]))
+}
+
+pub fn claim_referral_rewards(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    let rewards = read_referral_rewards(deps.storage, &info.sender)?;
+
+    if rewards.is_zero() {
+        return Err(StdError::generic_err("no referral rewards to claim"));
+    }
+
+    remove_referral_rewards(deps.storage, &info.sender);
+
+    let msg = execute_transfer(deps.storage, &info.sender, rewards)?;
+
+    Ok(Response::new().add_submessage(msg).add_attributes(vec![
+        ("action", "claim_referral_rewards"),
+        ("address", info.sender.as_ref()),
+        ("amount", &rewards.to_string()),
+    ]))
+}
+
 // Open position via vamm
 pub fn internal_open_position(
     vamm: Addr,
@@ -1,11 +1,13 @@
-use cosmwasm_std::{Addr, Deps, Env, StdError, StdResult, Storage, SubMsg, Uint128};
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, StdError, StdResult, Storage, SubMsg, Uint128};
 
 use crate::{
-    contract::TRANSFER_FAILURE_REPLY_ID,
-    state::{read_config, State},
+    contract::{CREDIT_MARKET_FUND_REPLY_ID, TRANSFER_FAILURE_REPLY_ID},
+    state::{add_period_fees, read_config, Config, State},
+    utils::accrue_referral_rewards,
 };
 
 use margined_common::{asset::AssetInfo, messages::wasm_execute};
+use margined_perp::margined_engine::{FeeRecipient, FeeShare};
 use margined_perp::margined_insurance_fund::ExecuteMsg as InsuranceFundExecuteMessage;
 
 pub fn execute_transfer_from(
@@ -41,8 +43,9 @@ pub fn execute_transfer(
 pub fn execute_transfer_to_insurance_fund(
     deps: Deps,
     env: Env,
+    vamm: &Addr,
     amount: Uint128,
-) -> StdResult<SubMsg> {
+) -> StdResult<Vec<SubMsg>> {
     let config = read_config(deps.storage)?;
 
     let token_balance = config
@@ -52,12 +55,38 @@ pub fn execute_transfer_to_insurance_fund(
     let amount_to_send = Uint128::min(token_balance, amount);
 
     match config.insurance_fund {
-        Some(insurance_fund) => execute_transfer(deps.storage, &insurance_fund, amount_to_send),
+        Some(insurance_fund) => Ok(vec![
+            execute_transfer(deps.storage, &insurance_fund, amount_to_send)?,
+            execute_credit_market_fund(&insurance_fund, vamm, amount_to_send)?,
+        ]),
         None => Err(StdError::generic_err("insurance fund is not registered")),
     }
 }
 
-pub fn execute_insurance_fund_withdrawal(deps: Deps, amount: Uint128) -> StdResult<SubMsg> {
+// Allocates tokens that have been sent to the insurance fund to the sub-fund of the vamm,
+// if the vamm is no longer registered the tokens are left in the shared pool
+pub fn execute_credit_market_fund(
+    insurance_fund: &Addr,
+    vamm: &Addr,
+    amount: Uint128,
+) -> StdResult<SubMsg> {
+    let msg = wasm_execute(
+        insurance_fund,
+        &InsuranceFundExecuteMessage::CreditMarketFund {
+            vamm: vamm.to_string(),
+            amount,
+        },
+        vec![],
+    )?;
+
+    Ok(SubMsg::reply_on_error(msg, CREDIT_MARKET_FUND_REPLY_ID))
+}
+
+pub fn execute_insurance_fund_withdrawal(
+    deps: Deps,
+    vamm: &Addr,
+    amount: Uint128,
+) -> StdResult<SubMsg> {
     let config = read_config(deps.storage)?;
 
     let insurance_fund = config
@@ -69,6 +98,7 @@ pub fn execute_insurance_fund_withdrawal(deps: Deps, amount: Uint128) -> StdResu
         &InsuranceFundExecuteMessage::Withdraw {
             token: config.eligible_collateral,
             amount,
+            vamm: Some(vamm.to_string()),
         },
         vec![],
     )?;
@@ -76,9 +106,12 @@ pub fn execute_insurance_fund_withdrawal(deps: Deps, amount: Uint128) -> StdResu
     Ok(SubMsg::reply_on_error(msg, TRANSFER_FAILURE_REPLY_ID))
 }
 
-// Transfers the toll and spread fees to the the insurance fund and fee pool
+// Transfers the toll and spread fees to their recipients in the fee distribution table, any
+// referral rewards are deducted from the toll fee and kept in the engine until they are claimed
 pub fn transfer_fees(
-    deps: Deps,
+    deps: DepsMut,
+    env: &Env,
+    vamm: &Addr,
     from: Addr,
     spread_fee: Uint128,
     toll_fee: Uint128,
@@ -87,23 +120,106 @@ pub fn transfer_fees(
     let mut messages: Vec<SubMsg> = vec![];
     let config = read_config(deps.storage)?;
 
+    // the fees decide how the liquidity is adjusted at the next funding payment
+    let fees = spread_fee.checked_add(toll_fee)?;
+    if !fees.is_zero() {
+        add_period_fees(deps.storage, vamm.clone(), fees)?;
+    }
+
     if !spread_fee.is_zero() {
-        if let Some(insurance_fund) = config.insurance_fund {
-            let msg = match open_position {
-                true => execute_transfer_from(deps.storage, &from, &insurance_fund, spread_fee)?,
-                false => execute_transfer(deps.storage, &insurance_fund, spread_fee)?,
-            };
-            messages.push(msg);
-        }
+        messages.append(&mut distribute_fee(
+            deps.storage,
+            &config,
+            &config.fee_distribution.spread_fee,
+            vamm,
+            &from,
+            spread_fee,
+            open_position,
+        )?);
     };
 
     if !toll_fee.is_zero() {
+        let referral_amount =
+            accrue_referral_rewards(deps.storage, &from, toll_fee, config.decimals)?;
+        let distributed_amount = toll_fee.checked_sub(referral_amount)?;
+
+        if !distributed_amount.is_zero() {
+            messages.append(&mut distribute_fee(
+                deps.storage,
+                &config,
+                &config.fee_distribution.toll_fee,
+                vamm,
+                &from,
+                distributed_amount,
+                open_position,
+            )?);
+        }
+
+        // native tokens are already held by the engine, cw20 tokens have to be pulled in
+        if open_position && !referral_amount.is_zero() {
+            if let AssetInfo::Token { .. } = config.eligible_collateral {
+                messages.push(execute_transfer_from(
+                    deps.storage,
+                    &from,
+                    &env.contract.address,
+                    referral_amount,
+                )?);
+            }
+        }
+    };
+    Ok(messages)
+}
+
+// Splits a fee between the weighted recipients, the last recipient takes any rounding remainder.
+// The insurance fund's share is credited to the sub-fund of the vamm the fee originates from,
+// or sent to the fee pool while no insurance fund is registered
+fn distribute_fee(
+    storage: &dyn Storage,
+    config: &Config,
+    shares: &[FeeShare],
+    vamm: &Addr,
+    from: &Addr,
+    amount: Uint128,
+    open_position: bool,
+) -> StdResult<Vec<SubMsg>> {
+    let mut messages: Vec<SubMsg> = vec![];
+    let mut remaining = amount;
+
+    for (index, share) in shares.iter().enumerate() {
+        let share_amount = if index == shares.len() - 1 {
+            remaining
+        } else {
+            amount
+                .checked_mul(share.weight)?
+                .checked_div(config.decimals)?
+        };
+        remaining = remaining.checked_sub(share_amount)?;
+
+        if share_amount.is_zero() {
+            continue;
+        }
+
+        // if the insurance fund is not registered its share goes to the fee pool
+        let (recipient, is_insurance_fund) = match &share.recipient {
+            FeeRecipient::InsuranceFund => match &config.insurance_fund {
+                Some(insurance_fund) => (insurance_fund.clone(), true),
+                None => (config.fee_pool.clone(), false),
+            },
+            FeeRecipient::FeePool => (config.fee_pool.clone(), false),
+            FeeRecipient::Address(address) => (Addr::unchecked(address), false),
+        };
+
         let msg = match open_position {
-            true => execute_transfer_from(deps.storage, &from, &config.fee_pool, toll_fee)?,
-            false => execute_transfer(deps.storage, &config.fee_pool, toll_fee)?,
+            true => execute_transfer_from(storage, from, &recipient, share_amount)?,
+            false => execute_transfer(storage, &recipient, share_amount)?,
         };
         messages.push(msg);
-    };
+
+        if is_insurance_fund {
+            messages.push(execute_credit_market_fund(&recipient, vamm, share_amount)?);
+        }
+    }
+
     Ok(messages)
 }
 
@@ -111,6 +227,7 @@ pub fn withdraw(
     deps: Deps,
     env: Env,
     state: &mut State,
+    vamm: &Addr,
     receiver: &Addr,
     eligible_collateral: AssetInfo,
     amount: Uint128,
@@ -128,7 +245,7 @@ pub fn withdraw(
 
         // add any shortfall to bad_debt
         state.prepaid_bad_debt = state.prepaid_bad_debt.checked_add(shortfall)?;
-        messages.push(execute_insurance_fund_withdrawal(deps, shortfall)?);
+        messages.push(execute_insurance_fund_withdrawal(deps, vamm, shortfall)?);
     }
 
     messages.push(execute_transfer(deps.storage, receiver, amount)?);
@@ -1,9 +1,12 @@
-use cosmwasm_std::{Deps, Order, StdError, StdResult, Storage, Uint128};
+use cosmwasm_std::{Deps, Env, Order, StdError, StdResult, Storage, Uint128};
 use margined_common::integer::Integer;
 use margined_perp::margined_engine::{
     ConfigResponse, LastPositionIdResponse, PauserResponse, PnlCalcOption, Position,
-    PositionFilter, PositionTpSlResponse, PositionUnrealizedPnlResponse, Side, StateResponse,
+    PositionFilter, PositionTpSlResponse, PositionUnrealizedPnlResponse, ReferralConfigResponse,
+    ReferralResponse, ReferrerStatsResponse, RemainMarginResponse, Side,
+    SimulateClosePositionResponse, SimulateOpenPositionResponse, StateResponse,
 };
+use margined_perp::margined_vamm::{CalcFeeResponse, Direction};
 use margined_utils::{
     contracts::helpers::{InsuranceFundController, VammController},
     tools::price_swap::get_output_price_with_reserves,
@@ -13,14 +16,17 @@ use crate::{
     contract::PAUSER,
     state::{
         read_config, read_last_position_id, read_position, read_positions,
-        read_positions_with_indexer, read_state, read_vamm_map, TmpReserveInfo,
+        read_positions_with_indexer, read_referral_config, read_referral_rewards,
+        read_referrer_info, read_state, read_trader_referral, read_vamm_map, TmpReserveInfo,
         PREFIX_POSITION_BY_PRICE, PREFIX_POSITION_BY_SIDE, PREFIX_POSITION_BY_TRADER,
     },
     tick::query_ticks,
     utils::{
+        calc_accrued_premium_fraction, calc_close_position_pnl, calc_close_position_withdraw,
         calc_funding_payment, calc_remain_margin_with_funding_payment, calculate_tp_sl_spread,
         check_tp_sl_price, get_position_notional_unrealized_pnl, keccak_256, position_is_bad_debt,
-        position_is_liquidated,
+        position_is_liquidated, require_additional_margin, require_non_zero_input,
+        require_position_not_zero, require_vamm, require_vamm_active, side_to_direction,
     },
 };
 
@@ -310,10 +316,10 @@ pub fn query_position_is_tpsl(
     let config = read_config(deps.storage)?;
     let vamm_addr = deps.api.addr_validate(&vamm)?;
     let vamm_controller = VammController(vamm_addr.clone());
-    let vamm_state = vamm_controller.state(&deps.querier).unwrap();
+    let curve_reserves = vamm_controller.curve_reserves(&deps.querier)?;
     let tmp_reserve = TmpReserveInfo {
-        quote_asset_reserve: vamm_state.quote_asset_reserve,
-        base_asset_reserve: vamm_state.base_asset_reserve,
+        quote_asset_reserve: curve_reserves.quote_asset_reserve,
+        base_asset_reserve: curve_reserves.base_asset_reserve,
     };
 
     let order_by = if do_tp == (side == Side::Buy) {
@@ -391,13 +397,13 @@ pub fn query_position_is_bad_debt(deps: Deps, position_id: u64, vamm: String) ->
     let vamm_key = keccak_256(vamm.as_bytes());
     let vamm_addr = deps.api.addr_validate(&vamm)?;
     let vamm_controller = VammController(vamm_addr.clone());
-    let vamm_state = vamm_controller.state(&deps.querier)?;
+    let curve_reserves = vamm_controller.curve_reserves(&deps.querier)?;
     let position = read_position(deps.storage, &vamm_key, position_id)?;
     let is_bad_debt = position_is_bad_debt(
         deps,
         &position,
-        vamm_state.quote_asset_reserve,
-        vamm_state.base_asset_reserve,
+        curve_reserves.quote_asset_reserve,
+        curve_reserves.base_asset_reserve,
     )?;
     Ok(is_bad_debt)
 }
@@ -416,3 +422,274 @@ pub fn query_position_is_liquidated(deps: Deps, position_id: u64, vamm: String)
     )?;
     Ok(is_liquidated)
 }
+
+/// Queries the referral reward and discount ratios
+pub fn query_referral_config(deps: Deps) -> StdResult<ReferralConfigResponse> {
+    read_referral_config(deps.storage)
+}
+
+/// Queries the code, referred traders and rewards of a referrer
+pub fn query_referrer_stats(deps: Deps, referrer: String) -> StdResult<ReferrerStatsResponse> {
+    let referrer = deps.api.addr_validate(&referrer)?;
+    let claimable_rewards = read_referral_rewards(deps.storage, &referrer)?;
+
+    let response = match read_referrer_info(deps.storage, &referrer)? {
+        Some(info) => ReferrerStatsResponse {
+            referrer,
+            code: Some(info.code),
+            referred_traders: info.referred_traders,
+            total_rewards: info.total_rewards,
+            claimable_rewards,
+        },
+        None => ReferrerStatsResponse {
+            referrer,
+            code: None,
+            referred_traders: 0u64,
+            total_rewards: Uint128::zero(),
+            claimable_rewards,
+        },
+    };
+
+    Ok(response)
+}
+
+/// Queries the referral code bound by a trader
+pub fn query_referral(deps: Deps, trader: String) -> StdResult<ReferralResponse> {
+    let trader = deps.api.addr_validate(&trader)?;
+
+    let response = match read_trader_referral(deps.storage, &trader)? {
+        Some(referral) => ReferralResponse {
+            trader,
+            referrer: Some(referral.referrer),
+            code: Some(referral.code),
+            total_discount: referral.total_discount,
+        },
+        None => ReferralResponse {
+            trader,
+            referrer: None,
+            code: None,
+            total_discount: Uint128::zero(),
+        },
+    };
+
+    Ok(response)
+}
+
+/// Queries the referral rewards an address is able to claim
+pub fn query_referral_rewards(deps: Deps, address: String) -> StdResult<Uint128> {
+    let address = deps.api.addr_validate(&address)?;
+
+    read_referral_rewards(deps.storage, &address)
+}
+
+/// Simulates opening a position with the same steps as `open_position` and its
+/// reply, the caps are checked regardless of the whitelist
+pub fn query_simulate_open_position(
+    deps: Deps,
+    vamm: String,
+    side: Side,
+    margin_amount: Uint128,
+    leverage: Uint128,
+) -> StdResult<SimulateOpenPositionResponse> {
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let vamm_controller = VammController(vamm.clone());
+    let config = read_config(deps.storage)?;
+    let state = read_state(deps.storage)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+    require_vamm_active(deps, &vamm)?;
+    require_non_zero_input(margin_amount)?;
+    require_non_zero_input(leverage)?;
+
+    if leverage < config.decimals {
+        return Err(StdError::generic_err("Leverage must be greater than 1"));
+    }
+
+    let vamm_config = vamm_controller.config(&deps.querier)?;
+
+    // calculate the margin ratio of new position wrt to leverage
+    let margin_ratio = config
+        .decimals
+        .checked_mul(config.decimals)?
+        .checked_div(leverage)?;
+
+    require_additional_margin(
+        Integer::from(margin_ratio),
+        Uint128::max(
+            config.initial_margin_ratio,
+            vamm_config.initial_margin_ratio,
+        ),
+    )?;
+
+    let CalcFeeResponse {
+        spread_fee,
+        toll_fee,
+    } = vamm_controller.calc_fee(
+        &deps.querier,
+        margin_amount
+            .checked_mul(leverage)?
+            .checked_div(config.decimals)?,
+        Some(side_to_direction(&side)),
+    )?;
+
+    // the fees are taken from the margin before the position is opened
+    let new_margin_amount = margin_amount
+        .checked_sub(spread_fee)?
+        .checked_sub(toll_fee)?;
+    require_non_zero_input(new_margin_amount)?;
+
+    let notional = new_margin_amount
+        .checked_mul(leverage)?
+        .checked_div(config.decimals)?;
+    let margin = notional
+        .checked_mul(config.decimals)?
+        .checked_div(leverage)?;
+
+    let base_asset_amount =
+        vamm_controller.input_amount(&deps.querier, side_to_direction(&side), notional)?;
+    require_non_zero_input(base_asset_amount)?;
+
+    let entry_price = notional
+        .checked_mul(config.decimals)?
+        .checked_div(base_asset_amount)?;
+
+    let spot_price = vamm_controller.spot_price(&deps.querier)?;
+    let price_impact = entry_price
+        .abs_diff(spot_price)
+        .checked_mul(config.decimals)?
+        .checked_div(spot_price)?;
+
+    // the price at which the margin ratio falls to the maintenance margin ratio
+    let (size, base_direction, liquidation_price) = match side {
+        Side::Buy => (
+            Integer::new_positive(base_asset_amount),
+            Direction::RemoveFromAmm,
+            notional
+                .saturating_sub(margin)
+                .checked_mul(config.decimals)?
+                .checked_div(base_asset_amount)?
+                .checked_mul(config.decimals)?
+                .checked_div(
+                    config
+                        .decimals
+                        .checked_sub(config.maintenance_margin_ratio)?,
+                )?,
+        ),
+        Side::Sell => (
+            Integer::new_negative(base_asset_amount),
+            Direction::AddToAmm,
+            notional
+                .checked_add(margin)?
+                .checked_mul(config.decimals)?
+                .checked_div(base_asset_amount)?
+                .checked_mul(config.decimals)?
+                .checked_div(
+                    config
+                        .decimals
+                        .checked_add(config.maintenance_margin_ratio)?,
+                )?,
+        ),
+    };
+
+    let is_over_fluctuation_limit = vamm_controller.is_over_fluctuation_limit(
+        &deps.querier,
+        base_direction,
+        base_asset_amount,
+    )?;
+
+    let is_over_open_interest_cap = !vamm_config.open_interest_notional_cap.is_zero()
+        && state.open_interest_notional.checked_add(notional)?
+            > vamm_config.open_interest_notional_cap;
+
+    let is_over_base_asset_holding_cap = !vamm_config.base_asset_holding_cap.is_zero()
+        && base_asset_amount > vamm_config.base_asset_holding_cap;
+
+    Ok(SimulateOpenPositionResponse {
+        spread_fee,
+        toll_fee,
+        margin,
+        notional,
+        size,
+        entry_price,
+        price_impact,
+        liquidation_price,
+        is_over_fluctuation_limit,
+        is_over_open_interest_cap,
+        is_over_base_asset_holding_cap,
+    })
+}
+
+/// Simulates closing a whole position with the same steps as `close_position`
+/// and its reply, a position which `close_position` would only close partially
+/// over the fluctuation limit cannot be simulated
+pub fn query_simulate_close_position(
+    deps: Deps,
+    env: Env,
+    vamm: String,
+    position_id: u64,
+) -> StdResult<SimulateClosePositionResponse> {
+    let config = read_config(deps.storage)?;
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+
+    let vamm_key = keccak_256(vamm.as_bytes());
+    let position = read_position(deps.storage, &vamm_key, position_id)?;
+
+    require_position_not_zero(position.size.value)?;
+
+    let vamm_controller = VammController(vamm.clone());
+    if config.partial_liquidation_ratio < config.decimals
+        && vamm_controller.is_over_fluctuation_limit(
+            &deps.querier,
+            Direction::RemoveFromAmm,
+            position.size.value,
+        )?
+    {
+        return Err(StdError::generic_err(
+            "Position would be closed partially over the fluctuation limit",
+        ));
+    }
+
+    // closing accrues the funding since the last trade before settling the position
+    let latest_premium_fraction = query_cumulative_premium_fraction(deps, vamm.to_string())?
+        .checked_add(calc_accrued_premium_fraction(
+            &read_vamm_map(deps.storage, &vamm)?,
+            env.block.time.seconds(),
+        )?)?;
+
+    let quote_asset_amount = vamm_controller.output_amount(
+        &deps.querier,
+        position.direction.clone(),
+        position.size.value,
+    )?;
+    let exit_price = quote_asset_amount
+        .checked_mul(config.decimals)?
+        .checked_div(position.size.value)?;
+
+    let realized_pnl =
+        calc_close_position_pnl(&position.direction, position.notional, quote_asset_amount);
+
+    let (
+        RemainMarginResponse {
+            funding_payment,
+            margin: _,
+            bad_debt,
+            latest_premium_fraction: _,
+        },
+        withdraw_amount,
+        spread_fee,
+        toll_fee,
+    ) = calc_close_position_withdraw(deps, &position, realized_pnl, latest_premium_fraction)?;
+
+    Ok(SimulateClosePositionResponse {
+        quote_asset_amount,
+        exit_price,
+        realized_pnl,
+        funding_payment,
+        spread_fee,
+        toll_fee,
+        bad_debt,
+        withdraw_amount: withdraw_amount.value,
+    })
+}
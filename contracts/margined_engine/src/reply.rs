@@ -2,31 +2,34 @@ use cosmwasm_std::{DepsMut, Env, Response, StdError, StdResult, SubMsg, Uint128}
 use margined_utils::contracts::helpers::VammController;
 
 use crate::{
+    contract::ADJUST_LIQUIDITY_REPLY_ID,
     messages::{
-        execute_insurance_fund_withdrawal, execute_transfer, execute_transfer_from,
-        execute_transfer_to_insurance_fund, transfer_fees, withdraw,
+        execute_credit_market_fund, execute_insurance_fund_withdrawal, execute_transfer,
+        execute_transfer_from, execute_transfer_to_insurance_fund, transfer_fees, withdraw,
     },
+    query::query_cumulative_premium_fraction,
     state::{
         append_cumulative_premium_fraction, enter_restriction_mode, read_config, read_position,
-        read_sent_funds, read_state, read_tmp_liquidator, read_tmp_swap, remove_position,
-        remove_sent_funds, remove_tmp_liquidator, remove_tmp_swap, store_position, store_state,
-        State,
+        read_sent_funds, read_state, read_tmp_liquidator, read_tmp_swap, read_vamm_map,
+        remove_position, remove_sent_funds, remove_tmp_liquidator, remove_tmp_swap, store_position,
+        store_state, store_vamm_map, State,
     },
     utils::{
+        accrue_funding, calc_close_position_pnl, calc_close_position_withdraw,
         calc_remain_margin_with_funding_payment, check_base_asset_holding_cap, keccak_256,
         realize_bad_debt, side_to_direction, update_open_interest_notional,
     },
 };
 
-use margined_common::{asset::AssetInfo, integer::Integer};
+use margined_common::{asset::AssetInfo, integer::Integer, messages::wasm_execute};
 use margined_perp::{
     margined_engine::{Position, RemainMarginResponse, Side},
-    margined_vamm::Direction,
+    margined_vamm::{Direction, ExecuteMsg as VammExecuteMsg},
 };
 
 // Updates position after successful execution of the swap
 pub fn open_position_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     input: Uint128,
     output: Uint128,
@@ -135,7 +138,9 @@ pub fn open_position_reply(
 
     // create messages to pay for toll and spread fees, check flag is true if this follows a reverse
     let mut fees_messages = transfer_fees(
-        deps.as_ref(),
+        deps.branch(),
+        &env,
+        &swap.vamm,
         swap.trader,
         swap.spread_fee,
         swap.toll_fee,
@@ -170,7 +175,7 @@ pub fn open_position_reply(
 
 // Closes position after successful execution of the swap
 pub fn close_position_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _input: Uint128,
     output: Uint128,
@@ -180,48 +185,28 @@ pub fn close_position_reply(
     let vamm_key = keccak_256(swap.vamm.as_bytes());
     let position = read_position(deps.storage, &vamm_key, position_id)?;
 
-    let margin_delta = match &position.direction {
-        Direction::AddToAmm => {
-            Integer::new_positive(output) - Integer::new_positive(swap.open_notional)
-        }
-        Direction::RemoveFromAmm => {
-            Integer::new_positive(swap.open_notional) - Integer::new_positive(output)
-        }
-    };
-
-    let RemainMarginResponse {
-        funding_payment,
-        margin,
-        bad_debt,
-        latest_premium_fraction: _,
-    } = calc_remain_margin_with_funding_payment(deps.as_ref(), &position, margin_delta)?;
+    let margin_delta = calc_close_position_pnl(&position.direction, swap.open_notional, output);
+
+    let latest_premium_fraction =
+        query_cumulative_premium_fraction(deps.as_ref(), swap.vamm.to_string())?;
+    let (
+        RemainMarginResponse {
+            funding_payment,
+            margin: _,
+            bad_debt,
+            latest_premium_fraction: _,
+        },
+        withdraw_amount,
+        spread_fee,
+        toll_fee,
+    ) = calc_close_position_withdraw(
+        deps.as_ref(),
+        &position,
+        margin_delta,
+        latest_premium_fraction,
+    )?;
 
     let mut msgs: Vec<SubMsg> = vec![];
-    let mut withdraw_amount = Integer::new_positive(margin).checked_add(swap.unrealized_pnl)?;
-    let mut spread_fee = Uint128::zero();
-    let mut toll_fee = Uint128::zero();
-
-    if withdraw_amount.value > position.spread_fee.checked_add(position.toll_fee)? {
-        spread_fee = position.spread_fee;
-        toll_fee = position.toll_fee;
-        withdraw_amount.value = withdraw_amount
-            .value
-            .checked_sub(position.spread_fee.checked_add(position.toll_fee)?)?;
-    } else if !position
-        .spread_fee
-        .checked_add(position.toll_fee)?
-        .is_zero()
-    {
-        // If withdraw_amount < spread_fee + toll_fee, we need to re-caculate fees
-        // new_spread_fee = withdraw_amount * spread_fee / (spread_fee + toll_fee)
-        // new_toll_fee = withdraw_amount - new_spread_fee
-        spread_fee = withdraw_amount
-            .value
-            .checked_mul(position.spread_fee)?
-            .checked_div(position.spread_fee.checked_add(position.toll_fee)?)?;
-        toll_fee = withdraw_amount.value.checked_sub(spread_fee)?;
-        withdraw_amount.value = Uint128::zero();
-    }
 
     // to prevent attacker to leverage the bad debt to withdraw extra token from insurance fund
     if !bad_debt.is_zero() {
@@ -233,8 +218,9 @@ pub fn close_position_reply(
         let config = read_config(deps.storage)?;
         msgs.append(&mut withdraw(
             deps.as_ref(),
-            env,
+            env.clone(),
             &mut state,
+            &swap.vamm,
             &swap.trader,
             config.eligible_collateral,
             withdraw_amount.value,
@@ -245,7 +231,9 @@ pub fn close_position_reply(
 
     if !spread_fee.is_zero() && !toll_fee.is_zero() {
         let mut fees_messages = transfer_fees(
-            deps.as_ref(),
+            deps.branch(),
+            &env,
+            &swap.vamm,
             swap.trader.clone(),
             spread_fee,
             toll_fee,
@@ -291,7 +279,7 @@ pub fn close_position_reply(
 
 // Partially closes position
 pub fn partial_close_position_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     input: Uint128,
     output: Uint128,
@@ -343,7 +331,9 @@ pub fn partial_close_position_reply(
 
     // calculate the fees
     let fees_messages = transfer_fees(
-        deps.as_ref(),
+        deps.branch(),
+        &env,
+        &swap.vamm,
         swap.trader,
         swap.spread_fee,
         swap.toll_fee,
@@ -442,27 +432,41 @@ pub fn liquidate_reply(
 
     let mut state = read_state(deps.storage)?;
     let pre_paid_shortfall = if !remain_margin.bad_debt.is_zero() {
-        realize_bad_debt(deps.as_ref(), remain_margin.bad_debt, &mut msgs, &mut state)?
+        realize_bad_debt(
+            deps.as_ref(),
+            &swap.vamm,
+            remain_margin.bad_debt,
+            &mut msgs,
+            &mut state,
+        )?
     } else {
         Uint128::zero()
     };
 
     // any remaining margin goes to the insurance contract
     if !remain_margin.margin.is_zero() {
-        let msg = match config.insurance_fund {
-            Some(insurance_fund) => {
-                execute_transfer(deps.storage, &insurance_fund, remain_margin.margin)?
-            }
+        let insurance_fund = match config.insurance_fund {
+            Some(insurance_fund) => insurance_fund,
             None => return Err(StdError::generic_err("insurance fund is not registered")),
         };
 
-        msgs.push(msg);
+        msgs.push(execute_transfer(
+            deps.storage,
+            &insurance_fund,
+            remain_margin.margin,
+        )?);
+        msgs.push(execute_credit_market_fund(
+            &insurance_fund,
+            &swap.vamm,
+            remain_margin.margin,
+        )?);
     }
 
     msgs.append(&mut withdraw(
         deps.as_ref(),
         env.clone(),
         &mut state,
+        &swap.vamm,
         &liquidator,
         config.eligible_collateral,
         liquidation_fee,
@@ -561,14 +565,21 @@ pub fn partial_liquidation_reply(
     let mut messages: Vec<SubMsg> = vec![];
     let mut state = read_state(deps.storage)?;
     if !liquidation_fee.is_zero() {
-        let msg = match config.insurance_fund {
-            Some(insurance_fund) => {
-                execute_transfer(deps.storage, &insurance_fund, liquidation_fee)?
-            }
+        let insurance_fund = match config.insurance_fund {
+            Some(insurance_fund) => insurance_fund,
             None => return Err(StdError::generic_err("insurance fund is not registered")),
         };
 
-        messages.push(msg);
+        messages.push(execute_transfer(
+            deps.storage,
+            &insurance_fund,
+            liquidation_fee,
+        )?);
+        messages.push(execute_credit_market_fund(
+            &insurance_fund,
+            &swap.vamm,
+            liquidation_fee,
+        )?);
 
         // calculate token balance that should be remaining once
         // insurance fees have been paid
@@ -576,6 +587,7 @@ pub fn partial_liquidation_reply(
             deps.as_ref(),
             env.clone(),
             &mut state,
+            &swap.vamm,
             &liquidator,
             config.eligible_collateral,
             liquidation_fee,
@@ -602,38 +614,139 @@ pub fn partial_liquidation_reply(
 }
 
 /// pays funding, if funding rate is positive, traders with long position
-/// pay traders with short position and vice versa.
+/// pay traders with short position and vice versa. With continuous funding
+/// the premium fraction accrues over the next funding period instead, and
+/// the funding the traders accrued since the last payment is settled.
 pub fn pay_funding_reply(
     deps: DepsMut,
     env: Env,
     premium_fraction: Integer,
+    continuous_funding: bool,
     sender: &str,
 ) -> StdResult<Response> {
     let vamm = deps.api.addr_validate(sender)?;
+    let vamm_controller = VammController(vamm.clone());
+    let vamm_config = vamm_controller.config(&deps.querier)?;
+    let config = read_config(deps.storage)?;
 
-    // update the cumulative premium fraction
-    append_cumulative_premium_fraction(deps.storage, vamm.clone(), premium_fraction)?;
+    // accrue the ending period before its premium fraction is replaced
+    accrue_funding(deps.storage, &deps.querier, &env, &vamm)?;
 
-    let vamm_controller = VammController(vamm);
-    let total_position_size = vamm_controller.state(&deps.querier)?.total_position_size;
+    let mut vamm_map = read_vamm_map(deps.storage, &vamm)?;
+    let mut funding_payment = vamm_map.unsettled_funding_payment;
+    let period_fees = vamm_map.period_fees;
+    vamm_map.unsettled_funding_payment = Integer::zero();
+    vamm_map.period_fees = Uint128::zero();
 
-    let config = read_config(deps.storage)?;
-    let funding_payment =
-        total_position_size * premium_fraction / Integer::new_positive(config.decimals);
+    if continuous_funding {
+        vamm_map.funding_premium_fraction = premium_fraction;
+        vamm_map.funding_period = vamm_config.funding_period;
+        vamm_map.last_funding_accrual = env.block.time.seconds();
+        store_vamm_map(deps.storage, vamm.clone(), &vamm_map)?;
+    } else {
+        vamm_map.funding_premium_fraction = Integer::zero();
+        vamm_map.funding_period = 0u64;
+        store_vamm_map(deps.storage, vamm.clone(), &vamm_map)?;
+
+        // update the cumulative premium fraction
+        append_cumulative_premium_fraction(deps.storage, vamm.clone(), premium_fraction)?;
+
+        let total_position_size = vamm_controller.state(&deps.querier)?.total_position_size;
+        funding_payment = funding_payment.checked_add(
+            total_position_size * premium_fraction / Integer::new_positive(config.decimals),
+        )?;
+    }
 
     let mut response: Response = Response::new();
 
     if !funding_payment.is_zero() {
-        let sub_msg = if funding_payment.is_negative() {
-            execute_insurance_fund_withdrawal(deps.as_ref(), funding_payment.value)?
+        let sub_msgs = if funding_payment.is_negative() {
+            vec![execute_insurance_fund_withdrawal(
+                deps.as_ref(),
+                &vamm,
+                funding_payment.value,
+            )?]
         } else {
-            execute_transfer_to_insurance_fund(deps.as_ref(), env, funding_payment.value)?
+            execute_transfer_to_insurance_fund(deps.as_ref(), env, &vamm, funding_payment.value)?
         };
-        response = response.add_submessage(sub_msg);
+        response = response.add_submessages(sub_msgs);
+    }
+
+    // the fees of the period net of the funding paid by the insurance fund
+    let revenue = Integer::new_positive(period_fees).checked_add(funding_payment)?;
+
+    if !vamm_config.max_liquidity_change_ratio.is_zero() {
+        response = response.add_submessage(SubMsg::reply_always(
+            wasm_execute(&vamm, &VammExecuteMsg::AdjustLiquidity { revenue }, vec![])?,
+            ADJUST_LIQUIDITY_REPLY_ID,
+        ));
     }
 
     Ok(response.add_attributes(vec![
         ("action", "pay_funding_reply"),
         ("funding_payment", &funding_payment.to_string()),
+        ("period_fees", &period_fees.to_string()),
+    ]))
+}
+
+/// settles the repeg cost, if it is positive the traders have gained and the
+/// insurance fund covers it, else the traders have lost and it is credited
+pub fn repeg_reply(
+    deps: DepsMut,
+    env: Env,
+    repeg_cost: Integer,
+    sender: &str,
+) -> StdResult<Response> {
+    let vamm = deps.api.addr_validate(sender)?;
+
+    let mut response: Response = Response::new();
+
+    if !repeg_cost.is_zero() {
+        let sub_msgs = if repeg_cost.is_positive() {
+            vec![execute_insurance_fund_withdrawal(
+                deps.as_ref(),
+                &vamm,
+                repeg_cost.value,
+            )?]
+        } else {
+            execute_transfer_to_insurance_fund(deps.as_ref(), env, &vamm, repeg_cost.value)?
+        };
+        response = response.add_submessages(sub_msgs);
+    }
+
+    Ok(response.add_attributes(vec![
+        ("action", "repeg_reply"),
+        ("repeg_cost", &repeg_cost.to_string()),
+    ]))
+}
+
+/// settles the cost of the liquidity adjustment with the insurance fund, like
+/// the repeg cost it is positive if the traders gained
+pub fn adjust_liquidity_reply(
+    deps: DepsMut,
+    env: Env,
+    liquidity_cost: Integer,
+    sender: &str,
+) -> StdResult<Response> {
+    let vamm = deps.api.addr_validate(sender)?;
+
+    let mut response: Response = Response::new();
+
+    if !liquidity_cost.is_zero() {
+        let sub_msgs = if liquidity_cost.is_positive() {
+            vec![execute_insurance_fund_withdrawal(
+                deps.as_ref(),
+                &vamm,
+                liquidity_cost.value,
+            )?]
+        } else {
+            execute_transfer_to_insurance_fund(deps.as_ref(), env, &vamm, liquidity_cost.value)?
+        };
+        response = response.add_submessages(sub_msgs);
+    }
+
+    Ok(response.add_attributes(vec![
+        ("action", "adjust_liquidity_reply"),
+        ("liquidity_cost", &liquidity_cost.to_string()),
     ]))
 }
@@ -7,7 +7,7 @@ use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
 use std::cmp::Ordering;
 
 use margined_common::{asset::Asset, integer::Integer};
-use margined_perp::margined_engine::{ConfigResponse, Position, Side};
+use margined_perp::margined_engine::{ConfigResponse, Position, ReferralConfigResponse, Side};
 
 use crate::utils::calc_range_start;
 
@@ -15,6 +15,9 @@ use crate::utils::calc_range_start;
 pub const MAX_LIMIT: u32 = 100;
 pub const DEFAULT_LIMIT: u32 = 10;
 
+// maximum number of recipients of a single fee type
+pub const MAX_FEE_RECIPIENTS: usize = 5;
+
 pub static KEY_CONFIG: &[u8] = b"config";
 pub static KEY_STATE: &[u8] = b"state";
 pub static KEY_SENT_FUNDS: &[u8] = b"sent-funds";
@@ -22,12 +25,17 @@ pub static KEY_TMP_SWAP: &[u8] = b"tmp-swap";
 pub static KEY_TMP_LIQUIDATOR: &[u8] = b"tmp-liquidator";
 pub static KEY_VAMM_MAP: &[u8] = b"vamm-map";
 pub static KEY_LAST_POSITION_ID: &[u8] = b"last_position_id";
+pub static KEY_REFERRAL_CONFIG: &[u8] = b"referral-config";
 
 static PREFIX_POSITION: &[u8] = b"position"; // prefix position
 pub static PREFIX_POSITION_BY_SIDE: &[u8] = b"position_by_direction"; // position from the direction
 pub static PREFIX_POSITION_BY_PRICE: &[u8] = b"position_by_price"; // position from the price
 pub static PREFIX_POSITION_BY_TRADER: &[u8] = b"position_by_trader"; // position from a trader
 pub static PREFIX_TICK: &[u8] = b"tick"; // this is tick with value is the total positions
+static PREFIX_REFERRAL_CODE: &[u8] = b"referral_code"; // referrer that owns a code
+static PREFIX_REFERRER: &[u8] = b"referrer"; // stats of a referrer
+static PREFIX_TRADER_REFERRAL: &[u8] = b"trader_referral"; // referral bound by a trader
+static PREFIX_REFERRAL_REWARDS: &[u8] = b"referral_rewards"; // claimable rewards of an address
 
 pub type Config = ConfigResponse;
 
@@ -313,6 +321,19 @@ pub struct TmpReserveInfo {
 pub struct VammMap {
     pub last_restriction_block: u64,
     pub cumulative_premium_fractions: Vec<Integer>,
+    // premium fraction per funding period that accrues every second, zero period if funding is discrete
+    #[serde(default)]
+    pub funding_premium_fraction: Integer,
+    #[serde(default)]
+    pub funding_period: u64,
+    #[serde(default)]
+    pub last_funding_accrual: u64,
+    // funding paid by the traders since the last settlement with the insurance fund
+    #[serde(default)]
+    pub unsettled_funding_payment: Integer,
+    // fees collected since the last funding payment
+    #[serde(default)]
+    pub period_fees: Uint128,
 }
 
 pub fn store_vamm_map(storage: &mut dyn Storage, vamm: Addr, vamm_map: &VammMap) -> StdResult<()> {
@@ -355,6 +376,15 @@ pub fn append_cumulative_premium_fraction(
     store_vamm_map(storage, vamm, &vamm_map)
 }
 
+/// Adds the fees of a trade to the fees the vamm collected in the funding period
+pub fn add_period_fees(storage: &mut dyn Storage, vamm: Addr, fees: Uint128) -> StdResult<()> {
+    let mut vamm_map = read_vamm_map(storage, &vamm)?;
+
+    vamm_map.period_fees = vamm_map.period_fees.checked_add(fees)?;
+
+    store_vamm_map(storage, vamm, &vamm_map)
+}
+
 pub fn enter_restriction_mode(
     storage: &mut dyn Storage,
     vamm: Addr,
@@ -366,3 +396,96 @@ pub fn enter_restriction_mode(
 
     store_vamm_map(storage, vamm, &vamm_map)
 }
+
+pub type ReferralConfig = ReferralConfigResponse;
+
+pub fn store_referral_config(storage: &mut dyn Storage, config: &ReferralConfig) -> StdResult<()> {
+    storage.set(KEY_REFERRAL_CONFIG, &to_vec(config)?);
+    Ok(())
+}
+
+pub fn read_referral_config(storage: &dyn Storage) -> StdResult<ReferralConfig> {
+    match storage.get(KEY_REFERRAL_CONFIG) {
+        Some(data) => from_slice(&data),
+        None => Ok(ReferralConfig::default()),
+    }
+}
+
+#[cw_serde]
+pub struct ReferrerInfo {
+    pub code: String,
+    pub referred_traders: u64,
+    pub total_rewards: Uint128,
+}
+
+#[cw_serde]
+pub struct TraderReferral {
+    pub referrer: Addr,
+    pub code: String,
+    pub total_discount: Uint128,
+}
+
+pub fn store_referral_code(
+    storage: &mut dyn Storage,
+    code: &str,
+    referrer: &Addr,
+) -> StdResult<()> {
+    Bucket::new(storage, PREFIX_REFERRAL_CODE).save(code.as_bytes(), referrer)
+}
+
+pub fn read_referral_code(storage: &dyn Storage, code: &str) -> StdResult<Option<Addr>> {
+    ReadonlyBucket::new(storage, PREFIX_REFERRAL_CODE).may_load(code.as_bytes())
+}
+
+pub fn store_referrer_info(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    info: &ReferrerInfo,
+) -> StdResult<()> {
+    Bucket::new(storage, PREFIX_REFERRER).save(referrer.as_bytes(), info)
+}
+
+pub fn read_referrer_info(
+    storage: &dyn Storage,
+    referrer: &Addr,
+) -> StdResult<Option<ReferrerInfo>> {
+    ReadonlyBucket::new(storage, PREFIX_REFERRER).may_load(referrer.as_bytes())
+}
+
+pub fn store_trader_referral(
+    storage: &mut dyn Storage,
+    trader: &Addr,
+    referral: &TraderReferral,
+) -> StdResult<()> {
+    Bucket::new(storage, PREFIX_TRADER_REFERRAL).save(trader.as_bytes(), referral)
+}
+
+pub fn read_trader_referral(
+    storage: &dyn Storage,
+    trader: &Addr,
+) -> StdResult<Option<TraderReferral>> {
+    ReadonlyBucket::new(storage, PREFIX_TRADER_REFERRAL).may_load(trader.as_bytes())
+}
+
+/// Adds to the referral rewards an address is able to claim
+pub fn increase_referral_rewards(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    Bucket::<Uint128>::new(storage, PREFIX_REFERRAL_REWARDS).update(address.as_bytes(), |rewards| {
+        Ok(rewards.unwrap_or_default().checked_add(amount)?)
+    })
+}
+
+pub fn read_referral_rewards(storage: &dyn Storage, address: &Addr) -> StdResult<Uint128> {
+    let rewards = ReadonlyBucket::<Uint128>::new(storage, PREFIX_REFERRAL_REWARDS)
+        .may_load(address.as_bytes())?
+        .unwrap_or_default();
+
+    Ok(rewards)
+}
+
+pub fn remove_referral_rewards(storage: &mut dyn Storage, address: &Addr) {
+    Bucket::<Uint128>::new(storage, PREFIX_REFERRAL_REWARDS).remove(address.as_bytes())
+}
@@ -0,0 +1,348 @@
+use cosmwasm_std::Uint128;
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::Side;
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, SimpleScenario},
+};
+
+use crate::testing::new_simple_scenario;
+
+pub const HALF_FUNDING_PERIOD: u64 = 43_200u64;
+
+#[test]
+fn test_continuous_funding_accrues_per_second_and_settles_on_pay_funding() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        owner,
+        insurance_fund,
+        engine,
+        vamm,
+        usdc,
+        pricefeed,
+        ..
+    } = new_simple_scenario();
+
+    let msg = vamm.set_continuous_funding(true).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            Some(to_decimals(17)),
+            Some(Uint128::zero()),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(1200u64),
+            to_decimals(1u64),
+            Some(to_decimals(5)),
+            Some(to_decimals(28)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(1_590_000_000u128),
+            1_000_000_000,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(2 * HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    // the first payment only sets the premium fraction of the next period
+    let msg = engine.pay_funding(vamm.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let premium_fraction = engine
+        .get_latest_cumulative_premium_fraction(&router.wrap(), vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(premium_fraction, Integer::zero());
+
+    let insurance_balance = usdc.balance(&router.wrap(), insurance_fund.addr()).unwrap();
+    assert_eq!(insurance_balance, to_decimals(5_000u64));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    // any trade accrues the funding of the half period, 0.01 * 0.5
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), 2, to_decimals(1u64))
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let premium_fraction = engine
+        .get_latest_cumulative_premium_fraction(&router.wrap(), vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(premium_fraction, Integer::new_positive(5_000_000u128));
+
+    // {balance: 37.5, margin: 300} => {balance: 37.5, margin: 299.8125}
+    let alice_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(alice_position.margin, Uint128::from(299_812_500_000u128));
+
+    // {balance: -187.5, margin: 1200} => {balance: -187.5, margin: 1200.9375 - 1}
+    let bob_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 2)
+        .unwrap();
+    assert_eq!(bob_position.margin, Uint128::from(1_199_937_500_000u128));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    let msg = engine.pay_funding(vamm.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let premium_fraction = engine
+        .get_latest_cumulative_premium_fraction(&router.wrap(), vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(premium_fraction, Integer::new_positive(10_000_000u128));
+
+    let alice_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(alice_position.margin, Uint128::from(299_625_000_000u128));
+
+    let bob_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 2)
+        .unwrap();
+    assert_eq!(bob_position.margin, Uint128::from(1_200_875_000_000u128));
+
+    // the funding accrued over the period generates a 1.5 loss, which the insurance fund covers
+    let insurance_balance = usdc.balance(&router.wrap(), insurance_fund.addr()).unwrap();
+    assert_eq!(insurance_balance, Uint128::from(4_998_500_000_000u128));
+}
+
+#[test]
+fn test_continuous_funding_is_proportional_to_holding_time() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        david,
+        owner,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = new_simple_scenario();
+
+    let msg = vamm.set_continuous_funding(true).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            Some(to_decimals(17)),
+            Some(Uint128::zero()),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(1200u64),
+            to_decimals(1u64),
+            Some(to_decimals(5)),
+            Some(to_decimals(28)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(1_590_000_000u128),
+            1_000_000_000,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(2 * HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    let msg = engine.pay_funding(vamm.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    // david opens once half of the period has accrued
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(10u64),
+            to_decimals(1u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(david.clone(), msg).unwrap();
+
+    let david_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 3)
+        .unwrap();
+    assert_eq!(david_position.margin, to_decimals(10u64));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    let msg = engine.pay_funding(vamm.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // david only pays funding for the half period the position was held
+    let david_position = engine
+        .get_position_with_funding_payment(&router.wrap(), vamm.addr().to_string(), 3)
+        .unwrap();
+    let funding_payment = david_position
+        .size
+        .checked_mul(Integer::new_positive(5_000_000u128))
+        .unwrap()
+        .checked_div(Integer::new_positive(1_000_000_000u128))
+        .unwrap();
+    assert_eq!(
+        Integer::new_positive(david_position.margin),
+        Integer::new_positive(to_decimals(10u64))
+            .checked_sub(funding_payment)
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_simulate_close_position_includes_accrued_funding() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        usdc,
+        pricefeed,
+        ..
+    } = new_simple_scenario();
+
+    let msg = vamm.set_continuous_funding(true).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            Some(to_decimals(17)),
+            Some(Uint128::zero()),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(1200u64),
+            to_decimals(1u64),
+            Some(to_decimals(5)),
+            Some(to_decimals(28)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(1_590_000_000u128),
+            1_000_000_000,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(2 * HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    let msg = engine.pay_funding(vamm.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(HALF_FUNDING_PERIOD);
+        block.height += 1;
+    });
+
+    // nothing has traded since the payment, the half period is still to be accrued
+    let premium_fraction = engine
+        .get_latest_cumulative_premium_fraction(&router.wrap(), vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(premium_fraction, Integer::zero());
+
+    // {balance: 37.5} pays 37.5 * 0.01 * 0.5 when closing
+    let simulation = engine
+        .simulate_close_position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(
+        simulation.funding_payment,
+        Integer::new_positive(187_500_000u128)
+    );
+
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance_after = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+    assert_eq!(
+        alice_balance_after.checked_sub(alice_balance).unwrap(),
+        simulation.withdraw_amount
+    );
+}
@@ -0,0 +1,205 @@
+use cosmwasm_std::Uint128;
+use margined_perp::margined_engine::{FeeRecipient, FeeShare, Side};
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, SimpleScenario},
+};
+
+use crate::testing::new_simple_scenario;
+
+#[test]
+fn test_update_fee_distribution_validation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .update_fee_distribution(
+            None,
+            Some(vec![FeeShare {
+                recipient: FeeRecipient::FeePool,
+                weight: to_decimals(1u64),
+            }]),
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: unauthorized".to_string()
+    );
+
+    // weights have to sum to 100%
+    let msg = engine
+        .update_fee_distribution(
+            None,
+            Some(vec![
+                FeeShare {
+                    recipient: FeeRecipient::FeePool,
+                    weight: Uint128::from(700_000_000u128),
+                },
+                FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(200_000_000u128),
+                },
+            ]),
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: fee weights must sum to 100%".to_string()
+    );
+
+    let msg = engine.update_fee_distribution(Some(vec![]), None).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: invalid number of fee recipients".to_string()
+    );
+
+    let msg = engine
+        .update_fee_distribution(
+            Some(vec![
+                FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(500_000_000u128),
+                },
+                FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(500_000_000u128),
+                },
+            ]),
+            None,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: duplicate fee recipient".to_string()
+    );
+
+    let msg = engine
+        .update_fee_distribution(
+            Some(vec![
+                FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: to_decimals(1u64),
+                },
+                FeeShare {
+                    recipient: FeeRecipient::FeePool,
+                    weight: Uint128::zero(),
+                },
+            ]),
+            None,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: fee weight cannot be zero".to_string()
+    );
+}
+
+#[test]
+fn test_fees_are_split_between_recipients() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        carol,
+        usdc,
+        fee_pool,
+        insurance_fund,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    // 10% toll and spread fees
+    let msg = vamm.set_toll_ratio(Uint128::from(100_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // toll fee: 60% fee pool, 30% insurance fund, 10% to carol as treasury
+    let msg = engine
+        .update_fee_distribution(
+            None,
+            Some(vec![
+                FeeShare {
+                    recipient: FeeRecipient::FeePool,
+                    weight: Uint128::from(600_000_000u128),
+                },
+                FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(300_000_000u128),
+                },
+                FeeShare {
+                    recipient: FeeRecipient::Address(carol.to_string()),
+                    weight: Uint128::from(100_000_000u128),
+                },
+            ]),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = engine.config(&router.wrap()).unwrap();
+    assert_eq!(config.fee_distribution.toll_fee.len(), 3usize);
+
+    // alice opens long position with 60 margin, 2x leverage, spread and toll fees are 12 each
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(2u64),
+            None,
+            None,
+            Uint128::zero(),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let fee_pool_balance = usdc
+        .balance(&router.wrap(), fee_pool.addr().clone())
+        .unwrap();
+    assert_eq!(fee_pool_balance, Uint128::from(7_200_000_000u128));
+    let carol_balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    assert_eq!(carol_balance, Uint128::from(1_200_000_000u128));
+    let insurance_fund_balance = usdc
+        .balance(&router.wrap(), insurance_fund.addr().clone())
+        .unwrap();
+    assert_eq!(insurance_fund_balance, Uint128::from(5_015_600_000_000u128));
+
+    // the insurance fund's share of both fees is credited to the market they came from
+    let market_fund = insurance_fund
+        .market_fund(&router.wrap(), vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(market_fund.balance, Uint128::from(15_600_000_000u128));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // the same split is applied when the fees are charged on close
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let fee_pool_balance = usdc
+        .balance(&router.wrap(), fee_pool.addr().clone())
+        .unwrap();
+    assert_eq!(fee_pool_balance, Uint128::from(14_400_000_000u128));
+    let carol_balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    assert_eq!(carol_balance, Uint128::from(2_400_000_000u128));
+}
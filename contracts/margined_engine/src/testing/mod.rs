@@ -1,10 +1,12 @@
 mod bad_debt_tests;
+mod continuous_funding_tests;
 mod cw_token_add_remove_margin_tests;
 mod cw_token_liquidation_frontrun_hack_tests;
 mod cw_token_liquidation_tests;
 mod cw_token_pay_funding_tests;
 mod cw_token_position_fee_tests;
 mod fee_calculation_tests;
+mod fee_distribution_tests;
 mod fluctuation_tests;
 mod margin_engine_tests;
 mod margin_ratio_tests;
@@ -19,6 +21,9 @@ mod personal_position_tests;
 mod position_liquidation_tests;
 mod position_tests;
 mod position_upper_bound_tests;
+mod referral_tests;
+mod repeg_tests;
+mod simulation_tests;
 mod tests;
 mod tp_sl_test;
 mod whitelist_tests;
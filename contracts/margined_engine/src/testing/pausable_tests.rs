@@ -1,4 +1,4 @@
-use margined_perp::margined_engine::Side;
+use margined_perp::{margined_engine::Side, margined_vamm::MarketStatus};
 use margined_utils::{
     cw_multi_test::Executor,
     testing::{to_decimals, SimpleScenario},
@@ -156,3 +156,152 @@ fn test_cant_unpause_when_already_unpaused_and_vice_versa() {
         "Generic error: unauthorized".to_string()
     );
 }
+
+#[test]
+fn test_reduce_only_vamm_only_closes_positions() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(1u64),
+            to_decimals(1u64),
+            Some(to_decimals(11)),
+            Some(to_decimals(8)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = vamm.set_status(MarketStatus::ReduceOnly).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(1u64),
+            to_decimals(1u64),
+            Some(to_decimals(11)),
+            Some(to_decimals(8)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is reduce only".to_string()
+    );
+
+    // margin can still be added to the position but not taken out of it
+    let msg = engine
+        .deposit_margin(vamm.addr().to_string(), 1, to_decimals(1u64), vec![])
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), 1, to_decimals(1u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is reduce only".to_string()
+    );
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_settled_vamm_refuses_trading() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(1u64),
+            to_decimals(1u64),
+            Some(to_decimals(11)),
+            Some(to_decimals(8)),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = vamm.set_status(MarketStatus::Settling).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), 1, to_decimals(1u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is reduce only".to_string()
+    );
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = vamm.set_status(MarketStatus::Settled).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(1u64),
+            to_decimals(1u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is not open".to_string()
+    );
+
+    let msg = engine
+        .deposit_margin(vamm.addr().to_string(), 1, to_decimals(1u64), vec![])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is not open".to_string()
+    );
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), 1, to_decimals(1u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: vAMM is not open".to_string()
+    );
+}
@@ -1111,6 +1111,17 @@ fn test_openclose_position_to_check_fee_is_charged() {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
     router.execute(owner.clone(), msg).unwrap();
@@ -1184,6 +1195,17 @@ fn test_openclose_position_to_check_fee_is_charged_toll_ratio_5_percent() {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
     router.execute(owner.clone(), msg).unwrap();
@@ -0,0 +1,232 @@
+use cosmwasm_std::Uint128;
+use margined_perp::margined_engine::Side;
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, SimpleScenario},
+};
+
+use crate::testing::new_simple_scenario;
+
+#[test]
+fn test_referral_code_registration_and_binding() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        carol,
+        engine,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .register_referral_code("bob-code".to_string())
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    // a referrer can only register a single code
+    let msg = engine
+        .register_referral_code("bob-code-2".to_string())
+        .unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: referral code already registered".to_string()
+    );
+
+    // codes cannot be registered twice
+    let msg = engine
+        .register_referral_code("bob-code".to_string())
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: referral code already taken".to_string()
+    );
+
+    let msg = engine.register_referral_code("c!".to_string()).unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: invalid referral code".to_string()
+    );
+
+    let msg = engine.bind_referral_code("bob-code".to_string()).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: cannot bind own referral code".to_string()
+    );
+
+    let msg = engine.bind_referral_code("carol-code".to_string()).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: referral code does not exist".to_string()
+    );
+
+    let msg = engine.bind_referral_code("bob-code".to_string()).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // a trader can only bind once
+    let msg = engine.bind_referral_code("bob-code".to_string()).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: referral code already bound".to_string()
+    );
+
+    let referral = engine
+        .get_referral(&router.wrap(), alice.to_string())
+        .unwrap();
+    assert_eq!(referral.referrer, Some(bob.clone()));
+    assert_eq!(referral.code, Some("bob-code".to_string()));
+
+    let stats = engine
+        .get_referrer_stats(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(stats.code, Some("bob-code".to_string()));
+    assert_eq!(stats.referred_traders, 1u64);
+    assert_eq!(stats.total_rewards, Uint128::zero());
+}
+
+#[test]
+fn test_update_referral_config() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .update_referral_config(Some(Uint128::from(200_000_000u128)), None)
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: unauthorized".to_string()
+    );
+
+    // reward and discount together cannot exceed the toll fee
+    let msg = engine
+        .update_referral_config(
+            Some(Uint128::from(600_000_000u128)),
+            Some(Uint128::from(500_000_000u128)),
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: referral reward and discount exceed toll fee".to_string()
+    );
+
+    let msg = engine
+        .update_referral_config(
+            Some(Uint128::from(200_000_000u128)),
+            Some(Uint128::from(100_000_000u128)),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = engine.referral_config(&router.wrap()).unwrap();
+    assert_eq!(config.reward_ratio, Uint128::from(200_000_000u128));
+    assert_eq!(config.discount_ratio, Uint128::from(100_000_000u128));
+}
+
+#[test]
+fn test_referral_rewards_are_taken_from_toll_fee() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        fee_pool,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    // 10% toll fee
+    let msg = vamm.set_toll_ratio(Uint128::from(100_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // 20% of the toll fee to the referrer, 10% back to the trader
+    let msg = engine
+        .update_referral_config(
+            Some(Uint128::from(200_000_000u128)),
+            Some(Uint128::from(100_000_000u128)),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .register_referral_code("bob-code".to_string())
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine.bind_referral_code("bob-code".to_string()).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // alice opens long position with 60 margin, 5x leverage, toll fee is 30
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(5u64),
+            None,
+            None,
+            Uint128::zero(),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4940u64));
+
+    // fee pool receives the toll fee less 6 for bob and 3 for alice
+    let fee_pool_balance = usdc
+        .balance(&router.wrap(), fee_pool.addr().clone())
+        .unwrap();
+    assert_eq!(fee_pool_balance, to_decimals(21u64));
+    let engine_balance = usdc.balance(&router.wrap(), engine.addr().clone()).unwrap();
+    assert_eq!(engine_balance, to_decimals(39u64));
+
+    let stats = engine
+        .get_referrer_stats(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(stats.total_rewards, to_decimals(6u64));
+    assert_eq!(stats.claimable_rewards, to_decimals(6u64));
+
+    let referral = engine
+        .get_referral(&router.wrap(), alice.to_string())
+        .unwrap();
+    assert_eq!(referral.total_discount, to_decimals(3u64));
+
+    let msg = engine.claim_referral_rewards().unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine.claim_referral_rewards().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let bob_balance = usdc.balance(&router.wrap(), bob.clone()).unwrap();
+    assert_eq!(bob_balance, to_decimals(5006u64));
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4943u64));
+
+    let rewards = engine
+        .get_referral_rewards(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(rewards, Uint128::zero());
+
+    // nothing left to claim
+    let msg = engine.claim_referral_rewards().unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: no referral rewards to claim".to_string()
+    );
+}
@@ -0,0 +1,205 @@
+use cosmwasm_std::{StdError, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::{PnlCalcOption, Side};
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, SimpleScenario},
+};
+
+use crate::testing::new_simple_scenario;
+
+#[test]
+fn test_repeg_price_credits_insurance_fund_when_traders_lose() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        insurance_fund,
+        engine,
+        vamm,
+        usdc,
+        ..
+    } = new_simple_scenario();
+
+    // alice is long 37.5 and the price moves to 25.6
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // only the owner can repeg
+    let msg = engine
+        .repeg_price(vamm.addr().to_string(), Some(to_decimals(20u64)))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "unauthorized".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = engine
+        .repeg_price(vamm.addr().to_string(), Some(to_decimals(20u64)))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // the position of alice is worth 490.090345161 instead of 600
+    let alice_pnl = engine
+        .get_unrealized_pnl(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            1,
+            PnlCalcOption::SpotPrice,
+        )
+        .unwrap();
+    assert_eq!(
+        alice_pnl.unrealized_pnl,
+        Integer::new_negative(109_909_654_839u128)
+    );
+
+    // the loss of the traders is moved to the insurance fund
+    let engine_balance = usdc.balance(&router.wrap(), engine.addr()).unwrap();
+    assert_eq!(engine_balance, Uint128::from(190_090_345_161u128));
+    let insurance_balance = usdc.balance(&router.wrap(), insurance_fund.addr()).unwrap();
+    assert_eq!(insurance_balance, Uint128::from(5_109_909_654_839u128));
+}
+
+#[test]
+fn test_auto_repeg_charges_insurance_fund_within_budget() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        insurance_fund,
+        engine,
+        vamm,
+        usdc,
+        pricefeed,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // disabled until the vamm has a repeg threshold
+    let msg = engine.auto_repeg(vamm.addr().to_string()).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "repeg failure - reply (id 7)".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // 10% threshold and a budget of 100 per funding period
+    let msg = vamm
+        .set_repeg_config(Uint128::from(100_000_000u128), to_decimals(100u64))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(30u64), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600);
+        block.height += 1;
+    });
+
+    // anyone can trigger the repeg once the twap of 25.6 diverges from 30
+    let msg = engine.auto_repeg(vamm.addr().to_string()).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let spot_price = vamm.spot_price(&router.wrap()).unwrap();
+    assert!(spot_price.abs_diff(to_decimals(30u64)) < Uint128::from(10u128));
+
+    // alice gains 82.016978267 which is covered by the insurance fund
+    let engine_balance = usdc.balance(&router.wrap(), engine.addr()).unwrap();
+    assert_eq!(engine_balance, Uint128::from(382_016_978_267u128));
+    let insurance_balance = usdc.balance(&router.wrap(), insurance_fund.addr()).unwrap();
+    assert_eq!(insurance_balance, Uint128::from(4_917_983_021_733u128));
+
+    let budget = vamm.repeg_budget(&router.wrap()).unwrap();
+    assert_eq!(budget.spent, Uint128::from(82_016_978_267u128));
+}
+
+#[test]
+fn test_auto_repeg_fails_over_budget() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        insurance_fund,
+        engine,
+        vamm,
+        usdc,
+        pricefeed,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the repeg would cost 82.016978267
+    let msg = vamm
+        .set_repeg_config(Uint128::from(100_000_000u128), to_decimals(50u64))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(30u64), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600);
+        block.height += 1;
+    });
+
+    let msg = engine.auto_repeg(vamm.addr().to_string()).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "repeg failure - reply (id 7)".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let engine_balance = usdc.balance(&router.wrap(), engine.addr()).unwrap();
+    assert_eq!(engine_balance, to_decimals(300u64));
+    let insurance_balance = usdc.balance(&router.wrap(), insurance_fund.addr()).unwrap();
+    assert_eq!(insurance_balance, to_decimals(5_000u64));
+}
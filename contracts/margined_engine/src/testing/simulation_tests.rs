@@ -0,0 +1,411 @@
+use cosmwasm_std::{StdError, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::Side;
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, SimpleScenario},
+};
+
+use crate::testing::new_simple_scenario;
+
+#[test]
+fn test_simulate_open_position() {
+    let SimpleScenario {
+        router,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    // {margin: 300, leverage: 2} => {notional: 600, size: 37.5}
+    let simulation = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.spread_fee, Uint128::zero());
+    assert_eq!(simulation.toll_fee, Uint128::zero());
+    assert_eq!(simulation.margin, to_decimals(300u64));
+    assert_eq!(simulation.notional, to_decimals(600u64));
+    assert_eq!(simulation.size, Integer::new_positive(37_500_000_000u128));
+    assert_eq!(simulation.entry_price, to_decimals(16u64));
+    assert_eq!(simulation.price_impact, Uint128::from(600_000_000u128));
+    // (600 - 300) / 37.5 / 0.95
+    assert_eq!(
+        simulation.liquidation_price,
+        Uint128::from(8_421_052_631u128)
+    );
+    assert!(!simulation.is_over_fluctuation_limit);
+    assert!(!simulation.is_over_open_interest_cap);
+    assert!(!simulation.is_over_base_asset_holding_cap);
+
+    // {margin: 300, leverage: 2} => {notional: 600, size: -150}
+    let simulation = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(300u64),
+            to_decimals(2u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.size, Integer::new_negative(to_decimals(150u64)));
+    assert_eq!(simulation.entry_price, to_decimals(4u64));
+    assert_eq!(simulation.price_impact, Uint128::from(600_000_000u128));
+    // (600 + 300) / 150 / 1.05
+    assert_eq!(
+        simulation.liquidation_price,
+        Uint128::from(5_714_285_714u128)
+    );
+
+    let err = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(30u64),
+        )
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: Position is undercollateralized"
+                .to_string()
+        },
+        err
+    );
+}
+
+#[test]
+fn test_simulate_open_position_matches_open_position() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    // 1% toll and spread fees
+    let msg = vamm.set_toll_ratio(Uint128::from(10_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let simulation = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.spread_fee, to_decimals(6u64));
+    assert_eq!(simulation.toll_fee, to_decimals(6u64));
+    assert_eq!(simulation.margin, to_decimals(288u64));
+    assert_eq!(simulation.notional, to_decimals(576u64));
+    assert_eq!(simulation.size, Integer::new_positive(36_548_223_350u128));
+    assert_eq!(simulation.entry_price, Uint128::from(15_760_000_000u128));
+    assert_eq!(simulation.price_impact, Uint128::from(576_000_000u128));
+    assert_eq!(
+        simulation.liquidation_price,
+        Uint128::from(8_294_736_842u128)
+    );
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(position.spread_fee, simulation.spread_fee);
+    assert_eq!(position.toll_fee, simulation.toll_fee);
+    assert_eq!(position.margin, simulation.margin);
+    assert_eq!(position.notional, simulation.notional);
+    assert_eq!(position.size, simulation.size);
+    assert_eq!(position.entry_price, simulation.entry_price);
+}
+
+#[test]
+fn test_simulate_open_position_reports_caps() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    let msg = vamm
+        .set_open_interest_notional_cap(to_decimals(500u64))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm.set_base_asset_holding_cap(to_decimals(10u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_fluctuation_limit_ratio(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // a long of 600 moves the price from 10 to 25.6
+    let simulation = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+        )
+        .unwrap();
+    assert!(simulation.is_over_fluctuation_limit);
+    assert!(simulation.is_over_open_interest_cap);
+    assert!(simulation.is_over_base_asset_holding_cap);
+
+    // a long of 10 moves the price from 10 to 10.201
+    let simulation = engine
+        .simulate_open_position(
+            &router.wrap(),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(10u64),
+            to_decimals(1u64),
+        )
+        .unwrap();
+    assert!(!simulation.is_over_fluctuation_limit);
+    assert!(!simulation.is_over_open_interest_cap);
+    assert!(!simulation.is_over_base_asset_holding_cap);
+}
+
+#[test]
+fn test_simulate_close_position_matches_close_position() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        usdc,
+        ..
+    } = new_simple_scenario();
+
+    // 1% toll and spread fees
+    let msg = vamm.set_toll_ratio(Uint128::from(10_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(100u64),
+            to_decimals(1u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    // {size: 36.54822335, notional: 576, margin: 288} => {quote: 635.420725094}
+    let simulation = engine
+        .simulate_close_position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(
+        simulation.quote_asset_amount,
+        Uint128::from(635_420_725_094u128)
+    );
+    assert_eq!(simulation.exit_price, Uint128::from(17_385_817_061u128));
+    assert_eq!(
+        simulation.realized_pnl,
+        Integer::new_positive(59_420_725_094u128)
+    );
+    assert_eq!(simulation.funding_payment, Integer::zero());
+    assert_eq!(simulation.spread_fee, to_decimals(6u64));
+    assert_eq!(simulation.toll_fee, to_decimals(6u64));
+    assert_eq!(simulation.bad_debt, Uint128::zero());
+    // 288 + 59.420725094 - 12
+    assert_eq!(
+        simulation.withdraw_amount,
+        Uint128::from(335_420_725_094u128)
+    );
+
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance_after = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+    assert_eq!(
+        alice_balance_after.checked_sub(alice_balance).unwrap(),
+        simulation.withdraw_amount
+    );
+}
+
+#[test]
+fn test_simulate_close_position_with_bad_debt() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(10u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(1000u64),
+            to_decimals(3u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    // {size: 75, notional: 3000, margin: 300} => {quote: 428.571428571}
+    let simulation = engine
+        .simulate_close_position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+    assert_eq!(
+        simulation.realized_pnl,
+        Integer::new_negative(2_571_428_571_429u128)
+    );
+    assert_eq!(simulation.bad_debt, Uint128::from(2_271_428_571_429u128));
+    assert_eq!(simulation.withdraw_amount, Uint128::zero());
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), 1, to_decimals(0u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Cannot close position - bad debt".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_simulate_close_position_over_fluctuation_limit() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = new_simple_scenario();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(300u64),
+            to_decimals(2u64),
+            None,
+            None,
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(1);
+        block.height += 1;
+    });
+
+    let msg = vamm
+        .set_fluctuation_limit_ratio(Uint128::from(100u128)) // 0.000001
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_partial_liquidation_ratio(Uint128::from(250_000_000u128)) // 0.25
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let err = engine
+        .simulate_close_position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: Position would be closed partially over the fluctuation limit"
+                .to_string()
+        },
+        err
+    );
+
+    // the whole position is closed while the partial liquidation ratio is 1
+    let msg = engine
+        .set_partial_liquidation_ratio(to_decimals(1u64))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    engine
+        .simulate_close_position(&router.wrap(), vamm.addr().to_string(), 1)
+        .unwrap();
+}
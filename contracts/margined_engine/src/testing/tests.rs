@@ -1,10 +1,18 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, ADJUST_LIQUIDITY_REPLY_ID,
+    CREDIT_MARKET_FUND_REPLY_ID, PAY_FUNDING_REPLY_ID,
+};
+use crate::messages::transfer_fees;
+use crate::state::{read_config, KEY_CONFIG};
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{from_binary, Addr, Uint128};
+use cosmwasm_std::{
+    coins, from_binary, to_vec, Addr, BankMsg, CosmosMsg, Reply, Storage, SubMsgResult, Uint128,
+};
 use margined_common::asset::{AssetInfo, NATIVE_DENOM};
 use margined_common::integer::Integer;
 use margined_perp::margined_engine::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, PauserResponse, QueryMsg,
+    ConfigResponse, ExecuteMsg, FeeDistribution, FeeRecipient, FeeShare, InstantiateMsg,
+    MigrateMsg, PauserResponse, QueryMsg,
 };
 
 const OWNER: &str = "owner";
@@ -53,10 +61,91 @@ fn test_instantiation() {
             partial_liquidation_ratio: Uint128::zero(),
             tp_sl_spread: Uint128::from(50_000u128),
             liquidation_fee: Uint128::from(100u128),
+            fee_distribution: FeeDistribution {
+                spread_fee: vec![FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(10u128.pow(6u32)),
+                }],
+                toll_fee: vec![FeeShare {
+                    recipient: FeeRecipient::FeePool,
+                    weight: Uint128::from(10u128.pow(6u32)),
+                }],
+            },
         }
     );
 }
 
+#[test]
+fn test_migrate_fills_in_fee_distribution() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        pauser: OWNER.to_string(),
+        operator: None,
+        insurance_fund: Some(INSURANCE_FUND.to_string()),
+        fee_pool: FEE_POOL.to_string(),
+        eligible_collateral: NATIVE_DENOM.to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128), // 0.05
+        maintenance_margin_ratio: Uint128::from(50_000u128), // 0.05
+        tp_sl_spread: Uint128::from(50_000u128),         // 0.05
+        liquidation_fee: Uint128::from(100u128),
+    };
+    let info = mock_info(OWNER, &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the config as it was stored before the fee distribution
+    let config = read_config(&deps.storage).unwrap();
+    let data = String::from_utf8(to_vec(&config).unwrap()).unwrap();
+    let end = data.find(r#","fee_distribution""#).unwrap();
+    let legacy = format!("{}}}", &data[..end]);
+    deps.storage.set(KEY_CONFIG, legacy.as_bytes());
+    assert_eq!(
+        read_config(&deps.storage).unwrap().fee_distribution,
+        FeeDistribution::default()
+    );
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    assert_eq!(read_config(&deps.storage).unwrap(), config);
+}
+
+#[test]
+fn test_insurance_fee_share_goes_to_fee_pool_without_insurance_fund() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        pauser: OWNER.to_string(),
+        operator: None,
+        insurance_fund: None,
+        fee_pool: FEE_POOL.to_string(),
+        eligible_collateral: NATIVE_DENOM.to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128), // 0.05
+        maintenance_margin_ratio: Uint128::from(50_000u128), // 0.05
+        tp_sl_spread: Uint128::from(50_000u128),         // 0.05
+        liquidation_fee: Uint128::from(100u128),
+    };
+    let info = mock_info(OWNER, &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the default distribution sends the spread fee to the insurance fund
+    let messages = transfer_fees(
+        deps.as_mut(),
+        &mock_env(),
+        &Addr::unchecked("vamm"),
+        Addr::unchecked("trader"),
+        Uint128::from(100u128),
+        Uint128::zero(),
+        false,
+    )
+    .unwrap();
+    assert_eq!(messages.len(), 1usize);
+    assert_eq!(
+        messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: FEE_POOL.to_string(),
+            amount: coins(100u128, NATIVE_DENOM),
+        })
+    );
+}
+
 #[test]
 fn test_update_config() {
     let mut deps = mock_dependencies();
@@ -107,6 +196,16 @@ fn test_update_config() {
             partial_liquidation_ratio: Uint128::zero(),
             tp_sl_spread: Uint128::from(50_000u128),
             liquidation_fee: Uint128::from(100u128),
+            fee_distribution: FeeDistribution {
+                spread_fee: vec![FeeShare {
+                    recipient: FeeRecipient::InsuranceFund,
+                    weight: Uint128::from(10u128.pow(6u32)),
+                }],
+                toll_fee: vec![FeeShare {
+                    recipient: FeeRecipient::FeePool,
+                    weight: Uint128::from(10u128.pow(6u32)),
+                }],
+            },
         }
     );
 
@@ -186,3 +285,68 @@ fn test_update_pauser() {
     let result = execute(deps.as_mut(), mock_env(), info, msg);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_failed_liquidity_adjustment_is_skipped() {
+    let mut deps = mock_dependencies();
+
+    // a failed liquidity adjustment leaves the funding payment in place
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: ADJUST_LIQUIDITY_REPLY_ID,
+            result: SubMsgResult::Err("liquidity adjustment is disabled".to_string()),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes
+            .iter()
+            .find(|&attr| attr.key == "error")
+            .unwrap()
+            .value,
+        "liquidity adjustment is disabled",
+    );
+
+    // while a failed funding payment still reverts
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: PAY_FUNDING_REPLY_ID,
+            result: SubMsgResult::Err("vAMM is not open".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res.to_string(),
+        "Generic error: funding payment failure - reply (id 6)"
+    );
+}
+
+#[test]
+fn test_failed_market_fund_credit_is_skipped() {
+    let mut deps = mock_dependencies();
+
+    // fees for a vamm removed from the insurance fund stay in its shared pool
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: CREDIT_MARKET_FUND_REPLY_ID,
+            result: SubMsgResult::Err("vAMM is not registered".to_string()),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes
+            .iter()
+            .find(|&attr| attr.key == "error")
+            .unwrap()
+            .value,
+        "vAMM is not registered",
+    );
+}
@@ -1,6 +1,6 @@
 use cosmwasm_std::{
-    Addr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, SubMsg, SubMsgResponse,
-    Uint128,
+    Addr, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdError, StdResult, Storage,
+    SubMsg, SubMsgResponse, Uint128,
 };
 use margined_utils::{
     contracts::helpers::{InsuranceFundController, VammController},
@@ -16,15 +16,20 @@ use margined_common::{
     messages::{read_event, read_response},
 };
 use margined_perp::margined_engine::{
-    PnlCalcOption, Position, PositionUnrealizedPnlResponse, RemainMarginResponse, Side,
+    FeeRecipient, FeeShare, PnlCalcOption, Position, PositionUnrealizedPnlResponse,
+    RemainMarginResponse, Side,
 };
-use margined_perp::margined_vamm::Direction;
+use margined_perp::margined_vamm::{Direction, MarketStatus};
 
 use crate::{
     contract::{PAUSER, WHITELIST},
     messages::execute_insurance_fund_withdrawal,
     query::{query_cumulative_premium_fraction, query_margin_ratio},
-    state::{read_config, read_state, read_vamm_map, store_state, State, TmpReserveInfo},
+    state::{
+        increase_referral_rewards, read_config, read_referral_config, read_referrer_info,
+        read_state, read_trader_referral, read_vamm_map, store_referrer_info, store_state,
+        store_trader_referral, store_vamm_map, State, TmpReserveInfo, VammMap, MAX_FEE_RECIPIENTS,
+    },
 };
 
 pub fn keccak_256(input: &[u8]) -> Vec<u8> {
@@ -60,6 +65,7 @@ pub fn get_asset(info: MessageInfo, eligible_collateral: AssetInfo) -> Asset {
 
 pub fn realize_bad_debt(
     deps: Deps,
+    vamm: &Addr,
     bad_debt: Uint128,
     messages: &mut Vec<SubMsg>,
     state: &mut State,
@@ -71,7 +77,11 @@ pub fn realize_bad_debt(
         // in order to realize all the bad debt vault need extra tokens from insuranceFund
         let bad_debt_delta = bad_debt.checked_sub(state.prepaid_bad_debt)?;
 
-        messages.push(execute_insurance_fund_withdrawal(deps, bad_debt_delta)?);
+        messages.push(execute_insurance_fund_withdrawal(
+            deps,
+            vamm,
+            bad_debt_delta,
+        )?);
 
         state.prepaid_bad_debt = Uint128::zero();
 
@@ -81,6 +91,89 @@ pub fn realize_bad_debt(
     Ok(Uint128::zero())
 }
 
+// credits the referrer and the trader with their share of the toll fee, returns the total
+// amount that has been carved out of the toll fee and is kept by the engine until claimed
+pub fn accrue_referral_rewards(
+    storage: &mut dyn Storage,
+    trader: &Addr,
+    toll_fee: Uint128,
+    decimals: Uint128,
+) -> StdResult<Uint128> {
+    let mut referral = match read_trader_referral(storage, trader)? {
+        Some(referral) => referral,
+        None => return Ok(Uint128::zero()),
+    };
+
+    let referral_config = read_referral_config(storage)?;
+
+    let reward = toll_fee
+        .checked_mul(referral_config.reward_ratio)?
+        .checked_div(decimals)?;
+    let discount = toll_fee
+        .checked_mul(referral_config.discount_ratio)?
+        .checked_div(decimals)?;
+
+    if !reward.is_zero() {
+        increase_referral_rewards(storage, &referral.referrer, reward)?;
+
+        if let Some(mut referrer_info) = read_referrer_info(storage, &referral.referrer)? {
+            referrer_info.total_rewards = referrer_info.total_rewards.checked_add(reward)?;
+            store_referrer_info(storage, &referral.referrer, &referrer_info)?;
+        }
+    }
+
+    if !discount.is_zero() {
+        increase_referral_rewards(storage, trader, discount)?;
+
+        referral.total_discount = referral.total_discount.checked_add(discount)?;
+        store_trader_referral(storage, trader, &referral)?;
+    }
+
+    Ok(reward.checked_add(discount)?)
+}
+
+// validates the recipients of a fee type and that their weights add up to the whole fee
+pub fn validate_fee_shares(
+    deps: Deps,
+    shares: Vec<FeeShare>,
+    decimals: Uint128,
+) -> StdResult<Vec<FeeShare>> {
+    if shares.is_empty() || shares.len() > MAX_FEE_RECIPIENTS {
+        return Err(StdError::generic_err("invalid number of fee recipients"));
+    }
+
+    let mut total_weight = Uint128::zero();
+    let mut validated: Vec<FeeShare> = vec![];
+    for share in shares {
+        if share.weight.is_zero() {
+            return Err(StdError::generic_err("fee weight cannot be zero"));
+        }
+        total_weight = total_weight.checked_add(share.weight)?;
+
+        let recipient = match share.recipient {
+            FeeRecipient::Address(address) => {
+                FeeRecipient::Address(deps.api.addr_validate(&address)?.to_string())
+            }
+            recipient => recipient,
+        };
+
+        if validated.iter().any(|s| s.recipient == recipient) {
+            return Err(StdError::generic_err("duplicate fee recipient"));
+        }
+
+        validated.push(FeeShare {
+            recipient,
+            weight: share.weight,
+        });
+    }
+
+    if total_weight != decimals {
+        return Err(StdError::generic_err("fee weights must sum to 100%"));
+    }
+
+    Ok(validated)
+}
+
 // this blocks trades if open interest is too high, required during the bootstrapping of the project
 pub fn update_open_interest_notional(
     deps: &Deps,
@@ -218,9 +311,21 @@ pub fn calc_remain_margin_with_funding_payment(
     position: &Position,
     margin_delta: Integer,
 ) -> StdResult<RemainMarginResponse> {
-    // calculate the funding payment
     let latest_premium_fraction =
         query_cumulative_premium_fraction(deps, position.vamm.to_string())?;
+
+    calc_remain_margin_with_premium_fraction(deps, position, margin_delta, latest_premium_fraction)
+}
+
+/// Calculates the remaining margin of a position with the funding paid up to
+/// the given cumulative premium fraction
+pub fn calc_remain_margin_with_premium_fraction(
+    deps: Deps,
+    position: &Position,
+    margin_delta: Integer,
+    latest_premium_fraction: Integer,
+) -> StdResult<RemainMarginResponse> {
+    // calculate the funding payment
     let config = read_config(deps.storage)?;
     let funding_payment = (latest_premium_fraction - position.last_updated_premium_fraction)
         * position.size
@@ -262,6 +367,143 @@ pub fn calc_funding_payment(
     }
 }
 
+// takes the position fees out of the amount withdrawn when closing a position,
+// returns the amount withdrawn and the spread and toll fees paid
+pub fn calc_close_position_fees(
+    mut withdraw_amount: Integer,
+    position_spread_fee: Uint128,
+    position_toll_fee: Uint128,
+) -> StdResult<(Integer, Uint128, Uint128)> {
+    let mut spread_fee = Uint128::zero();
+    let mut toll_fee = Uint128::zero();
+
+    if withdraw_amount.value > position_spread_fee.checked_add(position_toll_fee)? {
+        spread_fee = position_spread_fee;
+        toll_fee = position_toll_fee;
+        withdraw_amount.value = withdraw_amount
+            .value
+            .checked_sub(position_spread_fee.checked_add(position_toll_fee)?)?;
+    } else if !position_spread_fee
+        .checked_add(position_toll_fee)?
+        .is_zero()
+    {
+        // If withdraw_amount < spread_fee + toll_fee, we need to re-caculate fees
+        // new_spread_fee = withdraw_amount * spread_fee / (spread_fee + toll_fee)
+        // new_toll_fee = withdraw_amount - new_spread_fee
+        spread_fee = withdraw_amount
+            .value
+            .checked_mul(position_spread_fee)?
+            .checked_div(position_spread_fee.checked_add(position_toll_fee)?)?;
+        toll_fee = withdraw_amount.value.checked_sub(spread_fee)?;
+        withdraw_amount.value = Uint128::zero();
+    }
+
+    Ok((withdraw_amount, spread_fee, toll_fee))
+}
+
+/// Calculates the pnl realized by closing a position of `open_notional` for
+/// `quote_asset_amount`
+pub fn calc_close_position_pnl(
+    direction: &Direction,
+    open_notional: Uint128,
+    quote_asset_amount: Uint128,
+) -> Integer {
+    match direction {
+        Direction::AddToAmm => {
+            Integer::new_positive(quote_asset_amount) - Integer::new_positive(open_notional)
+        }
+        Direction::RemoveFromAmm => {
+            Integer::new_positive(open_notional) - Integer::new_positive(quote_asset_amount)
+        }
+    }
+}
+
+/// Calculates the remaining margin of a closed position with the funding paid
+/// up to the given cumulative premium fraction, and the amount withdrawn by
+/// the trader once the fees are paid from it
+pub fn calc_close_position_withdraw(
+    deps: Deps,
+    position: &Position,
+    realized_pnl: Integer,
+    latest_premium_fraction: Integer,
+) -> StdResult<(RemainMarginResponse, Integer, Uint128, Uint128)> {
+    let remain_margin = calc_remain_margin_with_premium_fraction(
+        deps,
+        position,
+        realized_pnl,
+        latest_premium_fraction,
+    )?;
+
+    let (withdraw_amount, spread_fee, toll_fee) = calc_close_position_fees(
+        Integer::new_positive(remain_margin.margin),
+        position.spread_fee,
+        position.toll_fee,
+    )?;
+
+    Ok((remain_margin, withdraw_amount, spread_fee, toll_fee))
+}
+
+/// Accrues the funding of a vamm with continuous funding for the seconds
+/// elapsed since the last trade, this has to happen before the open
+/// interest of the vamm changes. Funding paid by the traders is tracked
+/// until it is settled with the insurance fund by `PayFunding`.
+pub fn accrue_funding(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    vamm: &Addr,
+) -> StdResult<()> {
+    let mut vamm_map = read_vamm_map(storage, vamm)?;
+
+    // funding is discrete
+    if vamm_map.funding_period == 0 {
+        return Ok(());
+    }
+
+    let now = env.block.time.seconds();
+    if now <= vamm_map.last_funding_accrual {
+        return Ok(());
+    }
+
+    let premium_fraction = calc_accrued_premium_fraction(&vamm_map, now)?;
+
+    if !premium_fraction.is_zero() {
+        match vamm_map.cumulative_premium_fractions.last_mut() {
+            Some(latest) => *latest = latest.checked_add(premium_fraction)?,
+            None => vamm_map.cumulative_premium_fractions.push(premium_fraction),
+        }
+
+        let config = read_config(storage)?;
+        let total_position_size = VammController(vamm.clone())
+            .state(querier)?
+            .total_position_size;
+
+        vamm_map.unsettled_funding_payment = vamm_map.unsettled_funding_payment.checked_add(
+            total_position_size
+                .checked_mul(premium_fraction)?
+                .checked_div(Integer::new_positive(config.decimals))?,
+        )?;
+    }
+
+    vamm_map.last_funding_accrual = now;
+
+    store_vamm_map(storage, vamm.clone(), &vamm_map)
+}
+
+/// Premium fraction a vamm with continuous funding has accrued since the last
+/// accrual, zero if funding is discrete
+pub fn calc_accrued_premium_fraction(vamm_map: &VammMap, now: u64) -> StdResult<Integer> {
+    if vamm_map.funding_period == 0 || now <= vamm_map.last_funding_accrual {
+        return Ok(Integer::zero());
+    }
+
+    let elapsed = now - vamm_map.last_funding_accrual;
+    Ok(vamm_map
+        .funding_premium_fraction
+        .checked_mul(Integer::new_positive(elapsed))?
+        .checked_div(Integer::new_positive(vamm_map.funding_period))?)
+}
+
 pub fn update_pauser(deps: DepsMut, info: MessageInfo, pauser: String) -> StdResult<Response> {
     // validate the address
     let valid_pauser = deps.api.addr_validate(&pauser)?;
@@ -322,13 +564,23 @@ pub fn require_vamm(deps: Deps, insurance: &Option<Addr>, vamm: &Addr) -> StdRes
 
     let vamm_controller = VammController(vamm.clone());
     // check that vamm is open
-    if !vamm_controller.state(&deps.querier)?.open {
+    if !vamm_controller.state(&deps.querier)?.status.is_open() {
         return Err(StdError::generic_err("vAMM is not open"));
     }
 
     Ok(Response::new())
 }
 
+// Check that the vamm takes new positions, a reduce only vamm only lets them be closed
+pub fn require_vamm_active(deps: Deps, vamm: &Addr) -> StdResult<Response> {
+    let vamm_controller = VammController(vamm.clone());
+    if vamm_controller.state(&deps.querier)?.status != MarketStatus::Active {
+        return Err(StdError::generic_err("vAMM is reduce only"));
+    }
+
+    Ok(Response::new())
+}
+
 // Check no bad debt
 pub fn require_bad_debt(bad_debt: Uint128) -> StdResult<Response> {
     if !bad_debt.is_zero() {
@@ -435,15 +687,37 @@ pub fn parse_swap(response: &SubMsgResponse) -> StdResult<(Uint128, Uint128, u64
     }
 }
 
-pub fn parse_pay_funding(response: &SubMsgResponse) -> StdResult<(Integer, &str)> {
+pub fn parse_pay_funding(response: &SubMsgResponse) -> StdResult<(Integer, bool, &str)> {
     // Find swap inputs and output events
     let wasm = read_response("wasm", response)?;
     let premium_str = read_event("premium_fraction", wasm)?;
     let premium = Integer::from_str(premium_str)?;
 
+    let continuous_funding = read_event("continuous_funding", wasm)? == "true";
+
+    let sender = read_event("_contract_address", wasm)?;
+
+    Ok((premium, continuous_funding, sender))
+}
+
+pub fn parse_repeg(response: &SubMsgResponse) -> StdResult<(Integer, &str)> {
+    let wasm = read_response("wasm", response)?;
+    let repeg_cost_str = read_event("repeg_cost", wasm)?;
+    let repeg_cost = Integer::from_str(repeg_cost_str)?;
+
+    let sender = read_event("_contract_address", wasm)?;
+
+    Ok((repeg_cost, sender))
+}
+
+pub fn parse_adjust_liquidity(response: &SubMsgResponse) -> StdResult<(Integer, &str)> {
+    let wasm = read_response("wasm", response)?;
+    let liquidity_cost_str = read_event("liquidity_cost", wasm)?;
+    let liquidity_cost = Integer::from_str(liquidity_cost_str)?;
+
     let sender = read_event("_contract_address", wasm)?;
 
-    Ok((premium, sender))
+    Ok((liquidity_cost, sender))
 }
 
 // takes the side (buy|sell) and returns the direction (long|short)
@@ -1,8 +1,14 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
 use crate::{
-    handle::{add_token, remove_token, send_token, update_owner},
-    query::{query_all_token, query_config, query_is_token, query_owner, query_token_list_length},
+    handle::{
+        add_token, claim, distribute, remove_token, send_token, set_min_reserve, stake, unstake,
+        update_config, update_distribution, update_owner,
+    },
+    query::{
+        query_all_token, query_config, query_distribution, query_is_token, query_last_distribution,
+        query_owner, query_pending_rewards, query_staking_state, query_token_list_length,
+    },
     state::{store_config, Config},
 };
 
@@ -11,7 +17,9 @@ use cosmwasm_std::{
 };
 use cw2::set_contract_version;
 use cw_controllers::Admin;
-use margined_perp::margined_fee_pool::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use margined_perp::margined_fee_pool::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, DEFAULT_STAKER_SHARE_BPS,
+};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "crates.io:margined-fee-pool";
@@ -29,7 +37,10 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let config = Config {};
+    let config = Config {
+        staking_token: None,
+        staker_share_bps: DEFAULT_STAKER_SHARE_BPS,
+    };
 
     store_config(deps.storage, &config)?;
 
@@ -48,18 +59,37 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             token,
             amount,
             recipient,
-        } => send_token(deps.as_ref(), env, info, token, amount, recipient),
+        } => send_token(deps, env, info, token, amount, recipient),
+        ExecuteMsg::UpdateConfig {
+            staking_token,
+            staker_share_bps,
+        } => update_config(deps, env, info, staking_token, staker_share_bps),
+        ExecuteMsg::Stake { amount } => stake(deps, env, info, amount),
+        ExecuteMsg::Unstake { amount } => unstake(deps, env, info, amount),
+        ExecuteMsg::Claim {} => claim(deps, env, info),
+        ExecuteMsg::UpdateDistribution {
+            recipients,
+            interval,
+        } => update_distribution(deps, info, recipients, interval),
+        ExecuteMsg::SetMinReserve { token, amount } => set_min_reserve(deps, info, token, amount),
+        ExecuteMsg::Distribute {} => distribute(deps, env),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
         QueryMsg::IsToken { token } => to_binary(&query_is_token(deps, token)?),
         QueryMsg::GetTokenList { limit } => to_binary(&query_all_token(deps, limit)?),
         QueryMsg::GetTokenLength {} => to_binary(&query_token_list_length(deps)?),
+        QueryMsg::GetStakingState {} => to_binary(&query_staking_state(deps)?),
+        QueryMsg::GetPendingRewards { staker } => {
+            to_binary(&query_pending_rewards(deps, env, staker)?)
+        }
+        QueryMsg::GetDistribution {} => to_binary(&query_distribution(deps)?),
+        QueryMsg::GetLastDistribution {} => to_binary(&query_last_distribution(deps)?),
     }
 }
 
@@ -1,9 +1,18 @@
-use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
-use margined_common::validate::validate_eligible_collateral as validate_funds;
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+use margined_common::{asset::AssetInfo, validate::validate_eligible_collateral as validate_funds};
+use margined_perp::margined_fee_pool::DistributionShare;
 
 use crate::{
     contract::OWNER,
-    state::{is_token, remove_token as remove_token_from_list, save_token},
+    state::{
+        is_token, read_config, read_distribution, read_last_distribution, read_min_reserve,
+        read_reward_state, read_staker, read_token_list, read_total_staked, remove_staker,
+        remove_token as remove_token_from_list, save_token, store_config, store_distribution,
+        store_last_distribution, store_min_reserve, store_reward_state, store_staker,
+        store_total_staked, LastDistribution, BPS_DENOMINATOR, MAX_DISTRIBUTION_RECIPIENTS,
+        TOKEN_LIMIT,
+    },
+    utils::{sync_reward_state, update_rewards},
 };
 
 pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
@@ -46,7 +55,7 @@ pub fn remove_token(deps: DepsMut, info: MessageInfo, token: String) -> StdResul
 }
 
 pub fn send_token(
-    deps: Deps,
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
     token: String,
@@ -59,12 +68,12 @@ pub fn send_token(
     }
 
     // check permissions to send the message
-    if !OWNER.is_admin(deps, &info.sender)? {
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
     // validate the token we want to send (this also tells us if it is native token or not)
-    let valid_token = validate_funds(deps, token)?;
+    let valid_token = validate_funds(deps.as_ref(), token)?;
 
     // validate the recipient address
     let valid_recipient = deps.api.addr_validate(&recipient)?;
@@ -74,15 +83,340 @@ pub fn send_token(
         return Err(StdError::generic_err("This token is not supported"));
     };
 
-    // query the balance of the given token that this contract holds
-    let balance = valid_token.query_balance(&deps.querier, env.contract.address)?;
+    // only the fees that have not been allocated to stakers can be sent
+    let mut reward_state = sync_reward_state(deps.as_ref(), &env, &valid_token)?;
 
     // check that the balance is sufficient to pay the amount
-    if balance < amount {
+    if reward_state.last_balance < amount {
         return Err(StdError::generic_err("Insufficient funds"));
     }
+
+    reward_state.last_balance = reward_state.last_balance.checked_sub(amount)?;
+    store_reward_state(deps.storage, &valid_token, &reward_state)?;
+
     Ok(Response::default()
         .add_message(valid_token.into_msg(valid_recipient.to_string(), amount, None)?)
-        .add_attribute("action", "send_token")
-    )
+        .add_attribute("action", "send_token"))
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    staking_token: Option<String>,
+    staker_share_bps: Option<u64>,
+) -> StdResult<Response> {
+    let mut config = read_config(deps.storage)?;
+
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // change the governance token, only possible while nothing is staked
+    if let Some(staking_token) = staking_token {
+        if !read_total_staked(deps.storage)?.is_zero() {
+            return Err(StdError::generic_err(
+                "cannot change staking token while tokens are staked",
+            ));
+        }
+
+        config.staking_token = Some(deps.api.addr_validate(&staking_token)?);
+    }
+
+    // change the share of new fees allocated to the stakers
+    if let Some(staker_share_bps) = staker_share_bps {
+        if staker_share_bps > BPS_DENOMINATOR {
+            return Err(StdError::generic_err(
+                "staker share cannot exceed 10000 bps",
+            ));
+        }
+
+        // fees received up to now are split with the previous share
+        for token in read_token_list(deps.storage, TOKEN_LIMIT)
+            .unwrap_or_default()
+            .iter()
+        {
+            let reward_state = sync_reward_state(deps.as_ref(), &env, token)?;
+            store_reward_state(deps.storage, token, &reward_state)?;
+        }
+
+        config.staker_share_bps = staker_share_bps;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
+pub fn stake(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    let staking_token = config
+        .staking_token
+        .ok_or_else(|| StdError::generic_err("staking token is not set"))?;
+
+    if amount.is_zero() {
+        return Err(StdError::generic_err("stake amount cannot be zero"));
+    }
+
+    // rewards are settled before the stake changes
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+    update_rewards(deps.branch(), &env, &mut staker)?;
+
+    staker.staked = staker.staked.checked_add(amount)?;
+    store_staker(deps.storage, &info.sender, &staker)?;
+
+    let total_staked = read_total_staked(deps.storage)?.checked_add(amount)?;
+    store_total_staked(deps.storage, &total_staked)?;
+
+    let msg = AssetInfo::Token {
+        contract_addr: staking_token,
+    }
+    .into_msg(
+        env.contract.address.to_string(),
+        amount,
+        Some(info.sender.to_string()),
+    )?;
+
+    Ok(Response::default().add_message(msg).add_attributes(vec![
+        ("action", "stake"),
+        ("staker", info.sender.as_ref()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+pub fn unstake(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    let staking_token = config
+        .staking_token
+        .ok_or_else(|| StdError::generic_err("staking token is not set"))?;
+
+    if amount.is_zero() {
+        return Err(StdError::generic_err("unstake amount cannot be zero"));
+    }
+
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+    if staker.staked < amount {
+        return Err(StdError::generic_err("insufficient staked amount"));
+    }
+
+    // rewards are settled before the stake changes
+    update_rewards(deps.branch(), &env, &mut staker)?;
+
+    staker.staked = staker.staked.checked_sub(amount)?;
+    store_staker(deps.storage, &info.sender, &staker)?;
+
+    let total_staked = read_total_staked(deps.storage)?.checked_sub(amount)?;
+    store_total_staked(deps.storage, &total_staked)?;
+
+    let msg = AssetInfo::Token {
+        contract_addr: staking_token,
+    }
+    .into_msg(info.sender.to_string(), amount, None)?;
+
+    Ok(Response::default().add_message(msg).add_attributes(vec![
+        ("action", "unstake"),
+        ("staker", info.sender.as_ref()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+pub fn claim(mut deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+    update_rewards(deps.branch(), &env, &mut staker)?;
+
+    let mut msgs = vec![];
+    for reward in staker.rewards.iter_mut() {
+        if reward.pending.is_zero() {
+            continue;
+        }
+
+        // claimed rewards are no longer held for the stakers
+        let mut reward_state = read_reward_state(deps.storage, &reward.token)?;
+        reward_state.accounted_balance =
+            reward_state.accounted_balance.checked_sub(reward.pending)?;
+        store_reward_state(deps.storage, &reward.token, &reward_state)?;
+
+        msgs.push(
+            reward
+                .token
+                .clone()
+                .into_msg(info.sender.to_string(), reward.pending, None)?,
+        );
+        reward.pending = Uint128::zero();
+    }
+
+    if msgs.is_empty() {
+        return Err(StdError::generic_err("no rewards to claim"));
+    }
+
+    if staker.staked.is_zero() {
+        remove_staker(deps.storage, &info.sender);
+    } else {
+        store_staker(deps.storage, &info.sender, &staker)?;
+    }
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attributes(vec![("action", "claim"), ("staker", info.sender.as_ref())]))
+}
+
+pub fn update_distribution(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipients: Option<Vec<DistributionShare>>,
+    interval: Option<u64>,
+) -> StdResult<Response> {
+    let mut distribution = read_distribution(deps.storage)?;
+
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // change the recipients and their weights, an empty list disables distributions
+    if let Some(recipients) = recipients {
+        if recipients.len() > MAX_DISTRIBUTION_RECIPIENTS {
+            return Err(StdError::generic_err("too many distribution recipients"));
+        }
+
+        let mut valid_recipients = vec![];
+        for share in recipients {
+            let recipient = deps.api.addr_validate(&share.recipient)?;
+
+            if share.weight.is_zero() {
+                return Err(StdError::generic_err("recipient weight cannot be zero"));
+            }
+
+            if valid_recipients.iter().any(|(addr, _)| *addr == recipient) {
+                return Err(StdError::generic_err("duplicate distribution recipient"));
+            }
+
+            valid_recipients.push((recipient, share.weight));
+        }
+
+        distribution.recipients = valid_recipients;
+    }
+
+    // change the minimum time between distributions
+    if let Some(interval) = interval {
+        distribution.interval = interval;
+    }
+
+    store_distribution(deps.storage, &distribution)?;
+
+    Ok(Response::default().add_attribute("action", "update_distribution"))
+}
+
+pub fn set_min_reserve(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // validate the token and check that it is in the token list
+    let valid_token = validate_funds(deps.as_ref(), token)?;
+    if !is_token(deps.storage, valid_token.clone()) {
+        return Err(StdError::generic_err("This token is not supported"));
+    };
+
+    store_min_reserve(deps.storage, &valid_token, &amount)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "set_min_reserve"),
+        ("token", &valid_token.to_string()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+pub fn distribute(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let distribution = read_distribution(deps.storage)?;
+
+    if distribution.recipients.is_empty() {
+        return Err(StdError::generic_err("no distribution recipients set"));
+    }
+
+    // distributions can happen at most once per interval
+    let last_distribution = read_last_distribution(deps.storage)?;
+    if last_distribution.time != 0
+        && env.block.time.seconds() < last_distribution.time + distribution.interval
+    {
+        return Err(StdError::generic_err(
+            "distribution interval has not elapsed",
+        ));
+    }
+
+    let total_weight = distribution
+        .recipients
+        .iter()
+        .try_fold(Uint128::zero(), |acc, (_, weight)| acc.checked_add(*weight))?;
+
+    let mut msgs = vec![];
+    let mut distributed = vec![];
+    for token in read_token_list(deps.storage, TOKEN_LIMIT)
+        .unwrap_or_default()
+        .into_iter()
+    {
+        // only the fees that belong to the protocol above the reserve are distributed
+        let mut reward_state = sync_reward_state(deps.as_ref(), &env, &token)?;
+        let min_reserve = read_min_reserve(deps.storage, &token)?;
+        let amount = reward_state.last_balance.saturating_sub(min_reserve);
+
+        if amount.is_zero() {
+            continue;
+        }
+
+        // the last recipient takes any rounding remainder
+        let mut remaining = amount;
+        for (index, (recipient, weight)) in distribution.recipients.iter().enumerate() {
+            let share = if index == distribution.recipients.len() - 1 {
+                remaining
+            } else {
+                amount.multiply_ratio(*weight, total_weight)
+            };
+            remaining = remaining.checked_sub(share)?;
+
+            if !share.is_zero() {
+                msgs.push(token.into_msg(recipient.to_string(), share, None)?);
+            }
+        }
+
+        reward_state.last_balance = reward_state.last_balance.checked_sub(amount)?;
+        store_reward_state(deps.storage, &token, &reward_state)?;
+
+        distributed.push((token, amount));
+    }
+
+    if distributed.is_empty() {
+        return Err(StdError::generic_err("nothing to distribute"));
+    }
+
+    store_last_distribution(
+        deps.storage,
+        &LastDistribution {
+            time: env.block.time.seconds(),
+            distributed,
+        },
+    )?;
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "distribute"))
 }
@@ -3,6 +3,7 @@ mod error;
 mod handle;
 mod query;
 mod state;
+mod utils;
 
 #[cfg(test)]
 mod testing;
@@ -1,20 +1,26 @@
-use cosmwasm_std::{Deps, StdError, StdResult};
+use cosmwasm_std::{Deps, Env, StdError, StdResult};
 use margined_common::validate::validate_eligible_collateral as validate_funds;
 use margined_perp::margined_fee_pool::{
-    AllTokenResponse, ConfigResponse, OwnerResponse, TokenLengthResponse, TokenResponse,
+    AllTokenResponse, ConfigResponse, DistributionResponse, LastDistributionResponse,
+    OwnerResponse, PendingRewardsResponse, RewardResponse, StakingStateResponse,
+    TokenLengthResponse, TokenResponse,
 };
 
 use crate::{
     contract::OWNER,
-    state::{is_token, read_token_list, TOKEN_LIMIT},
+    state::{
+        is_token, read_config, read_distribution, read_last_distribution, read_min_reserve,
+        read_staker, read_token_list, read_total_staked, TOKEN_LIMIT,
+    },
+    utils::{settle_rewards, sync_reward_state},
 };
 
 const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
 const MAX_PAGINATION_LIMIT: u32 = TOKEN_LIMIT as u32;
 
 /// Queries contract config
-pub fn query_config(_: Deps) -> StdResult<ConfigResponse> {
-    Ok(ConfigResponse {})
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    read_config(deps.storage)
 }
 
 /// Queries contract owner from the admin
@@ -60,3 +66,67 @@ pub fn query_token_list_length(deps: Deps) -> StdResult<TokenLengthResponse> {
         length: list_length,
     })
 }
+
+/// Queries the total amount of governance tokens staked
+pub fn query_staking_state(deps: Deps) -> StdResult<StakingStateResponse> {
+    Ok(StakingStateResponse {
+        total_staked: read_total_staked(deps.storage)?,
+    })
+}
+
+/// Queries the stake of a staker and the rewards they can claim
+pub fn query_pending_rewards(
+    deps: Deps,
+    env: Env,
+    staker: String,
+) -> StdResult<PendingRewardsResponse> {
+    let staker = deps.api.addr_validate(&staker)?;
+    let mut info = read_staker(deps.storage, &staker)?;
+
+    // settle against the state as it would be after syncing
+    for token in read_token_list(deps.storage, TOKEN_LIMIT)
+        .unwrap_or_default()
+        .iter()
+    {
+        let reward_state = sync_reward_state(deps, &env, token)?;
+        settle_rewards(&mut info, token, &reward_state)?;
+    }
+
+    Ok(PendingRewardsResponse {
+        staker,
+        staked: info.staked,
+        rewards: info
+            .rewards
+            .into_iter()
+            .map(|reward| RewardResponse {
+                token: reward.token,
+                amount: reward.pending,
+            })
+            .collect(),
+    })
+}
+
+/// Queries the distribution recipients, interval and the reserve kept of each token
+pub fn query_distribution(deps: Deps) -> StdResult<DistributionResponse> {
+    let distribution = read_distribution(deps.storage)?;
+
+    let min_reserves = read_token_list(deps.storage, TOKEN_LIMIT)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|token| {
+            let amount = read_min_reserve(deps.storage, &token)?;
+            Ok((token, amount))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(DistributionResponse {
+        recipients: distribution.recipients,
+        interval: distribution.interval,
+        min_reserves,
+    })
+}
+
+/// Queries the time and amounts of the last distribution
+pub fn query_last_distribution(deps: Deps) -> StdResult<LastDistributionResponse> {
+    read_last_distribution(deps.storage)
+}
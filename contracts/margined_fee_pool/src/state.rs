@@ -1,18 +1,36 @@
-use cosmwasm_std::{from_slice, to_vec, StdError, StdResult, Storage};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_slice, to_vec, Addr, StdError, StdResult, Storage, Uint128};
 use margined_common::asset::AssetInfo;
-use margined_perp::margined_fee_pool::ConfigResponse;
+use margined_perp::margined_fee_pool::{ConfigResponse, LastDistributionResponse};
 
 pub static KEY_CONFIG: &[u8] = b"config";
 pub const TOKEN_LIST: &[u8] = b"token-list";
 pub const TOKEN_LIMIT: usize = 3usize;
+pub static KEY_TOTAL_STAKED: &[u8] = b"total-staked";
+static PREFIX_REWARD_STATE: &[u8] = b"reward-state";
+static PREFIX_STAKER: &[u8] = b"staker";
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000u128;
+pub static KEY_DISTRIBUTION: &[u8] = b"distribution";
+pub static KEY_LAST_DISTRIBUTION: &[u8] = b"last-distribution";
+static PREFIX_MIN_RESERVE: &[u8] = b"min-reserve";
+pub const MAX_DISTRIBUTION_RECIPIENTS: usize = 10usize;
+pub const BPS_DENOMINATOR: u64 = 10_000u64;
 
 pub type Config = ConfigResponse;
+pub type LastDistribution = LastDistributionResponse;
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
     storage.set(KEY_CONFIG, &to_vec(config)?);
     Ok(())
 }
 
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    match storage.get(KEY_CONFIG) {
+        Some(data) => from_slice(&data),
+        None => Err(StdError::generic_err("Config not found")),
+    }
+}
+
 // function checks if an addr is already added and adds it if not
 // We also check that we have not reached the limit of tokens here
 pub fn save_token(storage: &mut dyn Storage, input: AssetInfo) -> StdResult<()> {
@@ -85,3 +103,125 @@ pub fn remove_token(storage: &mut dyn Storage, token: AssetInfo) -> StdResult<()
     storage.set(TOKEN_LIST, &to_vec(&token_list)?);
     Ok(())
 }
+
+pub fn store_total_staked(storage: &mut dyn Storage, total_staked: &Uint128) -> StdResult<()> {
+    storage.set(KEY_TOTAL_STAKED, &to_vec(total_staked)?);
+    Ok(())
+}
+
+pub fn read_total_staked(storage: &dyn Storage) -> StdResult<Uint128> {
+    match storage.get(KEY_TOTAL_STAKED) {
+        Some(data) => from_slice(&data),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct RewardState {
+    pub reward_per_share: Uint128,  // scaled by REWARD_PRECISION
+    pub accounted_balance: Uint128, // rewards allocated to stakers but not yet claimed
+    pub last_balance: Uint128,      // unallocated balance at the last sync
+}
+
+pub fn store_reward_state(
+    storage: &mut dyn Storage,
+    token: &AssetInfo,
+    state: &RewardState,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_REWARD_STATE, token.as_bytes()].concat(),
+        &to_vec(state)?,
+    );
+    Ok(())
+}
+
+pub fn read_reward_state(storage: &dyn Storage, token: &AssetInfo) -> StdResult<RewardState> {
+    match storage.get(&[PREFIX_REWARD_STATE, token.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(RewardState::default()),
+    }
+}
+
+#[cw_serde]
+pub struct StakerReward {
+    pub token: AssetInfo,
+    pub reward_per_share_paid: Uint128,
+    pub pending: Uint128,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Staker {
+    pub staked: Uint128,
+    pub rewards: Vec<StakerReward>,
+}
+
+pub fn store_staker(storage: &mut dyn Storage, staker: &Addr, info: &Staker) -> StdResult<()> {
+    storage.set(&[PREFIX_STAKER, staker.as_bytes()].concat(), &to_vec(info)?);
+    Ok(())
+}
+
+pub fn remove_staker(storage: &mut dyn Storage, staker: &Addr) {
+    storage.remove(&[PREFIX_STAKER, staker.as_bytes()].concat())
+}
+
+pub fn read_staker(storage: &dyn Storage, staker: &Addr) -> StdResult<Staker> {
+    match storage.get(&[PREFIX_STAKER, staker.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Staker::default()),
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Distribution {
+    pub recipients: Vec<(Addr, Uint128)>, // recipients and their weights
+    pub interval: u64,
+}
+
+pub fn store_distribution(storage: &mut dyn Storage, distribution: &Distribution) -> StdResult<()> {
+    storage.set(KEY_DISTRIBUTION, &to_vec(distribution)?);
+    Ok(())
+}
+
+pub fn read_distribution(storage: &dyn Storage) -> StdResult<Distribution> {
+    match storage.get(KEY_DISTRIBUTION) {
+        Some(data) => from_slice(&data),
+        None => Ok(Distribution::default()),
+    }
+}
+
+pub fn store_last_distribution(
+    storage: &mut dyn Storage,
+    last_distribution: &LastDistribution,
+) -> StdResult<()> {
+    storage.set(KEY_LAST_DISTRIBUTION, &to_vec(last_distribution)?);
+    Ok(())
+}
+
+pub fn read_last_distribution(storage: &dyn Storage) -> StdResult<LastDistribution> {
+    match storage.get(KEY_LAST_DISTRIBUTION) {
+        Some(data) => from_slice(&data),
+        None => Ok(LastDistribution::default()),
+    }
+}
+
+pub fn store_min_reserve(
+    storage: &mut dyn Storage,
+    token: &AssetInfo,
+    amount: &Uint128,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_MIN_RESERVE, token.as_bytes()].concat(),
+        &to_vec(amount)?,
+    );
+    Ok(())
+}
+
+pub fn read_min_reserve(storage: &dyn Storage, token: &AssetInfo) -> StdResult<Uint128> {
+    match storage.get(&[PREFIX_MIN_RESERVE, token.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Uint128::zero()),
+    }
+}
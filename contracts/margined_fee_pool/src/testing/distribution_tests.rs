@@ -0,0 +1,218 @@
+use crate::testing::new_simple_scenario;
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, StdError, Uint128, WasmMsg};
+use cw20::{Cw20Contract, Cw20ExecuteMsg};
+use margined_common::asset::AssetInfo;
+use margined_perp::margined_fee_pool::DistributionShare;
+use margined_utils::cw_multi_test::{App, Executor};
+use margined_utils::testing::SimpleScenario;
+
+// mints usdc to the fee pool as if it was paid in fees
+fn pay_fees(router: &mut App, owner: &Addr, usdc: &Cw20Contract, fee_pool: &Addr, amount: u128) {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: usdc.addr().to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: fee_pool.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+    });
+    router.execute(owner.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_distribute_by_weight() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        carol,
+        david,
+        fee_pool,
+        usdc,
+        ..
+    } = new_simple_scenario();
+
+    let msg = fee_pool.add_token(usdc.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // nothing can be distributed before recipients are set
+    let msg = fee_pool.distribute().unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "no distribution recipients set".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = fee_pool
+        .update_distribution(
+            Some(vec![
+                DistributionShare {
+                    recipient: carol.to_string(),
+                    weight: Uint128::from(3u128),
+                },
+                DistributionShare {
+                    recipient: david.to_string(),
+                    weight: Uint128::from(1u128),
+                },
+            ]),
+            Some(3_600u64),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = fee_pool
+        .set_min_reserve(usdc.addr().to_string(), Uint128::from(100u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let distribution = fee_pool.distribution(&router.wrap()).unwrap();
+    assert_eq!(
+        distribution.recipients,
+        vec![
+            (carol.clone(), Uint128::from(3u128)),
+            (david.clone(), Uint128::from(1u128)),
+        ]
+    );
+    assert_eq!(distribution.interval, 3_600u64);
+    assert_eq!(
+        distribution.min_reserves,
+        vec![(
+            AssetInfo::Token {
+                contract_addr: usdc.addr()
+            },
+            Uint128::from(100u128)
+        )]
+    );
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 500u128);
+
+    let carol_balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    let david_balance = usdc.balance(&router.wrap(), david.clone()).unwrap();
+
+    // anyone can trigger a distribution
+    let msg = fee_pool.distribute().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    assert_eq!(balance, carol_balance + Uint128::from(300u128));
+    let balance = usdc.balance(&router.wrap(), david.clone()).unwrap();
+    assert_eq!(balance, david_balance + Uint128::from(100u128));
+    let balance = usdc.balance(&router.wrap(), fee_pool.addr()).unwrap();
+    assert_eq!(balance, Uint128::from(100u128));
+
+    let last = fee_pool.last_distribution(&router.wrap()).unwrap();
+    assert_eq!(last.time, router.block_info().time.seconds());
+    assert_eq!(
+        last.distributed,
+        vec![(
+            AssetInfo::Token {
+                contract_addr: usdc.addr()
+            },
+            Uint128::from(400u128)
+        )]
+    );
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 100u128);
+
+    let msg = fee_pool.distribute().unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "distribution interval has not elapsed".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600);
+        block.height += 1;
+    });
+
+    // the remainder is split with the last recipient taking the dust
+    let msg = fee_pool
+        .set_min_reserve(usdc.addr().to_string(), Uint128::from(195u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = fee_pool.distribute().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    assert_eq!(balance, carol_balance + Uint128::from(303u128));
+    let balance = usdc.balance(&router.wrap(), david).unwrap();
+    assert_eq!(balance, david_balance + Uint128::from(102u128));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600);
+        block.height += 1;
+    });
+
+    // only the reserve is left
+    let msg = fee_pool.distribute().unwrap();
+    let err = router.execute(alice, msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "nothing to distribute".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_update_distribution_validation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        carol,
+        fee_pool,
+        ..
+    } = new_simple_scenario();
+
+    let share = DistributionShare {
+        recipient: carol.to_string(),
+        weight: Uint128::from(1u128),
+    };
+
+    let msg = fee_pool
+        .update_distribution(Some(vec![share.clone()]), None)
+        .unwrap();
+    let err = router.execute(alice, msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "unauthorized".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = fee_pool
+        .update_distribution(Some(vec![share.clone(), share]), None)
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "duplicate distribution recipient".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = fee_pool
+        .update_distribution(
+            Some(vec![DistributionShare {
+                recipient: carol.to_string(),
+                weight: Uint128::zero(),
+            }]),
+            None,
+        )
+        .unwrap();
+    let err = router.execute(owner, msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "recipient weight cannot be zero".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+}
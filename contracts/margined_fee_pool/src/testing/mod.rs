@@ -1,3 +1,5 @@
+mod distribution_tests;
+mod staking_tests;
 mod tests;
 
 use margined_utils::{
@@ -0,0 +1,334 @@
+use crate::testing::new_simple_scenario;
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, StdError, Uint128, WasmMsg};
+use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg};
+use margined_common::asset::AssetInfo;
+use margined_perp::margined_fee_pool::DistributionShare;
+use margined_utils::create_entry_points_testing;
+use margined_utils::cw_multi_test::{App, Executor};
+use margined_utils::testing::SimpleScenario;
+
+// instantiates a governance token and gives the stakers an allowance for the fee pool
+fn setup_governance_token(
+    router: &mut App,
+    owner: &Addr,
+    fee_pool: &Addr,
+    stakers: &[(&Addr, u128)],
+) -> Cw20Contract {
+    let code_id = router.store_code(Box::new(create_entry_points_testing!(cw20_base)));
+    let token_addr = router
+        .instantiate_contract(
+            code_id,
+            owner.clone(),
+            &cw20_base::msg::InstantiateMsg {
+                name: "Margined".to_string(),
+                symbol: "MRG".to_string(),
+                decimals: 6,
+                initial_balances: stakers
+                    .iter()
+                    .map(|(address, amount)| Cw20Coin {
+                        address: address.to_string(),
+                        amount: Uint128::from(*amount),
+                    })
+                    .collect(),
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "governance",
+            None,
+        )
+        .unwrap();
+
+    for (address, amount) in stakers {
+        router
+            .execute_contract(
+                (*address).clone(),
+                token_addr.clone(),
+                &Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: fee_pool.to_string(),
+                    amount: Uint128::from(*amount),
+                    expires: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    Cw20Contract(token_addr)
+}
+
+// mints usdc to the fee pool as if it was paid in fees
+fn pay_fees(router: &mut App, owner: &Addr, usdc: &Cw20Contract, fee_pool: &Addr, amount: u128) {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: usdc.addr().to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: fee_pool.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+    });
+    router.execute(owner.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_stakers_earn_fees_pro_rata() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        fee_pool,
+        usdc,
+        ..
+    } = new_simple_scenario();
+
+    let governance = setup_governance_token(
+        &mut router,
+        &owner,
+        &fee_pool.addr(),
+        &[(&alice, 1_000u128), (&bob, 3_000u128)],
+    );
+
+    let msg = fee_pool.add_token(usdc.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // staking is not possible before the governance token is set
+    let msg = fee_pool.stake(Uint128::from(1_000u128)).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "staking token is not set".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = fee_pool
+        .update_config(Some(governance.addr().to_string()), None)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // fees received before anyone stakes are kept by the protocol
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 50u128);
+
+    let msg = fee_pool.stake(Uint128::from(1_000u128)).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 100u128);
+
+    let msg = fee_pool.stake(Uint128::from(3_000u128)).unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 400u128);
+
+    let state = fee_pool.staking_state(&router.wrap()).unwrap();
+    assert_eq!(state.total_staked, Uint128::from(4_000u128));
+
+    let rewards = fee_pool
+        .pending_rewards(&router.wrap(), alice.to_string())
+        .unwrap();
+    assert_eq!(rewards.staked, Uint128::from(1_000u128));
+    assert_eq!(
+        rewards.rewards[0].token,
+        AssetInfo::Token {
+            contract_addr: usdc.addr()
+        }
+    );
+    assert_eq!(rewards.rewards[0].amount, Uint128::from(200u128));
+
+    let rewards = fee_pool
+        .pending_rewards(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(rewards.rewards[0].amount, Uint128::from(300u128));
+
+    // the owner can only send the fees that belong to the protocol
+    let msg = fee_pool
+        .send_token(
+            usdc.addr().to_string(),
+            Uint128::from(51u128),
+            owner.to_string(),
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Insufficient funds".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // the governance token cannot be changed while tokens are staked
+    let msg = fee_pool
+        .update_config(Some(usdc.addr().to_string()), None)
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "cannot change staking token while tokens are staked".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+
+    let msg = fee_pool.claim().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+    assert_eq!(balance, alice_balance + Uint128::from(200u128));
+
+    let msg = fee_pool.claim().unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "no rewards to claim".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // bob unstakes, his rewards are kept until he claims them
+    let msg = fee_pool.unstake(Uint128::from(3_001u128)).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "insufficient staked amount".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = fee_pool.unstake(Uint128::from(3_000u128)).unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let balance = governance.balance(&router.wrap(), bob.clone()).unwrap();
+    assert_eq!(balance, Uint128::from(3_000u128));
+
+    let rewards = fee_pool
+        .pending_rewards(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(rewards.staked, Uint128::zero());
+    assert_eq!(rewards.rewards[0].amount, Uint128::from(300u128));
+
+    let bob_balance = usdc.balance(&router.wrap(), bob.clone()).unwrap();
+
+    let msg = fee_pool.claim().unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), bob.clone()).unwrap();
+    assert_eq!(balance, bob_balance + Uint128::from(300u128));
+
+    // the protocol's fees are still available to the owner
+    let msg = fee_pool
+        .send_token(
+            usdc.addr().to_string(),
+            Uint128::from(50u128),
+            owner.to_string(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), fee_pool.addr()).unwrap();
+    assert_eq!(balance, Uint128::zero());
+}
+
+#[test]
+fn test_protocol_share_is_distributed_while_staked() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        carol,
+        fee_pool,
+        usdc,
+        ..
+    } = new_simple_scenario();
+
+    let governance = setup_governance_token(
+        &mut router,
+        &owner,
+        &fee_pool.addr(),
+        &[(&alice, 1_000u128)],
+    );
+
+    let msg = fee_pool.add_token(usdc.addr().to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = fee_pool
+        .update_config(Some(governance.addr().to_string()), Some(10_001u64))
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "staker share cannot exceed 10000 bps".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // the stakers receive 60% of the fees and the protocol keeps the rest
+    let msg = fee_pool
+        .update_config(Some(governance.addr().to_string()), Some(6_000u64))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = fee_pool.config(&router.wrap()).unwrap();
+    assert_eq!(config.staker_share_bps, 6_000u64);
+
+    let msg = fee_pool
+        .update_distribution(
+            Some(vec![DistributionShare {
+                recipient: carol.to_string(),
+                weight: Uint128::from(1u128),
+            }]),
+            Some(3_600u64),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = fee_pool.stake(Uint128::from(1_000u128)).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 500u128);
+
+    let rewards = fee_pool
+        .pending_rewards(&router.wrap(), alice.to_string())
+        .unwrap();
+    assert_eq!(rewards.rewards[0].amount, Uint128::from(300u128));
+
+    let carol_balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+
+    let msg = fee_pool.distribute().unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), carol.clone()).unwrap();
+    assert_eq!(balance, carol_balance + Uint128::from(200u128));
+
+    // fees received before the share changes are split with the previous share
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 100u128);
+
+    let msg = fee_pool.update_config(None, Some(10_000u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    pay_fees(&mut router, &owner, &usdc, &fee_pool.addr(), 100u128);
+
+    let alice_balance = usdc.balance(&router.wrap(), alice.clone()).unwrap();
+
+    let msg = fee_pool.claim().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), alice).unwrap();
+    assert_eq!(balance, alice_balance + Uint128::from(460u128));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600);
+        block.height += 1;
+    });
+
+    let msg = fee_pool.distribute().unwrap();
+    router.execute(bob, msg).unwrap();
+
+    let balance = usdc.balance(&router.wrap(), carol).unwrap();
+    assert_eq!(balance, carol_balance + Uint128::from(240u128));
+
+    let balance = usdc.balance(&router.wrap(), fee_pool.addr()).unwrap();
+    assert_eq!(balance, Uint128::zero());
+}
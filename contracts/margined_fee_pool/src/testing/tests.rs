@@ -23,7 +23,13 @@ fn test_instantiation() {
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!(config, ConfigResponse {});
+    assert_eq!(
+        config,
+        ConfigResponse {
+            staking_token: None,
+            staker_share_bps: 10_000u64,
+        }
+    );
 }
 
 #[test]
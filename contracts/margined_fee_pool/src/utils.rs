@@ -0,0 +1,109 @@
+use cosmwasm_std::{Deps, DepsMut, Env, StdResult, Uint128};
+use margined_common::asset::AssetInfo;
+
+use crate::state::{
+    read_config, read_reward_state, read_token_list, read_total_staked, store_reward_state,
+    RewardState, Staker, StakerReward, BPS_DENOMINATOR, REWARD_PRECISION, TOKEN_LIMIT,
+};
+
+/// Queries the balance of a token that is neither staked nor allocated to the stakers
+pub fn unallocated_balance(
+    deps: Deps,
+    env: &Env,
+    token: &AssetInfo,
+    reward_state: &RewardState,
+) -> StdResult<Uint128> {
+    let mut balance = token.query_balance(&deps.querier, env.contract.address.clone())?;
+
+    // the staked governance tokens are not fees
+    if let Some(staking_token) = read_config(deps.storage)?.staking_token {
+        if token.equal(&AssetInfo::Token {
+            contract_addr: staking_token,
+        }) {
+            balance = balance.saturating_sub(read_total_staked(deps.storage)?);
+        }
+    }
+
+    Ok(balance.saturating_sub(reward_state.accounted_balance))
+}
+
+/// Splits the fees received since the last sync between the protocol and the stakers,
+/// fees received while nothing is staked are kept by the protocol
+pub fn sync_reward_state(deps: Deps, env: &Env, token: &AssetInfo) -> StdResult<RewardState> {
+    let config = read_config(deps.storage)?;
+    let mut reward_state = read_reward_state(deps.storage, token)?;
+    let total_staked = read_total_staked(deps.storage)?;
+    let balance = unallocated_balance(deps, env, token, &reward_state)?;
+
+    if !total_staked.is_zero() && balance > reward_state.last_balance {
+        let fees = balance.checked_sub(reward_state.last_balance)?;
+        let staker_fees = fees.multiply_ratio(config.staker_share_bps, BPS_DENOMINATOR);
+        let increment = staker_fees.multiply_ratio(REWARD_PRECISION, total_staked);
+
+        // any rounding dust stays unallocated and is picked up by the next sync
+        let allocated = increment.multiply_ratio(total_staked, REWARD_PRECISION);
+
+        // the protocol's share is accounted before the stakers' share is allocated
+        reward_state.last_balance = reward_state
+            .last_balance
+            .checked_add(fees.checked_sub(staker_fees)?)?;
+        reward_state.reward_per_share = reward_state.reward_per_share.checked_add(increment)?;
+        reward_state.accounted_balance = reward_state.accounted_balance.checked_add(allocated)?;
+    } else {
+        reward_state.last_balance = balance;
+    }
+
+    Ok(reward_state)
+}
+
+/// Credits the staker with the rewards earned since they were last settled
+pub fn settle_rewards(
+    staker: &mut Staker,
+    token: &AssetInfo,
+    reward_state: &RewardState,
+) -> StdResult<()> {
+    let staked = staker.staked;
+
+    let index = match staker.rewards.iter().position(|r| r.token.equal(token)) {
+        Some(index) => index,
+        None => {
+            // a staker without stake has not earned anything up to now
+            let reward_per_share_paid = match staked.is_zero() {
+                true => reward_state.reward_per_share,
+                false => Uint128::zero(),
+            };
+            staker.rewards.push(StakerReward {
+                token: token.clone(),
+                reward_per_share_paid,
+                pending: Uint128::zero(),
+            });
+            staker.rewards.len() - 1
+        }
+    };
+
+    let reward = &mut staker.rewards[index];
+    let earned = staked.multiply_ratio(
+        reward_state
+            .reward_per_share
+            .checked_sub(reward.reward_per_share_paid)?,
+        REWARD_PRECISION,
+    );
+
+    reward.pending = reward.pending.checked_add(earned)?;
+    reward.reward_per_share_paid = reward_state.reward_per_share;
+
+    Ok(())
+}
+
+/// Syncs every supported token and settles the rewards of the staker
+pub fn update_rewards(deps: DepsMut, env: &Env, staker: &mut Staker) -> StdResult<()> {
+    let tokens = read_token_list(deps.storage, TOKEN_LIMIT).unwrap_or_default();
+
+    for token in tokens.iter() {
+        let reward_state = sync_reward_state(deps.as_ref(), env, token)?;
+        store_reward_state(deps.storage, token, &reward_state)?;
+        settle_rewards(staker, token, &reward_state)?;
+    }
+
+    Ok(())
+}
@@ -1,10 +1,15 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
 use crate::{
-    handle::{add_vamm, remove_vamm, shutdown_all_vamm, update_owner, withdraw, withdraw_fund},
+    handle::{
+        add_vamm, cancel_withdrawal, claim_unstaked, credit_market_fund, execute_withdrawal,
+        propose_withdrawal, remove_vamm, shutdown_all_vamm, stake, unstake, update_config,
+        update_owner, withdraw,
+    },
     query::{
-        query_all_vamm, query_config, query_is_vamm, query_owner, query_status_all_vamm,
-        query_vamm_status,
+        query_all_market_funds, query_all_vamm, query_config, query_is_vamm, query_market_fund,
+        query_owner, query_pending_withdrawals, query_staker, query_staking_state,
+        query_status_all_vamm, query_vamm_status,
     },
     state::{store_config, Config},
 };
@@ -13,7 +18,10 @@ use cosmwasm_std::{
 };
 use cw2::set_contract_version;
 use cw_controllers::Admin;
-use margined_perp::margined_insurance_fund::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use margined_perp::margined_insurance_fund::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, DEFAULT_UNSTAKE_COOLDOWN,
+    DEFAULT_WITHDRAWAL_DELAY,
+};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "crates.io:margined-insurance-fund";
@@ -21,6 +29,8 @@ const CONTRACT_NAME: &str = "crates.io:margined-insurance-fund";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Owner admin
 pub const OWNER: Admin = Admin::new("owner");
+/// Shortest time a proposed withdrawal can be timelocked for
+pub const MIN_WITHDRAWAL_DELAY: u64 = 86_400;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -33,6 +43,9 @@ pub fn instantiate(
 
     let config = Config {
         engine: deps.api.addr_validate(&msg.engine)?,
+        unstake_cooldown: DEFAULT_UNSTAKE_COOLDOWN,
+        shared_pool_fallback: true,
+        withdrawal_delay: DEFAULT_WITHDRAWAL_DELAY,
     };
 
     store_config(deps.storage, &config)?;
@@ -48,14 +61,41 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
         ExecuteMsg::AddVamm { vamm } => add_vamm(deps, info, vamm),
         ExecuteMsg::RemoveVamm { vamm } => remove_vamm(deps, info, vamm),
-        ExecuteMsg::Withdraw { token, amount } => withdraw(deps, info, token, amount),
+        ExecuteMsg::Withdraw {
+            token,
+            amount,
+            vamm,
+        } => withdraw(deps, env, info, token, amount, vamm),
         ExecuteMsg::ShutdownVamms {} => shutdown_all_vamm(deps, env, info),
-        ExecuteMsg::WithdrawFund { token, amount } => withdraw_fund(deps, info, token, amount),
+        ExecuteMsg::ProposeWithdrawal {
+            token,
+            amount,
+            recipient,
+        } => propose_withdrawal(deps, env, info, token, amount, recipient),
+        ExecuteMsg::ExecuteWithdrawal { id } => execute_withdrawal(deps, env, info, id),
+        ExecuteMsg::CancelWithdrawal { id } => cancel_withdrawal(deps, info, id),
+        ExecuteMsg::UpdateConfig {
+            unstake_cooldown,
+            shared_pool_fallback,
+            withdrawal_delay,
+        } => update_config(
+            deps,
+            info,
+            unstake_cooldown,
+            shared_pool_fallback,
+            withdrawal_delay,
+        ),
+        ExecuteMsg::Stake { amount } => stake(deps, env, info, amount),
+        ExecuteMsg::Unstake { shares } => unstake(deps, env, info, shares),
+        ExecuteMsg::ClaimUnstaked {} => claim_unstaked(deps, env, info),
+        ExecuteMsg::CreditMarketFund { vamm, amount } => {
+            credit_market_fund(deps, info, vamm, amount)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
@@ -63,6 +103,15 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::GetAllVamm { limit } => to_binary(&query_all_vamm(deps, limit)?),
         QueryMsg::GetVammStatus { vamm } => to_binary(&query_vamm_status(deps, vamm)?),
         QueryMsg::GetAllVammStatus { limit } => to_binary(&query_status_all_vamm(deps, limit)?),
+        QueryMsg::GetStakingState {} => to_binary(&query_staking_state(deps, env)?),
+        QueryMsg::GetStaker { staker } => to_binary(&query_staker(deps, env, staker)?),
+        QueryMsg::GetMarketFund { vamm } => to_binary(&query_market_fund(deps, vamm)?),
+        QueryMsg::GetAllMarketFunds { limit } => {
+            to_binary(&query_all_market_funds(deps, env, limit)?)
+        }
+        QueryMsg::GetPendingWithdrawals { start_after, limit } => {
+            to_binary(&query_pending_withdrawals(deps, start_after, limit)?)
+        }
     }
 }
 
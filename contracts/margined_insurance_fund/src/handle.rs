@@ -1,12 +1,25 @@
 use crate::{
-    contract::OWNER,
+    contract::{MIN_WITHDRAWAL_DELAY, OWNER},
     query::MAX_PAGINATION_LIMIT,
-    state::{read_config, read_vammlist, remove_vamm as remove_amm, save_vamm},
+    state::{
+        decrease_market_fund, increase_market_fund, increase_withdrawal_count, is_vamm,
+        read_config, read_market_fund, read_pending_withdrawals, read_staker, read_staking_state,
+        read_total_market_funds, read_vammlist, remove_market_fund, remove_staker,
+        remove_vamm as remove_amm, save_vamm, store_config, store_pending_withdrawals,
+        store_staker, store_staking_state, Withdrawal,
+    },
+    utils::{
+        debit_market_funds, realize_loss, shared_pool_balance, shares_to_value, staking_balance,
+        staking_token, sync_staking_state,
+    },
 };
 use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
 
-use margined_common::{asset::AssetInfo, messages::wasm_execute};
-use margined_perp::margined_vamm::ExecuteMsg as VammExecuteMessage;
+use margined_common::{
+    asset::{Asset, AssetInfo},
+    messages::wasm_execute,
+};
+use margined_perp::margined_vamm::{ExecuteMsg as VammExecuteMessage, MarketStatus};
 use margined_utils::contracts::helpers::{EngineController, VammController};
 
 pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
@@ -18,6 +31,43 @@ pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResul
         .map_err(|error| StdError::generic_err(error.to_string()))
 }
 
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    unstake_cooldown: Option<u64>,
+    shared_pool_fallback: Option<bool>,
+    withdrawal_delay: Option<u64>,
+) -> StdResult<Response> {
+    let mut config = read_config(deps.storage)?;
+
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // change the time stakers have to wait before their stake is released
+    if let Some(unstake_cooldown) = unstake_cooldown {
+        config.unstake_cooldown = unstake_cooldown;
+    }
+
+    // change whether a market can draw on the shared pool once its sub-fund is empty
+    if let Some(shared_pool_fallback) = shared_pool_fallback {
+        config.shared_pool_fallback = shared_pool_fallback;
+    }
+
+    // change how long proposed withdrawals are timelocked for
+    if let Some(withdrawal_delay) = withdrawal_delay {
+        if withdrawal_delay < MIN_WITHDRAWAL_DELAY {
+            return Err(StdError::generic_err("withdrawal delay is too short"));
+        }
+        config.withdrawal_delay = withdrawal_delay;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
 pub fn add_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<Response> {
     let config = read_config(deps.storage)?;
 
@@ -58,9 +108,15 @@ pub fn remove_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<
     let vamm_valid = deps.api.addr_validate(&vamm)?;
 
     // remove vamm here
-    remove_amm(deps.storage, vamm_valid)?;
+    remove_amm(deps.storage, vamm_valid.clone())?;
 
-    Ok(Response::default().add_attribute("action", "remove_amm"))
+    // no market can draw on the sub-fund anymore so it goes back to the shared pool
+    let released = remove_market_fund(deps.storage, &vamm_valid)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "remove_amm"),
+        ("released", &released.to_string()),
+    ]))
 }
 
 pub fn shutdown_all_vamm(deps: DepsMut, _env: Env, info: MessageInfo) -> StdResult<Response> {
@@ -72,10 +128,17 @@ pub fn shutdown_all_vamm(deps: DepsMut, _env: Env, info: MessageInfo) -> StdResu
     // construct all the shutdown messages
     let keys = read_vammlist(deps.storage, MAX_PAGINATION_LIMIT as usize)?;
 
-    // initialise the submsgs vec
+    // initialise the submsgs vec, active markets go reduce only first so that
+    // positions can still be closed and a second shutdown pauses them
     let mut msgs = vec![];
     for vamm in keys.iter() {
-        let msg = wasm_execute(vamm, &VammExecuteMessage::SetOpen { open: false }, vec![])?;
+        let status = match VammController(vamm.clone()).state(&deps.querier)?.status {
+            MarketStatus::Active => MarketStatus::ReduceOnly,
+            MarketStatus::ReduceOnly => MarketStatus::Paused,
+            _ => continue,
+        };
+
+        let msg = wasm_execute(vamm, &VammExecuteMessage::SetStatus { status }, vec![])?;
         msgs.push(msg);
     }
 
@@ -86,9 +149,11 @@ pub fn shutdown_all_vamm(deps: DepsMut, _env: Env, info: MessageInfo) -> StdResu
 
 pub fn withdraw(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     token: AssetInfo,
     amount: Uint128,
+    vamm: Option<String>,
 ) -> StdResult<Response> {
     let config = read_config(deps.storage)?;
 
@@ -97,6 +162,48 @@ pub fn withdraw(
         return Err(StdError::generic_err("unauthorized"));
     }
 
+    // the market's sub-fund is debited first, the remainder comes out of the shared pool
+    let mut from_shared_pool = amount;
+    let mut market_debit = None;
+    if let Some(vamm) = vamm {
+        let vamm = deps.api.addr_validate(&vamm)?;
+
+        // only registered vamms have a sub-fund that can be charged
+        if !is_vamm(deps.storage, vamm.clone()) {
+            return Err(StdError::generic_err("vAMM is not registered"));
+        }
+
+        let from_market = read_market_fund(deps.storage, &vamm)?.min(amount);
+        from_shared_pool = amount.checked_sub(from_market)?;
+
+        if !from_shared_pool.is_zero() && !config.shared_pool_fallback {
+            return Err(StdError::generic_err("insufficient market insurance funds"));
+        }
+
+        market_debit = Some((vamm, from_market));
+    }
+
+    // the shared pool cannot dip into the sub-funds of other markets
+    if !from_shared_pool.is_zero() && !read_total_market_funds(deps.storage)?.is_zero() {
+        let shared_pool = shared_pool_balance(deps.as_ref(), &env, &token)?;
+        if from_shared_pool > shared_pool {
+            return Err(StdError::generic_err("insufficient shared insurance funds"));
+        }
+    }
+
+    if let Some((vamm, from_market)) = market_debit {
+        decrease_market_fund(deps.storage, &vamm, from_market)?;
+    }
+
+    // stakers absorb their share of the bad debt being covered
+    let mut staking_state = read_staking_state(deps.storage)?;
+    if !staking_state.total_shares.is_zero() && token == staking_token(deps.as_ref(), &config)? {
+        let balance = staking_balance(deps.as_ref(), &env, &token, Uint128::zero())?;
+        sync_staking_state(&mut staking_state, balance)?;
+        realize_loss(&mut staking_state, amount)?;
+        store_staking_state(deps.storage, &staking_state)?;
+    }
+
     // send tokens if native or cw20
     let transfer_msg = token.into_msg(config.engine.to_string(), amount, None)?;
 
@@ -108,24 +215,315 @@ pub fn withdraw(
         ]))
 }
 
-pub fn withdraw_fund(
+pub fn propose_withdrawal(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     token: AssetInfo,
     amount: Uint128,
+    recipient: String,
 ) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
     // check permission
     if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
+    if amount.is_zero() {
+        return Err(StdError::generic_err("withdrawal amount cannot be zero"));
+    }
+
+    // validate address
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    // the withdrawal is public for the length of the delay before it can be executed
+    let withdrawal = Withdrawal {
+        id: increase_withdrawal_count(deps.storage)?,
+        token,
+        amount,
+        recipient,
+        unlock_time: env.block.time.seconds() + config.withdrawal_delay,
+    };
+
+    let mut withdrawals = read_pending_withdrawals(deps.storage)?;
+    withdrawals.push(withdrawal.clone());
+    store_pending_withdrawals(deps.storage, &withdrawals)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "propose_withdrawal"),
+        ("id", &withdrawal.id.to_string()),
+        ("amount", &amount.to_string()),
+        ("recipient", withdrawal.recipient.as_ref()),
+        ("unlock_time", &withdrawal.unlock_time.to_string()),
+    ]))
+}
+
+pub fn execute_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> StdResult<Response> {
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut withdrawals = read_pending_withdrawals(deps.storage)?;
+    let index = withdrawals
+        .iter()
+        .position(|withdrawal| withdrawal.id == id)
+        .ok_or_else(|| StdError::generic_err("withdrawal does not exist"))?;
+    let Withdrawal {
+        token,
+        amount,
+        recipient,
+        unlock_time,
+        ..
+    } = withdrawals.remove(index);
+
+    if env.block.time.seconds() < unlock_time {
+        return Err(StdError::generic_err("withdrawal is still timelocked"));
+    }
+
+    // the owner can only withdraw the collateral that is not allocated to a market
+    if !read_total_market_funds(deps.storage)?.is_zero()
+        && amount > shared_pool_balance(deps.as_ref(), &env, &token)?
+    {
+        return Err(StdError::generic_err("amount exceeds shared pool funds"));
+    }
+
+    // the owner can only withdraw the collateral that is not owned by stakers
+    let mut staking_state = read_staking_state(deps.storage)?;
+    if !staking_state.total_shares.is_zero() {
+        let config = read_config(deps.storage)?;
+        if token == staking_token(deps.as_ref(), &config)? {
+            let balance = staking_balance(deps.as_ref(), &env, &token, Uint128::zero())?;
+            sync_staking_state(&mut staking_state, balance)?;
+
+            let protocol_value = staking_state
+                .last_balance
+                .checked_sub(staking_state.staked_value)?;
+            if amount > protocol_value {
+                return Err(StdError::generic_err("amount exceeds protocol funds"));
+            }
+
+            staking_state.last_balance = staking_state.last_balance.checked_sub(amount)?;
+            store_staking_state(deps.storage, &staking_state)?;
+        }
+    }
+
+    store_pending_withdrawals(deps.storage, &withdrawals)?;
+
     // send tokens if native or cw20
-    let transfer_msg = token.into_msg(info.sender.to_string(), amount, None)?;
+    let transfer_msg = token.into_msg(recipient.to_string(), amount, None)?;
 
     Ok(Response::default()
         .add_message(transfer_msg)
         .add_attributes(vec![
-            ("action", "insurance_withdraw_to_operator"),
+            ("action", "execute_withdrawal"),
+            ("id", &id.to_string()),
             ("amount", &amount.to_string()),
+            ("recipient", recipient.as_ref()),
         ]))
 }
+
+pub fn cancel_withdrawal(deps: DepsMut, info: MessageInfo, id: u64) -> StdResult<Response> {
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut withdrawals = read_pending_withdrawals(deps.storage)?;
+    let index = withdrawals
+        .iter()
+        .position(|withdrawal| withdrawal.id == id)
+        .ok_or_else(|| StdError::generic_err("withdrawal does not exist"))?;
+    withdrawals.remove(index);
+
+    store_pending_withdrawals(deps.storage, &withdrawals)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "cancel_withdrawal"),
+        ("id", &id.to_string()),
+    ]))
+}
+
+pub fn credit_market_fund(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.engine {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // validate address
+    let vamm_valid = deps.api.addr_validate(&vamm)?;
+
+    // only registered vamms have a sub-fund that can be credited
+    if !is_vamm(deps.storage, vamm_valid.clone()) {
+        return Err(StdError::generic_err("vAMM is not registered"));
+    }
+
+    // the engine has already transferred the tokens, this only allocates them to the market
+    increase_market_fund(deps.storage, &vamm_valid, amount)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "credit_market_fund"),
+        ("vamm", vamm_valid.as_ref()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+pub fn stake(deps: DepsMut, env: Env, info: MessageInfo, amount: Uint128) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    let token = staking_token(deps.as_ref(), &config)?;
+
+    if amount.is_zero() {
+        return Err(StdError::generic_err("stake amount cannot be zero"));
+    }
+
+    // native tokens are already in the balance so they are excluded, cw20 are pulled in
+    let mut msgs = vec![];
+    let pending = match &token {
+        AssetInfo::NativeToken { .. } => {
+            Asset {
+                info: token.clone(),
+                amount,
+            }
+            .assert_sent_native_token_balance(&info)?;
+            amount
+        }
+        AssetInfo::Token { .. } => {
+            msgs.push(token.into_msg(
+                env.contract.address.to_string(),
+                amount,
+                Some(info.sender.to_string()),
+            )?);
+            Uint128::zero()
+        }
+    };
+
+    let mut staking_state = read_staking_state(deps.storage)?;
+    let balance = staking_balance(deps.as_ref(), &env, &token, pending)?;
+    sync_staking_state(&mut staking_state, balance)?;
+
+    // shares are minted at the current value of the stakers' pool, once losses have wiped it
+    // out the worthless shares have to be burnt before anyone can stake again
+    let shares = if staking_state.total_shares.is_zero() {
+        amount
+    } else if staking_state.staked_value.is_zero() {
+        return Err(StdError::generic_err(
+            "staked value is zero, existing shares must be unstaked first",
+        ));
+    } else {
+        amount.multiply_ratio(staking_state.total_shares, staking_state.staked_value)
+    };
+
+    if shares.is_zero() {
+        return Err(StdError::generic_err("stake amount too small"));
+    }
+
+    staking_state.total_shares = staking_state.total_shares.checked_add(shares)?;
+    staking_state.staked_value = staking_state.staked_value.checked_add(amount)?;
+    staking_state.last_balance = staking_state.last_balance.checked_add(amount)?;
+    store_staking_state(deps.storage, &staking_state)?;
+
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+    staker.shares = staker.shares.checked_add(shares)?;
+    store_staker(deps.storage, &info.sender, &staker)?;
+
+    Ok(Response::default().add_messages(msgs).add_attributes(vec![
+        ("action", "stake"),
+        ("staker", info.sender.as_ref()),
+        ("amount", &amount.to_string()),
+        ("shares", &shares.to_string()),
+    ]))
+}
+
+pub fn unstake(deps: DepsMut, env: Env, info: MessageInfo, shares: Uint128) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+
+    if shares.is_zero() {
+        return Err(StdError::generic_err("unstake shares cannot be zero"));
+    }
+
+    if staker.shares.checked_sub(staker.unstaking_shares)? < shares {
+        return Err(StdError::generic_err("insufficient shares"));
+    }
+
+    // adding to the shares in cooldown restarts the cooldown
+    staker.unstaking_shares = staker.unstaking_shares.checked_add(shares)?;
+    staker.release_time = env.block.time.seconds() + config.unstake_cooldown;
+    store_staker(deps.storage, &info.sender, &staker)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "unstake"),
+        ("staker", info.sender.as_ref()),
+        ("shares", &shares.to_string()),
+        ("release_time", &staker.release_time.to_string()),
+    ]))
+}
+
+pub fn claim_unstaked(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    let mut staker = read_staker(deps.storage, &info.sender)?;
+
+    if staker.unstaking_shares.is_zero() {
+        return Err(StdError::generic_err("no shares are unstaking"));
+    }
+
+    if env.block.time.seconds() < staker.release_time {
+        return Err(StdError::generic_err("unstake cooldown has not elapsed"));
+    }
+
+    let token = staking_token(deps.as_ref(), &config)?;
+    let mut staking_state = read_staking_state(deps.storage)?;
+    let balance = staking_balance(deps.as_ref(), &env, &token, Uint128::zero())?;
+    sync_staking_state(&mut staking_state, balance)?;
+
+    // shares are burnt at their value after any losses during the cooldown
+    let shares = staker.unstaking_shares;
+    let amount = shares_to_value(&staking_state, shares);
+
+    // the payout is taken from the shared pool and the sub-funds in proportion to their size
+    let total_market_funds = read_total_market_funds(deps.storage)?;
+    if !total_market_funds.is_zero() && !amount.is_zero() {
+        debit_market_funds(
+            deps.storage,
+            amount.multiply_ratio(total_market_funds, balance),
+        )?;
+    }
+
+    staking_state.total_shares = staking_state.total_shares.checked_sub(shares)?;
+    staking_state.staked_value = staking_state.staked_value.checked_sub(amount)?;
+    staking_state.last_balance = staking_state.last_balance.checked_sub(amount)?;
+    store_staking_state(deps.storage, &staking_state)?;
+
+    staker.shares = staker.shares.checked_sub(shares)?;
+    staker.unstaking_shares = Uint128::zero();
+    if staker.shares.is_zero() {
+        remove_staker(deps.storage, &info.sender);
+    } else {
+        store_staker(deps.storage, &info.sender, &staker)?;
+    }
+
+    let mut response = Response::default();
+    if !amount.is_zero() {
+        response = response.add_message(token.into_msg(info.sender.to_string(), amount, None)?);
+    }
+
+    Ok(response.add_attributes(vec![
+        ("action", "claim_unstaked"),
+        ("staker", info.sender.as_ref()),
+        ("shares", &shares.to_string()),
+        ("amount", &amount.to_string()),
+    ]))
+}
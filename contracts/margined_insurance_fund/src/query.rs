@@ -1,13 +1,21 @@
-use cosmwasm_std::{Addr, Deps, StdError, StdResult};
+use cosmwasm_std::{Addr, Deps, Env, StdError, StdResult, Uint128};
 use margined_perp::margined_insurance_fund::{
-    AllVammResponse, AllVammStatusResponse, ConfigResponse, OwnerResponse, VammResponse,
-    VammStatusResponse,
+    AllMarketFundsResponse, AllVammResponse, AllVammStatusResponse, ConfigResponse,
+    MarketFundResponse, OwnerResponse, PendingWithdrawalsResponse, StakerResponse,
+    StakingStateResponse, VammResponse, VammStatusResponse,
 };
+use margined_perp::margined_vamm::MarketStatus;
 use margined_utils::contracts::helpers::VammController;
 
 use crate::{
     contract::OWNER,
-    state::{is_vamm, read_config, read_vammlist},
+    state::{
+        is_vamm, read_config, read_market_fund, read_pending_withdrawals, read_staker,
+        read_staking_state, read_vammlist, StakingState,
+    },
+    utils::{
+        shared_pool_balance, shares_to_value, staking_balance, staking_token, sync_staking_state,
+    },
 };
 
 const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
@@ -57,30 +65,128 @@ pub fn query_vamm_status(deps: Deps, vamm: String) -> StdResult<VammStatusRespon
     let vamm_controller = VammController(vamm_valid);
 
     // query the vamms current status
-    let vamm_bool = vamm_controller.state(&deps.querier)?.open;
+    let vamm_status = vamm_controller.state(&deps.querier)?.status;
 
-    Ok(VammStatusResponse {
-        vamm_status: vamm_bool,
-    })
+    Ok(VammStatusResponse { vamm_status })
 }
 
-/// Queries the status of multiple vAMMs, returning the vAMM address and whether it is on/off
+/// Queries the status of multiple vAMMs, returning the vAMM address and its market status
 pub fn query_status_all_vamm(deps: Deps, limit: Option<u32>) -> StdResult<AllVammStatusResponse> {
     // set the limit for pagination
     let limit = limit
         .unwrap_or(DEFAULT_PAGINATION_LIMIT)
         .min(MAX_PAGINATION_LIMIT) as usize;
 
-    let mut status_list: Vec<(Addr, bool)> = vec![];
+    let mut status_list: Vec<(Addr, MarketStatus)> = vec![];
 
     // iterate through the vamm list and query the status one by one
     for vamm in read_vammlist(deps.storage, limit)? {
         let vamm_controller = VammController(vamm.clone());
-        let vamm_bool = vamm_controller.state(&deps.querier)?.open;
-        status_list.push((vamm, vamm_bool));
+        let vamm_status = vamm_controller.state(&deps.querier)?.status;
+        status_list.push((vamm, vamm_status));
     }
 
     Ok(AllVammStatusResponse {
         vamm_list_status: status_list,
     })
 }
+
+/// Returns the staking state as it would be after syncing with the current balance
+fn synced_staking_state(deps: Deps, env: &Env) -> StdResult<StakingState> {
+    let mut staking_state = read_staking_state(deps.storage)?;
+
+    if !staking_state.total_shares.is_zero() {
+        let config = read_config(deps.storage)?;
+        let token = staking_token(deps, &config)?;
+        let balance = staking_balance(deps, env, &token, Uint128::zero())?;
+        sync_staking_state(&mut staking_state, balance)?;
+    }
+
+    Ok(staking_state)
+}
+
+/// Queries the total shares and how the collateral is split between stakers and protocol
+pub fn query_staking_state(deps: Deps, env: Env) -> StdResult<StakingStateResponse> {
+    let staking_state = synced_staking_state(deps, &env)?;
+
+    Ok(StakingStateResponse {
+        total_shares: staking_state.total_shares,
+        staked_value: staking_state.staked_value,
+        protocol_value: staking_state
+            .last_balance
+            .checked_sub(staking_state.staked_value)?,
+    })
+}
+
+/// Queries the shares of a staker and their current value
+pub fn query_staker(deps: Deps, env: Env, staker: String) -> StdResult<StakerResponse> {
+    let staker = deps.api.addr_validate(&staker)?;
+    let info = read_staker(deps.storage, &staker)?;
+    let staking_state = synced_staking_state(deps, &env)?;
+
+    Ok(StakerResponse {
+        staker,
+        shares: info.shares,
+        value: shares_to_value(&staking_state, info.shares),
+        unstaking_shares: info.unstaking_shares,
+        release_time: info.release_time,
+    })
+}
+
+/// Queries the balance of the sub-fund of the vAMM with given address
+pub fn query_market_fund(deps: Deps, vamm: String) -> StdResult<MarketFundResponse> {
+    // validate address
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    let balance = read_market_fund(deps.storage, &vamm)?;
+
+    Ok(MarketFundResponse { vamm, balance })
+}
+
+/// Queries the sub-fund balances of the stored vAMMs and the balance of the shared pool
+pub fn query_all_market_funds(
+    deps: Deps,
+    env: Env,
+    limit: Option<u32>,
+) -> StdResult<AllMarketFundsResponse> {
+    // set the limit for pagination
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let mut market_funds: Vec<MarketFundResponse> = vec![];
+    for vamm in read_vammlist(deps.storage, limit)? {
+        let balance = read_market_fund(deps.storage, &vamm)?;
+        market_funds.push(MarketFundResponse { vamm, balance });
+    }
+
+    let config = read_config(deps.storage)?;
+    let token = staking_token(deps, &config)?;
+    let shared_pool = shared_pool_balance(deps, &env, &token)?;
+
+    Ok(AllMarketFundsResponse {
+        market_funds,
+        shared_pool,
+    })
+}
+
+/// Queries the withdrawals that have been proposed but not yet executed or cancelled
+pub fn query_pending_withdrawals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingWithdrawalsResponse> {
+    // set the limit for pagination
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    // withdrawals are stored in the order of their ids
+    let withdrawals = read_pending_withdrawals(deps.storage)?
+        .into_iter()
+        .filter(|withdrawal| start_after.map_or(true, |id| withdrawal.id > id))
+        .take(limit)
+        .collect();
+
+    Ok(PendingWithdrawalsResponse { withdrawals })
+}
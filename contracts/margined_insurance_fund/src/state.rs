@@ -1,10 +1,18 @@
-use cosmwasm_std::{from_slice, to_vec, Addr, StdError, StdResult, Storage};
-use margined_perp::margined_insurance_fund::ConfigResponse;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_slice, to_vec, Addr, StdError, StdResult, Storage, Uint128};
+use margined_perp::margined_insurance_fund::{ConfigResponse, WithdrawalResponse};
 
 pub static KEY_CONFIG: &[u8] = b"config";
 pub const VAMM_LIST: &[u8] = b"vamm-list";
+pub static KEY_STAKING_STATE: &[u8] = b"staking-state";
+static PREFIX_STAKER: &[u8] = b"staker";
+pub static KEY_TOTAL_MARKET_FUNDS: &[u8] = b"total-market-funds";
+static PREFIX_MARKET_FUND: &[u8] = b"market-fund";
+pub static KEY_PENDING_WITHDRAWALS: &[u8] = b"pending-withdrawals";
+pub static KEY_WITHDRAWAL_COUNT: &[u8] = b"withdrawal-count";
 
 pub type Config = ConfigResponse;
+pub type Withdrawal = WithdrawalResponse;
 
 // function checks if an addr is already added and adds it if not
 // We also check that we have not reached the limit of vAMMs here
@@ -82,3 +90,131 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
         None => Err(StdError::generic_err("Config not found")),
     }
 }
+
+#[cw_serde]
+#[derive(Default)]
+pub struct StakingState {
+    pub total_shares: Uint128,
+    pub staked_value: Uint128, // collateral owned by the stakers
+    pub last_balance: Uint128, // collateral balance at the last sync
+}
+
+pub fn store_staking_state(storage: &mut dyn Storage, state: &StakingState) -> StdResult<()> {
+    storage.set(KEY_STAKING_STATE, &to_vec(state)?);
+    Ok(())
+}
+
+pub fn read_staking_state(storage: &dyn Storage) -> StdResult<StakingState> {
+    match storage.get(KEY_STAKING_STATE) {
+        Some(data) => from_slice(&data),
+        None => Ok(StakingState::default()),
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Staker {
+    pub shares: Uint128,
+    pub unstaking_shares: Uint128, // shares in cooldown, these still absorb losses
+    pub release_time: u64,
+}
+
+pub fn store_staker(storage: &mut dyn Storage, staker: &Addr, info: &Staker) -> StdResult<()> {
+    storage.set(&[PREFIX_STAKER, staker.as_bytes()].concat(), &to_vec(info)?);
+    Ok(())
+}
+
+pub fn remove_staker(storage: &mut dyn Storage, staker: &Addr) {
+    storage.remove(&[PREFIX_STAKER, staker.as_bytes()].concat())
+}
+
+pub fn read_staker(storage: &dyn Storage, staker: &Addr) -> StdResult<Staker> {
+    match storage.get(&[PREFIX_STAKER, staker.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Staker::default()),
+    }
+}
+
+pub fn read_market_fund(storage: &dyn Storage, vamm: &Addr) -> StdResult<Uint128> {
+    match storage.get(&[PREFIX_MARKET_FUND, vamm.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+pub fn read_total_market_funds(storage: &dyn Storage) -> StdResult<Uint128> {
+    match storage.get(KEY_TOTAL_MARKET_FUNDS) {
+        Some(data) => from_slice(&data),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+// credits the sub-fund of a vAMM, keeping the total of all sub-funds in sync
+pub fn increase_market_fund(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let balance = read_market_fund(storage, vamm)?.checked_add(amount)?;
+    let total = read_total_market_funds(storage)?.checked_add(amount)?;
+
+    storage.set(
+        &[PREFIX_MARKET_FUND, vamm.as_bytes()].concat(),
+        &to_vec(&balance)?,
+    );
+    storage.set(KEY_TOTAL_MARKET_FUNDS, &to_vec(&total)?);
+    Ok(())
+}
+
+// debits the sub-fund of a vAMM, keeping the total of all sub-funds in sync
+pub fn decrease_market_fund(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let balance = read_market_fund(storage, vamm)?.checked_sub(amount)?;
+    let total = read_total_market_funds(storage)?.checked_sub(amount)?;
+
+    storage.set(
+        &[PREFIX_MARKET_FUND, vamm.as_bytes()].concat(),
+        &to_vec(&balance)?,
+    );
+    storage.set(KEY_TOTAL_MARKET_FUNDS, &to_vec(&total)?);
+    Ok(())
+}
+
+// removes the sub-fund of a vAMM, its balance returns to the shared pool
+pub fn remove_market_fund(storage: &mut dyn Storage, vamm: &Addr) -> StdResult<Uint128> {
+    let balance = read_market_fund(storage, vamm)?;
+    let total = read_total_market_funds(storage)?.checked_sub(balance)?;
+
+    storage.remove(&[PREFIX_MARKET_FUND, vamm.as_bytes()].concat());
+    storage.set(KEY_TOTAL_MARKET_FUNDS, &to_vec(&total)?);
+    Ok(balance)
+}
+
+pub fn store_pending_withdrawals(
+    storage: &mut dyn Storage,
+    withdrawals: &[Withdrawal],
+) -> StdResult<()> {
+    storage.set(KEY_PENDING_WITHDRAWALS, &to_vec(withdrawals)?);
+    Ok(())
+}
+
+pub fn read_pending_withdrawals(storage: &dyn Storage) -> StdResult<Vec<Withdrawal>> {
+    match storage.get(KEY_PENDING_WITHDRAWALS) {
+        Some(data) => from_slice(&data),
+        None => Ok(vec![]),
+    }
+}
+
+// increments the withdrawal counter and returns the id of the next withdrawal
+pub fn increase_withdrawal_count(storage: &mut dyn Storage) -> StdResult<u64> {
+    let count: u64 = match storage.get(KEY_WITHDRAWAL_COUNT) {
+        Some(data) => from_slice(&data)?,
+        None => 0u64,
+    };
+
+    storage.set(KEY_WITHDRAWAL_COUNT, &to_vec(&(count + 1))?);
+    Ok(count + 1)
+}
@@ -0,0 +1,370 @@
+use crate::testing::new_shutdown_scenario;
+use cosmwasm_std::{coin, Addr, StdError, Uint128};
+use margined_common::asset::{AssetInfo, NATIVE_DENOM};
+use margined_perp::margined_insurance_fund::ExecuteMsg;
+use margined_utils::cw_multi_test::{BankSudo, Executor, SudoMsg};
+use margined_utils::testing::ShutdownScenario;
+
+#[test]
+fn test_market_funds_are_debited_first() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        vamm1,
+        vamm2,
+        ..
+    } = new_shutdown_scenario();
+
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+    let token = AssetInfo::NativeToken {
+        denom: NATIVE_DENOM.to_string(),
+    };
+
+    for vamm in [&vamm1, &vamm2] {
+        let msg = insurance_fund.add_vamm(vamm.addr().to_string()).unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+    }
+
+    // 1000 in the shared pool, 300 from vamm1 fees and 200 from vamm2 fees
+    router
+        .sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: insurance_fund.addr().to_string(),
+            amount: vec![coin(1_500u128, NATIVE_DENOM)],
+        }))
+        .unwrap();
+
+    // only the engine can credit market funds
+    let msg = insurance_fund
+        .credit_market_fund(vamm1.addr().to_string(), Uint128::from(300u128))
+        .unwrap();
+    let err = router
+        .execute(Addr::unchecked("alice"), msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "unauthorized".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+    router.execute(engine.clone(), msg).unwrap();
+
+    let msg = insurance_fund
+        .credit_market_fund(vamm2.addr().to_string(), Uint128::from(200u128))
+        .unwrap();
+    router.execute(engine.clone(), msg).unwrap();
+
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.market_funds.len(), 2usize);
+    assert_eq!(funds.market_funds[0].vamm, vamm1.addr());
+    assert_eq!(funds.market_funds[0].balance, Uint128::from(300u128));
+    assert_eq!(funds.market_funds[1].balance, Uint128::from(200u128));
+    assert_eq!(funds.shared_pool, Uint128::from(1_000u128));
+
+    // vamm1 sub-fund is emptied and the shared pool covers the rest
+    let msg = ExecuteMsg::Withdraw {
+        token: token.clone(),
+        amount: Uint128::from(400u128),
+        vamm: Some(vamm1.addr().to_string()),
+    };
+    router
+        .execute_contract(engine.clone(), insurance_fund.addr(), &msg, &[])
+        .unwrap();
+
+    let fund = insurance_fund
+        .market_fund(&router.wrap(), vamm1.addr().to_string())
+        .unwrap();
+    assert_eq!(fund.balance, Uint128::zero());
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.shared_pool, Uint128::from(900u128));
+
+    // without the fallback a market can only use its own sub-fund
+    let msg = insurance_fund
+        .update_config(None, Some(false), None)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Withdraw {
+        token: token.clone(),
+        amount: Uint128::from(250u128),
+        vamm: Some(vamm2.addr().to_string()),
+    };
+    let err = router
+        .execute_contract(engine.clone(), insurance_fund.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "insufficient market insurance funds".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = ExecuteMsg::Withdraw {
+        token: token.clone(),
+        amount: Uint128::from(150u128),
+        vamm: Some(vamm2.addr().to_string()),
+    };
+    router
+        .execute_contract(engine.clone(), insurance_fund.addr(), &msg, &[])
+        .unwrap();
+
+    let fund = insurance_fund
+        .market_fund(&router.wrap(), vamm2.addr().to_string())
+        .unwrap();
+    assert_eq!(fund.balance, Uint128::from(50u128));
+
+    // the owner cannot withdraw funds allocated to a market
+    for amount in [901u128, 900u128] {
+        let msg = insurance_fund
+            .propose_withdrawal(token.clone(), Uint128::from(amount), owner.to_string())
+            .unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+    }
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(172_800);
+        block.height += 1;
+    });
+
+    let msg = insurance_fund.execute_withdrawal(1u64).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "amount exceeds shared pool funds".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = insurance_fund.execute_withdrawal(2u64).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // withdrawals not tied to a market cannot touch the sub-funds either
+    let msg = ExecuteMsg::Withdraw {
+        token,
+        amount: Uint128::from(1u128),
+        vamm: None,
+    };
+    let err = router
+        .execute_contract(engine, insurance_fund.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "insufficient shared insurance funds".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let balance = router
+        .wrap()
+        .query_balance(insurance_fund.addr(), NATIVE_DENOM)
+        .unwrap()
+        .amount;
+    assert_eq!(balance, Uint128::from(50u128));
+}
+
+#[test]
+fn test_withdraw_from_unregistered_market() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        vamm1,
+        vamm2,
+        ..
+    } = new_shutdown_scenario();
+
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+
+    let msg = insurance_fund.add_vamm(vamm1.addr().to_string()).unwrap();
+    router.execute(owner, msg).unwrap();
+
+    router
+        .sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: insurance_fund.addr().to_string(),
+            amount: vec![coin(1_000u128, NATIVE_DENOM)],
+        }))
+        .unwrap();
+
+    let msg = ExecuteMsg::Withdraw {
+        token: AssetInfo::NativeToken {
+            denom: NATIVE_DENOM.to_string(),
+        },
+        amount: Uint128::from(100u128),
+        vamm: Some(vamm2.addr().to_string()),
+    };
+    let err = router
+        .execute_contract(engine.clone(), insurance_fund.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "vAMM is not registered".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // nor can fees be credited to it
+    let msg = insurance_fund
+        .credit_market_fund(vamm2.addr().to_string(), Uint128::from(100u128))
+        .unwrap();
+    let err = router.execute(engine, msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "vAMM is not registered".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_claim_unstaked_debits_market_funds() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        vamm1,
+        ..
+    } = new_shutdown_scenario();
+
+    let alice = Addr::unchecked("alice");
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+
+    let msg = insurance_fund.add_vamm(vamm1.addr().to_string()).unwrap();
+    router.execute(owner, msg).unwrap();
+
+    for (address, amount) in [(&alice, 1_000u128), (&insurance_fund.addr(), 1_000u128)] {
+        router
+            .sudo(SudoMsg::Bank(BankSudo::Mint {
+                to_address: address.to_string(),
+                amount: vec![coin(amount, NATIVE_DENOM)],
+            }))
+            .unwrap();
+    }
+
+    let msg = insurance_fund
+        .stake(
+            Uint128::from(1_000u128),
+            vec![coin(1_000u128, NATIVE_DENOM)],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // 400 of fees from vamm1 are shared between the stakers and the protocol
+    router
+        .sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: insurance_fund.addr().to_string(),
+            amount: vec![coin(400u128, NATIVE_DENOM)],
+        }))
+        .unwrap();
+
+    let msg = insurance_fund
+        .credit_market_fund(vamm1.addr().to_string(), Uint128::from(400u128))
+        .unwrap();
+    router.execute(engine.clone(), msg).unwrap();
+
+    let msg = insurance_fund.unstake(Uint128::from(1_000u128)).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(604_800);
+        block.height += 1;
+    });
+
+    // alice's 1200 is taken from the shared pool and the sub-fund in proportion
+    let msg = insurance_fund.claim_unstaked().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = router
+        .wrap()
+        .query_balance(&alice, NATIVE_DENOM)
+        .unwrap()
+        .amount;
+    assert_eq!(balance, Uint128::from(1_200u128));
+
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.market_funds[0].balance, Uint128::from(200u128));
+    assert_eq!(funds.shared_pool, Uint128::from(1_000u128));
+
+    // the remaining sub-fund can still be paid out to the market
+    let msg = ExecuteMsg::Withdraw {
+        token: AssetInfo::NativeToken {
+            denom: NATIVE_DENOM.to_string(),
+        },
+        amount: Uint128::from(200u128),
+        vamm: Some(vamm1.addr().to_string()),
+    };
+    router
+        .execute_contract(engine, insurance_fund.addr(), &msg, &[])
+        .unwrap();
+
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.market_funds[0].balance, Uint128::zero());
+    assert_eq!(funds.shared_pool, Uint128::from(1_000u128));
+}
+
+#[test]
+fn test_remove_vamm_releases_market_fund() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        vamm1,
+        vamm2,
+        ..
+    } = new_shutdown_scenario();
+
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+
+    for vamm in [&vamm1, &vamm2] {
+        let msg = insurance_fund.add_vamm(vamm.addr().to_string()).unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+    }
+
+    router
+        .sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: insurance_fund.addr().to_string(),
+            amount: vec![coin(1_000u128, NATIVE_DENOM)],
+        }))
+        .unwrap();
+
+    let msg = insurance_fund
+        .credit_market_fund(vamm1.addr().to_string(), Uint128::from(300u128))
+        .unwrap();
+    router.execute(engine.clone(), msg).unwrap();
+
+    let msg = insurance_fund
+        .credit_market_fund(vamm2.addr().to_string(), Uint128::from(200u128))
+        .unwrap();
+    router.execute(engine, msg).unwrap();
+
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.shared_pool, Uint128::from(500u128));
+
+    // the sub-fund of the removed market goes back to the shared pool
+    let msg = insurance_fund
+        .remove_vamm(vamm1.addr().to_string())
+        .unwrap();
+    router.execute(owner, msg).unwrap();
+
+    let fund = insurance_fund
+        .market_fund(&router.wrap(), vamm1.addr().to_string())
+        .unwrap();
+    assert_eq!(fund.balance, Uint128::zero());
+
+    let funds = insurance_fund
+        .all_market_funds(&router.wrap(), None)
+        .unwrap();
+    assert_eq!(funds.market_funds.len(), 1usize);
+    assert_eq!(funds.market_funds[0].vamm, vamm2.addr());
+    assert_eq!(funds.market_funds[0].balance, Uint128::from(200u128));
+    assert_eq!(funds.shared_pool, Uint128::from(800u128));
+}
@@ -1,3 +1,5 @@
+mod market_fund_tests;
+mod staking_tests;
 mod tests;
 
 use margined_utils::{create_entry_points_testing, testing::ShutdownScenario};
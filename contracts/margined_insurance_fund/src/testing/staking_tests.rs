@@ -0,0 +1,268 @@
+use crate::testing::new_shutdown_scenario;
+use cosmwasm_std::{coin, Addr, StdError, Uint128};
+use margined_common::asset::{AssetInfo, NATIVE_DENOM};
+use margined_perp::margined_insurance_fund::ExecuteMsg;
+use margined_utils::cw_multi_test::{BankSudo, Executor, SudoMsg};
+use margined_utils::testing::ShutdownScenario;
+
+#[test]
+fn test_staking_shares_fees_and_losses() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        ..
+    } = new_shutdown_scenario();
+
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+
+    for (address, amount) in [
+        (&alice, 1_000u128),
+        (&bob, 1_100u128),
+        (&insurance_fund.addr(), 1_000u128),
+    ] {
+        router
+            .sudo(SudoMsg::Bank(BankSudo::Mint {
+                to_address: address.to_string(),
+                amount: vec![coin(amount, NATIVE_DENOM)],
+            }))
+            .unwrap();
+    }
+
+    // alice stakes alongside the 1000 owned by the protocol
+    let msg = insurance_fund
+        .stake(
+            Uint128::from(1_000u128),
+            vec![coin(1_000u128, NATIVE_DENOM)],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let staker = insurance_fund
+        .staker(&router.wrap(), alice.to_string())
+        .unwrap();
+    assert_eq!(staker.shares, Uint128::from(1_000u128));
+    assert_eq!(staker.value, Uint128::from(1_000u128));
+
+    // 200 of spread fees are split between the stakers and the protocol
+    router
+        .sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: insurance_fund.addr().to_string(),
+            amount: vec![coin(200u128, NATIVE_DENOM)],
+        }))
+        .unwrap();
+
+    let state = insurance_fund.staking_state(&router.wrap()).unwrap();
+    assert_eq!(state.staked_value, Uint128::from(1_100u128));
+    assert_eq!(state.protocol_value, Uint128::from(1_100u128));
+
+    // bob receives fewer shares as the share price has increased
+    let msg = insurance_fund
+        .stake(
+            Uint128::from(1_100u128),
+            vec![coin(1_100u128, NATIVE_DENOM)],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let staker = insurance_fund
+        .staker(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(staker.shares, Uint128::from(1_000u128));
+
+    // the engine withdraws 660 to cover bad debt, stakers hold 2/3 of the fund
+    let msg = ExecuteMsg::Withdraw {
+        token: AssetInfo::NativeToken {
+            denom: NATIVE_DENOM.to_string(),
+        },
+        amount: Uint128::from(660u128),
+        vamm: None,
+    };
+    router
+        .execute_contract(engine, insurance_fund.addr(), &msg, &[])
+        .unwrap();
+
+    let state = insurance_fund.staking_state(&router.wrap()).unwrap();
+    assert_eq!(state.total_shares, Uint128::from(2_000u128));
+    assert_eq!(state.staked_value, Uint128::from(1_760u128));
+    assert_eq!(state.protocol_value, Uint128::from(880u128));
+
+    // the owner cannot withdraw the stakers' collateral
+    let msg = insurance_fund
+        .propose_withdrawal(
+            AssetInfo::NativeToken {
+                denom: NATIVE_DENOM.to_string(),
+            },
+            Uint128::from(881u128),
+            owner.to_string(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(172_800);
+        block.height += 1;
+    });
+
+    let msg = insurance_fund.execute_withdrawal(1u64).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "amount exceeds protocol funds".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // alice unstakes and has to wait for the cooldown
+    let msg = insurance_fund.unstake(Uint128::from(1_000u128)).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = insurance_fund.unstake(Uint128::from(1u128)).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "insufficient shares".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = insurance_fund.claim_unstaked().unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "unstake cooldown has not elapsed".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(604_800);
+        block.height += 1;
+    });
+
+    let msg = insurance_fund.claim_unstaked().unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = router
+        .wrap()
+        .query_balance(&alice, NATIVE_DENOM)
+        .unwrap()
+        .amount;
+    assert_eq!(balance, Uint128::from(880u128));
+
+    let state = insurance_fund.staking_state(&router.wrap()).unwrap();
+    assert_eq!(state.total_shares, Uint128::from(1_000u128));
+    assert_eq!(state.staked_value, Uint128::from(880u128));
+    assert_eq!(state.protocol_value, Uint128::from(880u128));
+}
+
+#[test]
+fn test_update_unstake_cooldown() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        ..
+    } = new_shutdown_scenario();
+
+    let msg = insurance_fund
+        .update_config(Some(86_400u64), None, None)
+        .unwrap();
+    let err = router
+        .execute(Addr::unchecked("alice"), msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "unauthorized".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    router.execute(owner, msg).unwrap();
+
+    let config = insurance_fund.config(&router.wrap()).unwrap();
+    assert_eq!(config.unstake_cooldown, 86_400u64);
+}
+
+#[test]
+fn test_stake_after_staked_value_is_wiped_out() {
+    let ShutdownScenario {
+        mut router,
+        insurance_fund,
+        ..
+    } = new_shutdown_scenario();
+
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let engine = insurance_fund.config(&router.wrap()).unwrap().engine;
+
+    for address in [&alice, &bob] {
+        router
+            .sudo(SudoMsg::Bank(BankSudo::Mint {
+                to_address: address.to_string(),
+                amount: vec![coin(1_000u128, NATIVE_DENOM)],
+            }))
+            .unwrap();
+    }
+
+    let msg = insurance_fund
+        .stake(
+            Uint128::from(1_000u128),
+            vec![coin(1_000u128, NATIVE_DENOM)],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // bad debt takes the whole fund, alice's shares are now worthless
+    let msg = ExecuteMsg::Withdraw {
+        token: AssetInfo::NativeToken {
+            denom: NATIVE_DENOM.to_string(),
+        },
+        amount: Uint128::from(1_000u128),
+        vamm: None,
+    };
+    router
+        .execute_contract(engine, insurance_fund.addr(), &msg, &[])
+        .unwrap();
+
+    let state = insurance_fund.staking_state(&router.wrap()).unwrap();
+    assert_eq!(state.total_shares, Uint128::from(1_000u128));
+    assert_eq!(state.staked_value, Uint128::zero());
+
+    // bob's deposit would otherwise be shared with alice's worthless shares
+    let stake_msg = insurance_fund
+        .stake(
+            Uint128::from(1_000u128),
+            vec![coin(1_000u128, NATIVE_DENOM)],
+        )
+        .unwrap();
+    let err = router.execute(bob.clone(), stake_msg.clone()).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "staked value is zero, existing shares must be unstaked first".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let msg = insurance_fund.unstake(Uint128::from(1_000u128)).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(604_800);
+        block.height += 1;
+    });
+
+    let msg = insurance_fund.claim_unstaked().unwrap();
+    router.execute(alice, msg).unwrap();
+
+    // once the shares are burnt bob receives the whole value of his deposit
+    router.execute(bob.clone(), stake_msg).unwrap();
+
+    let staker = insurance_fund
+        .staker(&router.wrap(), bob.to_string())
+        .unwrap();
+    assert_eq!(staker.shares, Uint128::from(1_000u128));
+    assert_eq!(staker.value, Uint128::from(1_000u128));
+}
@@ -1,11 +1,14 @@
 use crate::contract::{execute, instantiate, query};
+use crate::state::KEY_CONFIG;
 use crate::testing::new_shutdown_scenario;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{from_binary, Addr, StdError, SubMsg, Uint128};
+use cosmwasm_std::{from_binary, Addr, StdError, Storage, SubMsg, Uint128};
 use margined_common::asset::AssetInfo;
 use margined_perp::margined_insurance_fund::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, OwnerResponse, QueryMsg,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, OwnerResponse, PendingWithdrawalsResponse,
+    QueryMsg, WithdrawalResponse,
 };
+use margined_perp::margined_vamm::MarketStatus;
 use margined_utils::cw_multi_test::Executor;
 use margined_utils::testing::ShutdownScenario;
 
@@ -26,10 +29,32 @@ fn test_instantiation() {
         config,
         ConfigResponse {
             engine: Addr::unchecked(ENGINE.to_string()),
+            unstake_cooldown: 604_800u64,
+            shared_pool_fallback: true,
+            withdrawal_delay: 172_800u64,
         }
     );
 }
 
+#[test]
+fn test_config_stored_before_staking() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        engine: ENGINE.to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the staking, sub-fund and timelock fields are missing from configs stored before them
+    deps.storage.set(KEY_CONFIG, br#"{"engine":"engine"}"#);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.unstake_cooldown, 604_800u64);
+    assert!(config.shared_pool_fallback);
+    assert_eq!(config.withdrawal_delay, 172_800u64);
+}
+
 #[test]
 fn test_update_owner() {
     let mut deps = mock_dependencies();
@@ -307,19 +332,32 @@ fn test_off_vamm_off_again() {
     let msg = insurance_fund.add_vamm(vamm1.addr().to_string()).unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
-    //turn vamm off
+    //turn vamm to reduce only
     let msg = insurance_fund.shutdown_vamms().unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
-    //turn vamm off again (note the unauthorized error comes from state.open == open)
+    let res = insurance_fund
+        .vamm_status(&router.wrap(), vamm1.addr().to_string())
+        .unwrap();
+    assert_eq!(res.vamm_status, MarketStatus::ReduceOnly);
+
+    //turn vamm off again, which pauses it
     let msg = insurance_fund.shutdown_vamms().unwrap();
-    let err = router.execute(owner, msg).unwrap_err();
-    assert_eq!(
-        StdError::GenericErr {
-            msg: "unauthorized".to_string(),
-        },
-        err.downcast().unwrap()
-    );
+    router.execute(owner.clone(), msg).unwrap();
+
+    let res = insurance_fund
+        .vamm_status(&router.wrap(), vamm1.addr().to_string())
+        .unwrap();
+    assert_eq!(res.vamm_status, MarketStatus::Paused);
+
+    //a paused vamm is left as it is
+    let msg = insurance_fund.shutdown_vamms().unwrap();
+    router.execute(owner, msg).unwrap();
+
+    let res = insurance_fund
+        .vamm_status(&router.wrap(), vamm1.addr().to_string())
+        .unwrap();
+    assert_eq!(res.vamm_status, MarketStatus::Paused);
 }
 
 #[test]
@@ -355,9 +393,9 @@ fn test_vamm_shutdown() {
     assert_eq!(
         vamms_status,
         vec![
-            (vamm1.addr(), true),
-            (vamm2.addr(), true),
-            (vamm3.addr(), true)
+            (vamm1.addr(), MarketStatus::Active),
+            (vamm2.addr(), MarketStatus::Active),
+            (vamm3.addr(), MarketStatus::Active)
         ]
     );
 
@@ -374,9 +412,9 @@ fn test_vamm_shutdown() {
     assert_eq!(
         vamms_status,
         vec![
-            (vamm1.addr(), false),
-            (vamm2.addr(), false),
-            (vamm3.addr(), false)
+            (vamm1.addr(), MarketStatus::ReduceOnly),
+            (vamm2.addr(), MarketStatus::ReduceOnly),
+            (vamm3.addr(), MarketStatus::ReduceOnly)
         ]
     );
 }
@@ -414,9 +452,9 @@ fn test_vamm_shutdown_from_insurance() {
     assert_eq!(
         vamms_status,
         vec![
-            (vamm1.addr(), true),
-            (vamm2.addr(), true),
-            (vamm3.addr(), true)
+            (vamm1.addr(), MarketStatus::Active),
+            (vamm2.addr(), MarketStatus::Active),
+            (vamm3.addr(), MarketStatus::Active)
         ]
     );
 
@@ -433,9 +471,9 @@ fn test_vamm_shutdown_from_insurance() {
     assert_eq!(
         vamms_status,
         vec![
-            (vamm1.addr(), false),
-            (vamm2.addr(), false),
-            (vamm3.addr(), false)
+            (vamm1.addr(), MarketStatus::ReduceOnly),
+            (vamm2.addr(), MarketStatus::ReduceOnly),
+            (vamm3.addr(), MarketStatus::ReduceOnly)
         ]
     );
 }
@@ -460,7 +498,7 @@ fn test_query_vamm_status() {
         .unwrap();
     let status = res.vamm_status;
 
-    assert_eq!(status, true);
+    assert_eq!(status, MarketStatus::Active);
 
     // shutdown vamm
     let msg = insurance_fund.shutdown_vamms().unwrap();
@@ -472,7 +510,7 @@ fn test_query_vamm_status() {
         .unwrap();
     let status = res.vamm_status;
 
-    assert_eq!(status, false);
+    assert_eq!(status, MarketStatus::ReduceOnly);
 }
 
 #[test]
@@ -512,7 +550,10 @@ fn test_all_vamm_status() {
 
     assert_eq!(
         vamms_status,
-        vec![(vamm1.addr(), true), (vamm2.addr(), true)]
+        vec![
+            (vamm1.addr(), MarketStatus::Active),
+            (vamm2.addr(), MarketStatus::Active),
+        ]
     );
 
     // switch first vamm off
@@ -527,7 +568,10 @@ fn test_all_vamm_status() {
 
     assert_eq!(
         vamms_status,
-        vec![(vamm1.addr(), false), (vamm2.addr(), false)]
+        vec![
+            (vamm1.addr(), MarketStatus::ReduceOnly),
+            (vamm2.addr(), MarketStatus::ReduceOnly),
+        ]
     );
 }
 
@@ -558,7 +602,7 @@ fn test_pagination() {
         .unwrap();
     let vamms_status = res.vamm_list_status;
 
-    assert_eq!(vamms_status, vec![(vamm1.addr(), true)]);
+    assert_eq!(vamms_status, vec![(vamm1.addr(), MarketStatus::Active)]);
 }
 #[test]
 fn test_pagination_limit() {
@@ -595,9 +639,9 @@ fn test_pagination_limit() {
     assert_eq!(
         vamms_status,
         vec![
-            (vamm1.addr(), true),
-            (vamm2.addr(), true),
-            (vamm3.addr(), true),
+            (vamm1.addr(), MarketStatus::Active),
+            (vamm2.addr(), MarketStatus::Active),
+            (vamm3.addr(), MarketStatus::Active),
         ]
     );
 
@@ -609,7 +653,10 @@ fn test_pagination_limit() {
 
     assert_eq!(
         vamms_status,
-        vec![(vamm1.addr(), true), (vamm2.addr(), true),]
+        vec![
+            (vamm1.addr(), MarketStatus::Active),
+            (vamm2.addr(), MarketStatus::Active),
+        ]
     );
 }
 
@@ -685,7 +732,7 @@ fn test_incompatible_decimals() {
 }
 
 #[test]
-fn tet_withdraw_fund_to_operator() {
+fn test_timelocked_withdrawal() {
     let mut deps = mock_dependencies();
     let msg = InstantiateMsg {
         engine: ENGINE.to_string(),
@@ -697,32 +744,94 @@ fn tet_withdraw_fund_to_operator() {
     let token = AssetInfo::Token {
         contract_addr: Addr::unchecked("usdc"),
     };
-    let msg = ExecuteMsg::WithdrawFund {
+    let msg = ExecuteMsg::ProposeWithdrawal {
         token: token.clone(),
         amount: Uint128::one(),
+        recipient: "treasury".to_string(),
     };
 
-    // withdraw fund failed, unauthorized
+    // propose withdrawal failed, unauthorized
     let info = mock_info("addr0000", &[]);
     let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
     assert_eq!(err, StdError::generic_err("unauthorized"));
 
-    // withdraw fund to operator successful
+    // propose two withdrawals, the first is cancelled
     let info = mock_info("owner", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let unlock_time = mock_env().block.time.seconds() + 172_800u64;
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPendingWithdrawals {
+            start_after: Some(1u64),
+            limit: Some(1u32),
+        },
+    )
+    .unwrap();
+    let pending: PendingWithdrawalsResponse = from_binary(&res).unwrap();
+    assert_eq!(pending.withdrawals.len(), 1usize);
+    assert_eq!(
+        pending.withdrawals[0],
+        WithdrawalResponse {
+            id: 2u64,
+            token: token.clone(),
+            amount: Uint128::one(),
+            recipient: Addr::unchecked("treasury"),
+            unlock_time,
+        }
+    );
+
+    let msg = ExecuteMsg::CancelWithdrawal { id: 1u64 };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, StdError::generic_err("withdrawal does not exist"));
+
+    // the withdrawal cannot be executed before the delay has passed
+    let msg = ExecuteMsg::ExecuteWithdrawal { id: 2u64 };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(err, StdError::generic_err("withdrawal is still timelocked"));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(172_800u64);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            ("action", "insurance_withdraw_to_operator"),
-            ("amount", &Uint128::one().to_string())
+            ("action", "execute_withdrawal"),
+            ("id", "2"),
+            ("amount", &Uint128::one().to_string()),
+            ("recipient", "treasury"),
         ]
     );
     assert_eq!(
         res.messages,
         vec![SubMsg::new(
             token
-                .into_msg("owner".to_string(), Uint128::one(), None)
+                .into_msg("treasury".to_string(), Uint128::one(), None)
                 .unwrap()
         )]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPendingWithdrawals {
+            start_after: None,
+            limit: None,
+        },
     )
+    .unwrap();
+    let pending: PendingWithdrawalsResponse = from_binary(&res).unwrap();
+    assert!(pending.withdrawals.is_empty());
+
+    // the timelock cannot be shortened below the minimum delay
+    let msg = ExecuteMsg::UpdateConfig {
+        unstake_cooldown: None,
+        shared_pool_fallback: None,
+        withdrawal_delay: Some(0u64),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+    assert_eq!(err, StdError::generic_err("withdrawal delay is too short"));
 }
@@ -0,0 +1,108 @@
+use cosmwasm_std::{Deps, Env, StdError, StdResult, Storage, Uint128};
+
+use margined_common::asset::AssetInfo;
+use margined_utils::contracts::helpers::EngineController;
+
+use crate::{
+    query::MAX_PAGINATION_LIMIT,
+    state::{
+        decrease_market_fund, read_market_fund, read_total_market_funds, read_vammlist, Config,
+        StakingState,
+    },
+};
+
+/// Returns the collateral that can be staked, this is the engine's eligible collateral
+pub fn staking_token(deps: Deps, config: &Config) -> StdResult<AssetInfo> {
+    let engine_controller = EngineController(config.engine.clone());
+
+    Ok(engine_controller.config(&deps.querier)?.eligible_collateral)
+}
+
+/// Queries the current collateral balance, excluding any amount that is being deposited
+pub fn staking_balance(
+    deps: Deps,
+    env: &Env,
+    token: &AssetInfo,
+    pending: Uint128,
+) -> StdResult<Uint128> {
+    let balance = token.query_balance(&deps.querier, env.contract.address.clone())?;
+
+    Ok(balance.saturating_sub(pending))
+}
+
+/// Queries the balance that is not allocated to any market's sub-fund
+pub fn shared_pool_balance(deps: Deps, env: &Env, token: &AssetInfo) -> StdResult<Uint128> {
+    let total_market_funds = read_total_market_funds(deps.storage)?;
+    let balance = token.query_balance(&deps.querier, env.contract.address.clone())?;
+
+    Ok(balance.saturating_sub(total_market_funds))
+}
+
+/// Debits an amount from the market sub-funds pro-rata to their balances
+pub fn debit_market_funds(storage: &mut dyn Storage, amount: Uint128) -> StdResult<()> {
+    let total_market_funds = read_total_market_funds(storage)?;
+    let vamms = read_vammlist(storage, MAX_PAGINATION_LIMIT as usize).unwrap_or_default();
+
+    let mut debits = vec![];
+    let mut remaining = amount;
+    for vamm in vamms {
+        let fund = read_market_fund(storage, &vamm)?;
+        let debit = fund.multiply_ratio(amount, total_market_funds);
+        remaining = remaining.checked_sub(debit)?;
+        debits.push((vamm, fund, debit));
+    }
+
+    // the rounding remainder is taken from the first sub-funds that can cover it
+    for (vamm, fund, debit) in debits.iter_mut() {
+        let extra = remaining.min(fund.checked_sub(*debit)?);
+        *debit = debit.checked_add(extra)?;
+        remaining = remaining.checked_sub(extra)?;
+
+        decrease_market_fund(storage, vamm, *debit)?;
+    }
+
+    if !remaining.is_zero() {
+        return Err(StdError::generic_err("insufficient market insurance funds"));
+    }
+
+    Ok(())
+}
+
+/// Accounts for any change in balance since the last sync, tokens received in the meantime
+/// (i.e. spread fees) are shared pro-rata between the stakers and the protocol
+pub fn sync_staking_state(state: &mut StakingState, balance: Uint128) -> StdResult<()> {
+    if balance > state.last_balance {
+        if !state.last_balance.is_zero() {
+            let gain = balance.checked_sub(state.last_balance)?;
+            let staker_gain = gain.multiply_ratio(state.staked_value, state.last_balance);
+            state.staked_value = state.staked_value.checked_add(staker_gain)?;
+        }
+        state.last_balance = balance;
+    } else if balance < state.last_balance {
+        realize_loss(state, state.last_balance.checked_sub(balance)?)?;
+    }
+
+    Ok(())
+}
+
+/// Removes an amount from the fund with the stakers absorbing their pro-rata share of it
+pub fn realize_loss(state: &mut StakingState, amount: Uint128) -> StdResult<()> {
+    let amount = amount.min(state.last_balance);
+
+    if !amount.is_zero() {
+        let staker_loss = amount.multiply_ratio(state.staked_value, state.last_balance);
+        state.staked_value = state.staked_value.checked_sub(staker_loss)?;
+        state.last_balance = state.last_balance.checked_sub(amount)?;
+    }
+
+    Ok(())
+}
+
+/// Converts a number of shares to the collateral they are worth
+pub fn shares_to_value(state: &StakingState, shares: Uint128) -> Uint128 {
+    if state.total_shares.is_zero() {
+        return Uint128::zero();
+    }
+
+    shares.multiply_ratio(state.staked_value, state.total_shares)
+}
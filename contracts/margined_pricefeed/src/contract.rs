@@ -1,12 +1,22 @@
 use crate::error::ContractError;
-use crate::handle::update_executor;
-use crate::query::{query_executor, query_get_price_detail, query_last_round_id};
+use crate::handle::{
+    force_append_price, submit_price, submit_signed_prices, sync_price, update_config,
+    update_executor, update_price_guard, update_price_source, update_publisher_keys,
+    update_publishers,
+};
+use crate::query::{
+    query_executor, query_get_price_at, query_get_price_detail, query_get_price_history,
+    query_last_round_id, query_price_guard, query_price_source, query_publisher_keys,
+    query_publishers,
+};
 use crate::{
     handle::{append_multiple_price, append_price, update_owner},
     query::{
         query_config, query_get_previous_price, query_get_price, query_get_twap_price, query_owner,
     },
-    state::{store_config, Config},
+    state::{
+        migrate_cumulative_prices, read_config, store_config, Config, DEFAULT_SUBMISSION_WINDOW,
+    },
 };
 use cw2::set_contract_version;
 use cw_controllers::Admin;
@@ -30,11 +40,14 @@ pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let config = Config {};
+    let config = Config {
+        submission_window: DEFAULT_SUBMISSION_WINDOW,
+        oracle_hub_contract: Some(deps.api.addr_validate(&msg.oracle_hub_contract)?),
+    };
 
     store_config(deps.storage, &config)?;
 
@@ -63,6 +76,38 @@ pub fn execute(
         } => append_multiple_price(deps, env, info, key, prices, timestamps),
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
         ExecuteMsg::UpdateExecutor { executor } => update_executor(deps, info, executor),
+        ExecuteMsg::UpdateConfig {
+            submission_window,
+            oracle_hub_contract,
+        } => update_config(deps, info, submission_window, oracle_hub_contract),
+        ExecuteMsg::UpdatePublishers {
+            key,
+            publishers,
+            quorum,
+        } => update_publishers(deps, info, key, publishers, quorum),
+        ExecuteMsg::SubmitPrice {
+            key,
+            price,
+            timestamp,
+        } => submit_price(deps, env, info, key, price, timestamp),
+        ExecuteMsg::UpdatePriceGuard {
+            key,
+            heartbeat,
+            max_deviation_bps,
+        } => update_price_guard(deps, info, key, heartbeat, max_deviation_bps),
+        ExecuteMsg::ForceAppendPrice {
+            key,
+            price,
+            timestamp,
+        } => force_append_price(deps, env, info, key, price, timestamp),
+        ExecuteMsg::UpdatePriceSource { key, source } => {
+            update_price_source(deps, info, key, source)
+        }
+        ExecuteMsg::SyncPrice { key } => sync_price(deps, env, key),
+        ExecuteMsg::UpdatePublisherKeys { key, pubkeys } => {
+            update_publisher_keys(deps, info, key, pubkeys)
+        }
+        ExecuteMsg::SubmitSignedPrices { payloads } => submit_signed_prices(deps, env, payloads),
     }
 }
 
@@ -71,7 +116,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
-        QueryMsg::GetPrice { key } => to_binary(&query_get_price(deps, key)?),
+        QueryMsg::GetPrice { key } => to_binary(&query_get_price(deps, env, key)?),
         QueryMsg::GetPreviousPrice {
             key,
             num_round_back,
@@ -81,12 +126,45 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         }
         QueryMsg::GetLastRoundId { key } => to_binary(&query_last_round_id(deps, key)?),
         QueryMsg::GetExecutor {} => to_binary(&query_executor(deps)?),
-        QueryMsg::GetPriceDetail { key } => to_binary(&query_get_price_detail(deps, key)?),
+        QueryMsg::GetPriceDetail { key } => to_binary(&query_get_price_detail(deps, env, key)?),
+        QueryMsg::GetPublishers { key } => to_binary(&query_publishers(deps, key)?),
+        QueryMsg::GetPriceGuard { key } => to_binary(&query_price_guard(deps, key)?),
+        QueryMsg::GetPriceSource { key } => to_binary(&query_price_source(deps, key)?),
+        QueryMsg::GetPublisherKeys { key } => to_binary(&query_publisher_keys(deps, key)?),
+        QueryMsg::GetPriceHistory {
+            key,
+            start_after_round,
+            limit,
+            order,
+        } => to_binary(&query_get_price_history(
+            deps,
+            key,
+            start_after_round,
+            limit,
+            order,
+        )?),
+        QueryMsg::GetPriceAt { key, timestamp } => {
+            to_binary(&query_get_price_at(deps, key, timestamp)?)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // configs stored before the submission window was added do not deserialize
+    if read_config(deps.storage).is_err() {
+        store_config(
+            deps.storage,
+            &Config {
+                submission_window: DEFAULT_SUBMISSION_WINDOW,
+                oracle_hub_contract: None,
+            },
+        )?;
+    }
+
+    migrate_cumulative_prices(deps.storage, env.block.time.seconds())?;
+
     Ok(Response::new())
 }
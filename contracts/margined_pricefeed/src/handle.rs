@@ -1,9 +1,21 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, Uint128};
+use cosmwasm_std::{
+    Binary, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Timestamp, Uint128,
+};
+use margined_perp::margined_pricefeed::{PriceData, PriceSource, SignedPrice};
 
 use crate::{
     contract::{EXECUTOR, OWNER},
     error::ContractError,
-    state::store_price_data,
+    query::query_oracle_hub_price,
+    state::{
+        read_config, read_last_round_id, read_pending_round, read_price_data, read_price_guard,
+        read_price_source, read_publisher_keys, read_publishers, read_signed_timestamp,
+        remove_pending_round, store_config, store_last_round_id, store_pending_round,
+        store_price_data, store_price_guard, store_price_source, store_publisher_keys,
+        store_publishers, store_round_price_data, store_round_submissions, store_signed_timestamp,
+        PendingRound, PublisherKeys, Publishers, BPS_DENOMINATOR, ED25519_PUBKEY_LENGTH,
+        MAX_PUBLISHERS,
+    },
 };
 
 pub fn update_owner(
@@ -30,6 +42,7 @@ pub fn append_price(
 ) -> Result<Response, ContractError> {
     // check permission
     EXECUTOR.assert_admin(deps.as_ref(), &info.sender)?;
+    assert_not_published(deps.storage, &key)?;
 
     if price.is_zero() {
         return Err(ContractError::Std(StdError::generic_err(
@@ -42,6 +55,10 @@ pub fn append_price(
             "Invalid timestamp",
         )));
     }
+
+    let last_round_id = read_last_round_id(deps.storage, &key)?;
+    assert_price_deviation(deps.storage, &key, last_round_id, price)?;
+
     store_price_data(deps.storage, key, price, timestamp)?;
 
     Ok(Response::default().add_attribute("action", "append_price"))
@@ -60,6 +77,7 @@ pub fn append_multiple_price(
 ) -> Result<Response, ContractError> {
     // check permission
     EXECUTOR.assert_admin(deps.as_ref(), &info.sender)?;
+    assert_not_published(deps.storage, &key)?;
 
     // This throws if the prices and timestamps are not the same length
     if prices.len() != timestamps.len() {
@@ -80,6 +98,11 @@ pub fn append_multiple_price(
                 "Invalid timestamp",
             )));
         }
+
+        // each price is checked against the one appended before it
+        let last_round_id = read_last_round_id(deps.storage, &key)?;
+        assert_price_deviation(deps.storage, &key, last_round_id, prices[index])?;
+
         store_price_data(deps.storage, key.clone(), prices[index], timestamps[index])?;
     }
 
@@ -103,3 +126,519 @@ pub fn update_executor(
         ("new_executor", &executor),
     ]))
 }
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    submission_window: Option<u64>,
+    oracle_hub_contract: Option<String>,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut config = read_config(deps.storage)?;
+
+    // change the time a round accepts submissions
+    if let Some(submission_window) = submission_window {
+        if submission_window == 0u64 {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Submission window must not be zero",
+            )));
+        }
+        config.submission_window = submission_window;
+    }
+
+    // change the oracle hub that prices are pulled from
+    if let Some(oracle_hub_contract) = oracle_hub_contract {
+        config.oracle_hub_contract = Some(deps.api.addr_validate(&oracle_hub_contract)?);
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
+/// sets the publishers of a key and the number of submissions needed
+/// for a round, an empty list hands the key back to the executor.
+pub fn update_publishers(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    publishers: Vec<String>,
+    quorum: u64,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if publishers.len() > MAX_PUBLISHERS {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Too many publishers",
+        )));
+    }
+
+    let mut valid_publishers = vec![];
+    for publisher in publishers.iter() {
+        let publisher = deps.api.addr_validate(publisher)?;
+        if valid_publishers.contains(&publisher) {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Duplicate publisher",
+            )));
+        }
+        valid_publishers.push(publisher);
+    }
+
+    if !valid_publishers.is_empty() && (quorum == 0u64 || quorum > valid_publishers.len() as u64) {
+        return Err(ContractError::Std(StdError::generic_err("Invalid quorum")));
+    }
+
+    store_publishers(
+        deps.storage,
+        &key,
+        &Publishers {
+            publishers: valid_publishers,
+            quorum,
+        },
+    )?;
+
+    // submissions made under the previous publisher set are discarded
+    remove_pending_round(deps.storage, &key);
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_publishers"),
+        ("key", &key),
+        ("quorum", &quorum.to_string()),
+    ]))
+}
+
+/// records the submission of a publisher, once the quorum is reached the
+/// median of the submissions is stored as the price of the round.
+pub fn submit_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+    price: Uint128,
+    timestamp: u64,
+) -> Result<Response, ContractError> {
+    let publishers = read_publishers(deps.storage, &key)?;
+
+    // check permission
+    if !publishers.publishers.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price is must not be zero",
+        )));
+    }
+
+    if timestamp > env.block.time.seconds() || timestamp == 0u64 {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Invalid timestamp",
+        )));
+    }
+
+    let config = read_config(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    // the first submission after the window has closed opens a new round, as
+    // does one after the owner has forced a price past the pending round
+    let mut pending_round = read_pending_round(deps.storage, &key)?;
+    if pending_round.submissions.is_empty()
+        || now >= pending_round.opened_at + config.submission_window
+        || (pending_round.round_id != 0u64
+            && pending_round.round_id != read_last_round_id(deps.storage, &key)?)
+    {
+        pending_round = PendingRound {
+            opened_at: now,
+            round_id: 0u64,
+            submissions: vec![],
+        };
+    }
+
+    if pending_round
+        .submissions
+        .iter()
+        .any(|(publisher, _, _)| *publisher == info.sender)
+    {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price already submitted for this round",
+        )));
+    }
+
+    // submissions are checked against the round before the one being collected
+    let previous_round_id = if pending_round.round_id == 0u64 {
+        read_last_round_id(deps.storage, &key)?
+    } else {
+        pending_round.round_id - 1
+    };
+    assert_price_deviation(deps.storage, &key, previous_round_id, price)?;
+
+    pending_round
+        .submissions
+        .push((info.sender, price, timestamp));
+
+    let submissions = pending_round.submissions.len() as u64;
+    if submissions >= publishers.quorum {
+        // the round gets an id when the quorum is first reached, later
+        // submissions in the window update its price
+        if pending_round.round_id == 0u64 {
+            pending_round.round_id = read_last_round_id(deps.storage, &key)? + 1;
+            store_last_round_id(deps.storage, &key, pending_round.round_id)?;
+        }
+
+        let mut prices: Vec<Uint128> = pending_round
+            .submissions
+            .iter()
+            .map(|(_, price, _)| *price)
+            .collect();
+        let latest_timestamp = pending_round
+            .submissions
+            .iter()
+            .map(|(_, _, timestamp)| *timestamp)
+            .max()
+            .unwrap_or(timestamp);
+
+        store_round_price_data(
+            deps.storage,
+            &key,
+            &PriceData {
+                round_id: pending_round.round_id,
+                price: median(&mut prices)?,
+                timestamp: Timestamp::from_seconds(latest_timestamp),
+            },
+        )?;
+        store_round_submissions(deps.storage, &key, pending_round.round_id, submissions)?;
+    }
+
+    store_pending_round(deps.storage, &key, &pending_round)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "submit_price"),
+        ("key", &key),
+        ("submissions", &submissions.to_string()),
+    ]))
+}
+
+pub fn update_price_guard(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    heartbeat: Option<u64>,
+    max_deviation_bps: Option<u64>,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut price_guard = read_price_guard(deps.storage, &key)?;
+
+    // change the max age of the latest price
+    if let Some(heartbeat) = heartbeat {
+        price_guard.heartbeat = heartbeat;
+    }
+
+    // change the max change between two rounds
+    if let Some(max_deviation_bps) = max_deviation_bps {
+        price_guard.max_deviation_bps = max_deviation_bps;
+    }
+
+    store_price_guard(deps.storage, &key, &price_guard)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_price_guard"),
+        ("key", &key),
+        ("heartbeat", &price_guard.heartbeat.to_string()),
+        (
+            "max_deviation_bps",
+            &price_guard.max_deviation_bps.to_string(),
+        ),
+    ]))
+}
+
+/// lets the owner append a price that is not checked against the
+/// previous round, e.g. to recover after a large market move.
+pub fn force_append_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+    price: Uint128,
+    timestamp: u64,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if price.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price is must not be zero",
+        )));
+    }
+
+    if timestamp > env.block.time.seconds() || timestamp == 0u64 {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Invalid timestamp",
+        )));
+    }
+
+    store_price_data(deps.storage, key, price, timestamp)?;
+
+    Ok(Response::default().add_attribute("action", "force_append_price"))
+}
+
+/// selects whether the price of a key is pulled from the oracle hub
+/// or taken from the rounds appended by the executor or publishers.
+pub fn update_price_source(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    source: PriceSource,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if source == PriceSource::OracleHub && read_config(deps.storage)?.oracle_hub_contract.is_none()
+    {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Oracle hub is not set",
+        )));
+    }
+
+    store_price_source(deps.storage, &key, &source)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_price_source"), ("key", &key)]))
+}
+
+/// appends the current oracle hub price of a key as a new round so it
+/// is part of the price history and twap, can be called by anyone.
+pub fn sync_price(deps: DepsMut, env: Env, key: String) -> Result<Response, ContractError> {
+    if read_price_source(deps.storage, &key)? != PriceSource::OracleHub {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price source is not the oracle hub",
+        )));
+    }
+
+    let hub_price = query_oracle_hub_price(deps.as_ref(), &key)?;
+
+    if hub_price.price.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price is must not be zero",
+        )));
+    }
+
+    if hub_price.last_updated > env.block.time.seconds() || hub_price.last_updated == 0u64 {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Invalid timestamp",
+        )));
+    }
+
+    // only prices newer than the latest round are appended
+    let last_round_id = read_last_round_id(deps.storage, &key)?;
+    if last_round_id != 0u64
+        && hub_price.last_updated
+            <= read_price_data(deps.storage, key.clone(), last_round_id)?
+                .timestamp
+                .seconds()
+    {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Oracle hub price has not been updated",
+        )));
+    }
+
+    assert_price_deviation(deps.storage, &key, last_round_id, hub_price.price)?;
+
+    store_price_data(
+        deps.storage,
+        key.clone(),
+        hub_price.price,
+        hub_price.last_updated,
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "sync_price"),
+        ("key", &key),
+        ("price", &hub_price.price.to_string()),
+    ]))
+}
+
+/// sets the ed25519 public keys whose signed prices are accepted for a
+/// key, an empty list stops signed prices being accepted.
+pub fn update_publisher_keys(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    pubkeys: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if pubkeys.len() > MAX_PUBLISHERS {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Too many publisher keys",
+        )));
+    }
+
+    for (index, pubkey) in pubkeys.iter().enumerate() {
+        if pubkey.len() != ED25519_PUBKEY_LENGTH {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Invalid public key",
+            )));
+        }
+
+        if pubkeys[..index].contains(pubkey) {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Duplicate publisher key",
+            )));
+        }
+    }
+
+    store_publisher_keys(deps.storage, &key, &PublisherKeys { pubkeys })?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_publisher_keys"), ("key", &key)]))
+}
+
+/// appends prices signed off-chain by registered publisher keys, so that
+/// anyone can relay them. Each price must be newer than the last price
+/// accepted from the same key.
+pub fn submit_signed_prices(
+    deps: DepsMut,
+    env: Env,
+    payloads: Vec<SignedPrice>,
+) -> Result<Response, ContractError> {
+    if payloads.is_empty() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "No signed prices",
+        )));
+    }
+
+    for payload in payloads.iter() {
+        assert_not_published(deps.storage, &payload.key)?;
+
+        let publisher_keys = read_publisher_keys(deps.storage, &payload.key)?;
+        if !publisher_keys.pubkeys.contains(&payload.pubkey) {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        if payload.price.is_zero() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Price is must not be zero",
+            )));
+        }
+
+        if payload.timestamp > env.block.time.seconds() || payload.timestamp == 0u64 {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Invalid timestamp",
+            )));
+        }
+
+        // a price already accepted from the key is a replay
+        let last_timestamp = read_signed_timestamp(deps.storage, &payload.key, &payload.pubkey)?;
+        if payload.timestamp == last_timestamp {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Signed price has already been submitted",
+            )));
+        }
+        if payload.timestamp < last_timestamp {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Signed price is older than the last one",
+            )));
+        }
+
+        let message = signed_price_message(&env, &payload.key, payload.price, payload.timestamp);
+        if !deps
+            .api
+            .ed25519_verify(&message, &payload.signature, &payload.pubkey)
+            .map_err(StdError::from)?
+        {
+            return Err(ContractError::Std(StdError::generic_err(
+                "Invalid signature",
+            )));
+        }
+
+        let last_round_id = read_last_round_id(deps.storage, &payload.key)?;
+        assert_price_deviation(deps.storage, &payload.key, last_round_id, payload.price)?;
+
+        store_price_data(
+            deps.storage,
+            payload.key.clone(),
+            payload.price,
+            payload.timestamp,
+        )?;
+        store_signed_timestamp(
+            deps.storage,
+            &payload.key,
+            &payload.pubkey,
+            payload.timestamp,
+        )?;
+    }
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "submit_signed_prices"),
+        ("prices", &payloads.len().to_string()),
+    ]))
+}
+
+// the bytes a publisher signs for a price, the chain id and contract address
+// keep a signature from being accepted by other deployments. They are length
+// prefixed and fixed width fields follow the key so the encoding is unambiguous
+fn signed_price_message(env: &Env, key: &str, price: Uint128, timestamp: u64) -> Vec<u8> {
+    let chain_id = env.block.chain_id.as_bytes();
+    let contract = env.contract.address.as_bytes();
+
+    [
+        &(chain_id.len() as u32).to_be_bytes()[..],
+        chain_id,
+        &(contract.len() as u32).to_be_bytes(),
+        contract,
+        key.as_bytes(),
+        &price.u128().to_be_bytes(),
+        &timestamp.to_be_bytes(),
+    ]
+    .concat()
+}
+
+// keys with publishers can only be updated through their submissions
+fn assert_not_published(storage: &dyn Storage, key: &str) -> Result<(), ContractError> {
+    if !read_publishers(storage, key)?.publishers.is_empty() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price is set by publishers",
+        )));
+    }
+
+    Ok(())
+}
+
+fn median(prices: &mut [Uint128]) -> StdResult<Uint128> {
+    prices.sort();
+
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        Ok(prices[mid - 1]
+            .checked_add(prices[mid])?
+            .checked_div(Uint128::from(2u128))?)
+    } else {
+        Ok(prices[mid])
+    }
+}
+
+// rejects prices that moved more than the max deviation of the key since the given round
+fn assert_price_deviation(
+    storage: &dyn Storage,
+    key: &str,
+    round_id: u64,
+    price: Uint128,
+) -> StdResult<()> {
+    let price_guard = read_price_guard(storage, key)?;
+    if price_guard.max_deviation_bps == 0u64 || round_id == 0u64 {
+        return Ok(());
+    }
+
+    let previous_price = read_price_data(storage, key.to_string(), round_id)?.price;
+    let deviation = if price > previous_price {
+        price.checked_sub(previous_price)?
+    } else {
+        previous_price.checked_sub(price)?
+    };
+
+    if deviation.checked_mul(Uint128::from(BPS_DENOMINATOR))?
+        > previous_price.checked_mul(Uint128::from(price_guard.max_deviation_bps))?
+    {
+        return Err(StdError::generic_err("Price deviation is too large"));
+    }
+
+    Ok(())
+}
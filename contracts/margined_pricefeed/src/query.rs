@@ -1,16 +1,22 @@
-use cosmwasm_std::{Deps, Env, StdError, StdResult, Uint128};
+use cosmwasm_std::{Deps, Env, Order, StdError, StdResult, Timestamp, Uint128};
 use margined_perp::margined_pricefeed::{
-    ConfigResponse, ExecutorResponse, OwnerResponse, PriceDetailResponse,
+    ConfigResponse, ExecutorResponse, OracleHubPriceResponse, OracleHubQueryMsg, OwnerResponse,
+    PriceData, PriceDetailResponse, PriceGuardResponse, PriceSource, PublisherKeysResponse,
+    PublishersResponse,
 };
 
 use crate::{
     contract::{EXECUTOR, OWNER},
-    state::{read_last_round_id, read_price_data},
+    state::{
+        read_config, read_cumulative_price, read_cumulative_start, read_last_round_id,
+        read_price_data, read_price_guard, read_price_source, read_publisher_keys, read_publishers,
+        read_round_submissions, DEFAULT_LIMIT, MAX_LIMIT,
+    },
 };
 
 /// Queries contract Config
-pub fn query_config(_deps: Deps) -> StdResult<ConfigResponse> {
-    Ok(ConfigResponse {})
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    read_config(deps.storage)
 }
 
 /// Queries contract owner from the admin
@@ -32,19 +38,25 @@ pub fn query_executor(deps: Deps) -> StdResult<ExecutorResponse> {
 }
 
 /// Queries latest price for pair stored with key
-pub fn query_get_price(deps: Deps, key: String) -> StdResult<Uint128> {
-    let last_round_id = read_last_round_id(deps.storage, &key)?;
-    let price_data = read_price_data(deps.storage, key, last_round_id)?;
+pub fn query_get_price(deps: Deps, env: Env, key: String) -> StdResult<Uint128> {
+    let price_data = read_latest_price(deps, &env, &key)?;
+
+    if is_stale(deps, &env, &key, &price_data)? {
+        return Err(StdError::generic_err("Price is stale"));
+    }
+
     Ok(price_data.price)
 }
 
 /// Queries latest price for pair stored with key
-pub fn query_get_price_detail(deps: Deps, key: String) -> StdResult<PriceDetailResponse> {
-    let last_round_id = read_last_round_id(deps.storage, &key)?;
-    let price_data = read_price_data(deps.storage, key, last_round_id)?;
+pub fn query_get_price_detail(deps: Deps, env: Env, key: String) -> StdResult<PriceDetailResponse> {
+    let price_data = read_latest_price(deps, &env, &key)?;
+    let submissions = read_round_submissions(deps.storage, &key, price_data.round_id)?;
     Ok(PriceDetailResponse {
         price: price_data.price,
         timestamp: price_data.timestamp,
+        submissions,
+        is_stale: is_stale(deps, &env, &key, &price_data)?,
     })
 }
 
@@ -56,14 +68,16 @@ pub fn query_get_previous_price(
 ) -> StdResult<Uint128> {
     let last_round_id = read_last_round_id(deps.storage, &key)?;
     // check round_id to get last previous price round_id by num_round_back
-    if let Some(round_id) = last_round_id.checked_sub(num_round_back) {
-        let price_data = read_price_data(deps.storage, key, round_id)?;
+    if num_round_back < last_round_id {
+        let price_data = read_price_data(deps.storage, key, last_round_id - num_round_back)?;
         return Ok(price_data.price);
     }
     Err(StdError::generic_err("Not enough history"))
 }
 
-/// Queries contract Config
+/// Queries the time weighted average price over the interval, the
+/// cumulative prices of the rounds make it logarithmic in the rounds stored.
+/// Rounds before the first round migrated to cumulative prices are not part of the history
 pub fn query_get_twap_price(
     deps: Deps,
     env: Env,
@@ -74,8 +88,9 @@ pub fn query_get_twap_price(
         return Err(StdError::generic_err("Interval can't be zero"));
     }
 
-    let base_timestamp = match env.block.time.seconds().checked_sub(interval) {
-        Some(val) => Uint128::from(val),
+    let now = env.block.time.seconds();
+    let base_timestamp = match now.checked_sub(interval) {
+        Some(val) => val,
         None => {
             return Err(StdError::generic_err(
                 "Interval can't be greater than block time",
@@ -84,57 +99,103 @@ pub fn query_get_twap_price(
     };
 
     // get the current data
-    let mut latest_round_ind = read_last_round_id(deps.storage, &key)?;
-    let mut latest_round = read_price_data(deps.storage, key.clone(), latest_round_ind)?;
-    let mut timestamp = Uint128::from(latest_round.timestamp.seconds());
-
-    if latest_round.round_id == 0u64 {
+    let last_round_id = read_last_round_id(deps.storage, &key)?;
+    if last_round_id == 0u64 {
         return Err(StdError::generic_err("Insufficient history"));
     }
 
+    let latest_round = read_price_data(deps.storage, key.clone(), last_round_id)?;
+    if is_stale(deps, &env, &key, &latest_round)? {
+        return Err(StdError::generic_err("Price is stale"));
+    }
+
     // if latest updated timestamp is earlier than target timestamp, return the latest price.
-    if timestamp < base_timestamp || latest_round.round_id == 1u64 {
+    let first_round_id = read_cumulative_start(deps.storage, &key)?;
+    if latest_round.timestamp.seconds() < base_timestamp || last_round_id == first_round_id {
         return Ok(latest_round.price);
     }
 
-    let mut cumulative_time = Uint128::from(env.block.time.seconds()).checked_sub(timestamp)?;
-    let mut weighted_price = latest_round.price.checked_mul(cumulative_time)?;
+    let cumulative_now = cumulative_price_at(deps, &key, &latest_round, now)?;
 
-    loop {
-        // no more item
-        if latest_round_ind == 0 {
-            break;
-        }
+    match find_round_at(deps, &key, first_round_id, last_round_id, base_timestamp)? {
+        Some(base_round) => {
+            let cumulative_base = cumulative_price_at(deps, &key, &base_round, base_timestamp)?;
+            let twap = cumulative_now
+                .checked_sub(cumulative_base)?
+                .checked_div(Uint128::from(interval))?;
 
-        if latest_round.round_id == 1u64 {
-            let twap = weighted_price.checked_div(cumulative_time)?;
-            return Ok(twap);
+            Ok(twap)
         }
+        // the history is shorter than the interval so the available time is used
+        None => {
+            let first_round = read_price_data(deps.storage, key.clone(), first_round_id)?;
+            let cumulative_first = read_cumulative_price(deps.storage, &key, first_round_id)?;
+            let elapsed = now.saturating_sub(first_round.timestamp.seconds());
 
-        latest_round_ind -= 1;
-        latest_round = read_price_data(deps.storage, key.clone(), latest_round_ind)?;
-        let latest_timestamp = Uint128::from(latest_round.timestamp.seconds());
+            // all the rounds were stored in the current block
+            if elapsed == 0u64 {
+                return Ok(latest_round.price);
+            }
+
+            let twap = cumulative_now
+                .checked_sub(cumulative_first)?
+                .checked_div(Uint128::from(elapsed))?;
+
+            Ok(twap)
+        }
+    }
+}
 
-        // time to break
-        if latest_timestamp <= base_timestamp {
-            let delta_timestamp = timestamp.checked_sub(base_timestamp)?;
-            weighted_price =
-                weighted_price.checked_add(latest_round.price.checked_mul(delta_timestamp)?)?;
+/// Queries the stored rounds of a key, latest first unless the order is ascending
+pub fn query_get_price_history(
+    deps: Deps,
+    key: String,
+    start_after_round: Option<u64>,
+    limit: Option<u32>,
+    order: Option<i32>,
+) -> StdResult<Vec<PriceData>> {
+    let order = order.and_then(|val| Order::try_from(val).ok());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let last_round_id = read_last_round_id(deps.storage, &key)?;
 
-            break;
+    // rounds are numbered from one without gaps, so the range is computed directly
+    let round_ids: Vec<u64> = match order {
+        Some(Order::Ascending) => {
+            let start = start_after_round.unwrap_or(0u64).saturating_add(1u64);
+            (start..=last_round_id).take(limit).collect()
+        }
+        _ => {
+            let end = start_after_round
+                .map_or(last_round_id, |round_id| round_id.saturating_sub(1u64))
+                .min(last_round_id);
+            (1u64..=end).rev().take(limit).collect()
         }
+    };
 
-        let delta_timestamp = timestamp.checked_sub(latest_timestamp)?;
-        weighted_price =
-            weighted_price.checked_add(latest_round.price.checked_mul(delta_timestamp)?)?;
+    round_ids
+        .into_iter()
+        .map(|round_id| read_price_data(deps.storage, key.clone(), round_id))
+        .collect()
+}
 
-        cumulative_time = cumulative_time.checked_add(delta_timestamp)?;
-        timestamp = latest_timestamp;
+/// Queries the round that was the latest price at the given time
+pub fn query_get_price_at(deps: Deps, key: String, timestamp: u64) -> StdResult<PriceData> {
+    let last_round_id = read_last_round_id(deps.storage, &key)?;
+    let first_round_id = read_cumulative_start(deps.storage, &key)?;
+
+    if let Some(round) = find_round_at(deps, &key, first_round_id, last_round_id, timestamp)? {
+        return Ok(round);
     }
 
-    let twap = weighted_price.checked_div(Uint128::from(interval))?;
+    // rounds stored before the migration are not ordered by time so they are scanned
+    for round_id in (1u64..first_round_id).rev() {
+        let round = read_price_data(deps.storage, key.clone(), round_id)?;
+        if round.timestamp.seconds() <= timestamp {
+            return Ok(round);
+        }
+    }
 
-    Ok(twap)
+    Err(StdError::generic_err("No price data before timestamp"))
 }
 
 /// Queries latest round id of price
@@ -142,3 +203,107 @@ pub fn query_last_round_id(deps: Deps, key: String) -> StdResult<u64> {
     let last_round_id = read_last_round_id(deps.storage, &key)?;
     Ok(last_round_id)
 }
+
+/// Queries the publishers of a key and the quorum of a round
+pub fn query_publishers(deps: Deps, key: String) -> StdResult<PublishersResponse> {
+    read_publishers(deps.storage, &key)
+}
+
+/// Queries the heartbeat and max deviation of a key
+pub fn query_price_guard(deps: Deps, key: String) -> StdResult<PriceGuardResponse> {
+    read_price_guard(deps.storage, &key)
+}
+
+// a price is stale once it is older than the heartbeat of its key
+fn is_stale(deps: Deps, env: &Env, key: &str, price_data: &PriceData) -> StdResult<bool> {
+    let price_guard = read_price_guard(deps.storage, key)?;
+
+    Ok(price_guard.heartbeat != 0u64
+        && env.block.time.seconds() > price_data.timestamp.seconds() + price_guard.heartbeat)
+}
+
+// the cumulative price of a round extended with its price until the given time
+fn cumulative_price_at(
+    deps: Deps,
+    key: &str,
+    price_data: &PriceData,
+    timestamp: u64,
+) -> StdResult<Uint128> {
+    let elapsed = timestamp.saturating_sub(price_data.timestamp.seconds());
+
+    read_cumulative_price(deps.storage, key, price_data.round_id)?
+        .checked_add(price_data.price.checked_mul(Uint128::from(elapsed))?)
+        .map_err(StdError::from)
+}
+
+// binary searches the latest round with a timestamp not after the given time
+fn find_round_at(
+    deps: Deps,
+    key: &str,
+    first_round_id: u64,
+    last_round_id: u64,
+    timestamp: u64,
+) -> StdResult<Option<PriceData>> {
+    let mut found = None;
+    let mut low = first_round_id;
+    let mut high = last_round_id;
+
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        let round = read_price_data(deps.storage, key.to_string(), mid)?;
+
+        if round.timestamp.seconds() <= timestamp {
+            low = mid + 1;
+            found = Some(round);
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Ok(found)
+}
+
+/// Queries where the price of a key is taken from
+pub fn query_price_source(deps: Deps, key: String) -> StdResult<PriceSource> {
+    read_price_source(deps.storage, &key)
+}
+
+/// Queries the public keys whose signed prices are accepted for a key
+pub fn query_publisher_keys(deps: Deps, key: String) -> StdResult<PublisherKeysResponse> {
+    read_publisher_keys(deps.storage, &key)
+}
+
+/// Queries the price of a key from the configured oracle hub
+pub fn query_oracle_hub_price(deps: Deps, key: &str) -> StdResult<OracleHubPriceResponse> {
+    let oracle_hub_contract = read_config(deps.storage)?
+        .oracle_hub_contract
+        .ok_or_else(|| StdError::generic_err("Oracle hub is not set"))?;
+
+    deps.querier.query_wasm_smart(
+        oracle_hub_contract,
+        &OracleHubQueryMsg::GetPrice {
+            key: key.to_string(),
+        },
+    )
+}
+
+// the latest price of a key, keys sourced from the oracle hub fall back to
+// the latest round when the hub cannot be queried or its price is stale
+fn read_latest_price(deps: Deps, env: &Env, key: &str) -> StdResult<PriceData> {
+    if read_price_source(deps.storage, key)? == PriceSource::OracleHub {
+        if let Ok(hub_price) = query_oracle_hub_price(deps, key) {
+            let price_data = PriceData {
+                round_id: 0u64,
+                price: hub_price.price,
+                timestamp: Timestamp::from_seconds(hub_price.last_updated),
+            };
+
+            if !price_data.price.is_zero() && !is_stale(deps, env, key, &price_data)? {
+                return Ok(price_data);
+            }
+        }
+    }
+
+    let last_round_id = read_last_round_id(deps.storage, key)?;
+    read_price_data(deps.storage, key.to_string(), last_round_id)
+}
@@ -1,17 +1,64 @@
-use cosmwasm_std::{from_slice, to_vec, StdResult, Storage, Timestamp, Uint128};
-use margined_perp::margined_pricefeed::{ConfigResponse, PriceData};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_slice, to_vec, Addr, Order, StdError, StdResult, Storage, Timestamp, Uint128,
+};
+use margined_perp::margined_pricefeed::{
+    ConfigResponse, PriceData, PriceGuardResponse, PriceSource, PublisherKeysResponse,
+    PublishersResponse,
+};
 
 pub static KEY_CONFIG: &[u8] = b"config";
 
 pub static PRICES: &[u8] = b"prices";
 pub static KEY_LAST_ROUND_ID: &[u8] = b"last_round_id";
+pub static PREFIX_PUBLISHERS: &[u8] = b"publishers";
+pub static PREFIX_PENDING_ROUND: &[u8] = b"pending_round";
+pub static PREFIX_ROUND_SUBMISSIONS: &[u8] = b"round_submissions";
+pub static PREFIX_PRICE_GUARD: &[u8] = b"price_guard";
+pub static PREFIX_CUMULATIVE_PRICE: &[u8] = b"cumulative_price";
+pub static PREFIX_CUMULATIVE_START: &[u8] = b"cumulative_start";
+pub static PREFIX_PRICE_SOURCE: &[u8] = b"price_source";
+pub static PREFIX_PUBLISHER_KEYS: &[u8] = b"publisher_keys";
+pub static PREFIX_SIGNED_TIMESTAMP: &[u8] = b"signed_timestamp";
+
+pub const DEFAULT_SUBMISSION_WINDOW: u64 = 60u64;
+pub const MAX_PUBLISHERS: usize = 20usize;
+pub const BPS_DENOMINATOR: u64 = 10_000u64;
+pub const ED25519_PUBKEY_LENGTH: usize = 32usize;
+pub const MAX_LIMIT: u32 = 100;
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MIGRATION_TWAP_WINDOW: u64 = 7 * 86_400; // longest spot price twap interval of the vamms
+pub const MAX_MIGRATED_ROUNDS: u64 = 10_080u64;
+
 pub type Config = ConfigResponse;
+pub type Publishers = PublishersResponse;
+pub type PriceGuard = PriceGuardResponse;
+pub type PublisherKeys = PublisherKeysResponse;
+
+/// Submissions of the round currently being collected for a key
+#[cw_serde]
+#[derive(Default)]
+pub struct PendingRound {
+    pub opened_at: u64,
+    pub round_id: u64,                          // zero until the quorum is reached
+    pub submissions: Vec<(Addr, Uint128, u64)>, // publisher, price and timestamp
+}
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
     storage.set(KEY_CONFIG, &to_vec(config)?);
     Ok(())
 }
 
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    match storage.get(KEY_CONFIG) {
+        Some(data) => from_slice(&data),
+        None => Ok(Config {
+            submission_window: DEFAULT_SUBMISSION_WINDOW,
+            oracle_hub_contract: None,
+        }),
+    }
+}
+
 pub fn store_price_data(
     storage: &mut dyn Storage,
     key: String,
@@ -25,25 +72,162 @@ pub fn store_price_data(
         timestamp: Timestamp::from_seconds(timestamp),
     };
     store_last_round_id(storage, &key, price_data.round_id)?;
+    store_round_price_data(storage, &key, &price_data)
+}
+
+/// Stores the price data of a round that has already been assigned an id
+/// together with the cumulative price up to its timestamp
+pub fn store_round_price_data(
+    storage: &mut dyn Storage,
+    key: &str,
+    price_data: &PriceData,
+) -> StdResult<()> {
+    // rounds must be ordered by time for the twap to search them
+    if price_data.round_id > 1u64 {
+        let previous = read_price_data(storage, key.to_string(), price_data.round_id - 1)?;
+        if price_data.timestamp < previous.timestamp {
+            return Err(StdError::generic_err(
+                "Timestamp is earlier than the previous round",
+            ));
+        }
+    }
+
     storage.set(
         &[PRICES, key.as_bytes(), &price_data.round_id.to_be_bytes()].concat(),
-        &to_vec(&price_data)?,
+        &to_vec(price_data)?,
+    );
+    update_cumulative_price(storage, key, price_data)
+}
+
+// the cumulative price of a round is the sum of price * seconds of all the rounds before it
+fn update_cumulative_price(
+    storage: &mut dyn Storage,
+    key: &str,
+    price_data: &PriceData,
+) -> StdResult<()> {
+    let cumulative_price = if price_data.round_id > read_cumulative_start(storage, key)? {
+        let previous = read_price_data(storage, key.to_string(), price_data.round_id - 1)?;
+        let elapsed = price_data
+            .timestamp
+            .seconds()
+            .saturating_sub(previous.timestamp.seconds());
+
+        read_cumulative_price(storage, key, previous.round_id)?
+            .checked_add(previous.price.checked_mul(Uint128::from(elapsed))?)?
+    } else {
+        Uint128::zero()
+    };
+
+    storage.set(
+        &[
+            PREFIX_CUMULATIVE_PRICE,
+            key.as_bytes(),
+            &price_data.round_id.to_be_bytes(),
+        ]
+        .concat(),
+        &to_vec(&cumulative_price)?,
     );
     Ok(())
 }
 
+pub fn read_cumulative_price(
+    storage: &dyn Storage,
+    key: &str,
+    round_id: u64,
+) -> StdResult<Uint128> {
+    match storage.get(
+        &[
+            PREFIX_CUMULATIVE_PRICE,
+            key.as_bytes(),
+            &round_id.to_be_bytes(),
+        ]
+        .concat(),
+    ) {
+        None => Err(StdError::generic_err(format!(
+            "No cumulative price for key: {} round: {}",
+            key, round_id
+        ))),
+        Some(data) => from_slice(&data),
+    }
+}
+
+/// Returns the first round of a key with a cumulative price, which is later than
+/// the first round for keys that had rounds before cumulative prices were tracked
+pub fn read_cumulative_start(storage: &dyn Storage, key: &str) -> StdResult<u64> {
+    match storage.get(&[PREFIX_CUMULATIVE_START, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(1u64),
+    }
+}
+
+/// Tracks the cumulative prices of the keys stored before they were, starting from the
+/// rounds inside the longest twap window so the twap is available straight away
+pub fn migrate_cumulative_prices(storage: &mut dyn Storage, now: u64) -> StdResult<()> {
+    // every key with prices has a last round id stored under its prefix
+    let mut end = KEY_LAST_ROUND_ID.to_vec();
+    if let Some(last) = end.last_mut() {
+        *last += 1;
+    }
+
+    let keys = storage
+        .range(Some(KEY_LAST_ROUND_ID), Some(&end), Order::Ascending)
+        .map(|(key, data)| {
+            let key = String::from_utf8(key[KEY_LAST_ROUND_ID.len()..].to_vec())?;
+            let last_round_id: u64 = from_slice(&data)?;
+            Ok((key, last_round_id))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let window_start = now.saturating_sub(MIGRATION_TWAP_WINDOW);
+    for (key, last_round_id) in keys {
+        // keys that already track cumulative prices are skipped
+        if last_round_id == 0u64 || read_cumulative_price(storage, &key, last_round_id).is_ok() {
+            continue;
+        }
+
+        // walks back until the round before the window, stopping at the first round that
+        // is out of order as earlier rounds were not checked to be ordered by time
+        let mut start_round_id = last_round_id;
+        let mut start_timestamp = read_price_data(storage, key.clone(), last_round_id)?
+            .timestamp
+            .seconds();
+        while start_round_id > 1u64
+            && start_timestamp > window_start
+            && last_round_id - start_round_id < MAX_MIGRATED_ROUNDS
+        {
+            let previous = read_price_data(storage, key.clone(), start_round_id - 1)?;
+            if previous.timestamp.seconds() > start_timestamp {
+                break;
+            }
+
+            start_round_id -= 1;
+            start_timestamp = previous.timestamp.seconds();
+        }
+
+        storage.set(
+            &[PREFIX_CUMULATIVE_START, key.as_bytes()].concat(),
+            &to_vec(&start_round_id)?,
+        );
+        for round_id in start_round_id..=last_round_id {
+            let price_data = read_price_data(storage, key.clone(), round_id)?;
+            update_cumulative_price(storage, &key, &price_data)?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn read_price_data(storage: &dyn Storage, key: String, round_id: u64) -> StdResult<PriceData> {
     match storage.get(&[PRICES, key.as_bytes(), &round_id.to_be_bytes()].concat()) {
-        None => Ok(PriceData::default()),
+        None => Err(StdError::generic_err(format!(
+            "No price data for key: {} round: {}",
+            key, round_id
+        ))),
         Some(data) => from_slice(&data),
     }
 }
 
-pub fn store_last_round_id(
-    storage: &mut dyn Storage,
-    key: &String,
-    round_id: u64,
-) -> StdResult<()> {
+pub fn store_last_round_id(storage: &mut dyn Storage, key: &str, round_id: u64) -> StdResult<()> {
     storage.set(
         &[KEY_LAST_ROUND_ID, key.as_bytes()].concat(),
         &to_vec(&round_id)?,
@@ -51,7 +235,7 @@ pub fn store_last_round_id(
     Ok(())
 }
 
-pub fn read_last_round_id(storage: &dyn Storage, key: &String) -> StdResult<u64> {
+pub fn read_last_round_id(storage: &dyn Storage, key: &str) -> StdResult<u64> {
     Ok(
         match storage.get(&[KEY_LAST_ROUND_ID, key.as_bytes()].concat()) {
             Some(data) => from_slice(&data)?,
@@ -59,3 +243,156 @@ pub fn read_last_round_id(storage: &dyn Storage, key: &String) -> StdResult<u64>
         },
     )
 }
+
+pub fn store_publishers(
+    storage: &mut dyn Storage,
+    key: &str,
+    publishers: &Publishers,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_PUBLISHERS, key.as_bytes()].concat(),
+        &to_vec(publishers)?,
+    );
+    Ok(())
+}
+
+pub fn read_publishers(storage: &dyn Storage, key: &str) -> StdResult<Publishers> {
+    match storage.get(&[PREFIX_PUBLISHERS, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Publishers::default()),
+    }
+}
+
+pub fn store_pending_round(
+    storage: &mut dyn Storage,
+    key: &str,
+    pending_round: &PendingRound,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_PENDING_ROUND, key.as_bytes()].concat(),
+        &to_vec(pending_round)?,
+    );
+    Ok(())
+}
+
+pub fn read_pending_round(storage: &dyn Storage, key: &str) -> StdResult<PendingRound> {
+    match storage.get(&[PREFIX_PENDING_ROUND, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(PendingRound::default()),
+    }
+}
+
+pub fn remove_pending_round(storage: &mut dyn Storage, key: &str) {
+    storage.remove(&[PREFIX_PENDING_ROUND, key.as_bytes()].concat());
+}
+
+pub fn store_round_submissions(
+    storage: &mut dyn Storage,
+    key: &str,
+    round_id: u64,
+    submissions: u64,
+) -> StdResult<()> {
+    storage.set(
+        &[
+            PREFIX_ROUND_SUBMISSIONS,
+            key.as_bytes(),
+            &round_id.to_be_bytes(),
+        ]
+        .concat(),
+        &to_vec(&submissions)?,
+    );
+    Ok(())
+}
+
+// prices appended directly by the executor have no publisher submissions
+pub fn read_round_submissions(storage: &dyn Storage, key: &str, round_id: u64) -> StdResult<u64> {
+    match storage.get(
+        &[
+            PREFIX_ROUND_SUBMISSIONS,
+            key.as_bytes(),
+            &round_id.to_be_bytes(),
+        ]
+        .concat(),
+    ) {
+        Some(data) => from_slice(&data),
+        None => Ok(0u64),
+    }
+}
+
+pub fn store_price_guard(
+    storage: &mut dyn Storage,
+    key: &str,
+    price_guard: &PriceGuard,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_PRICE_GUARD, key.as_bytes()].concat(),
+        &to_vec(price_guard)?,
+    );
+    Ok(())
+}
+
+pub fn read_price_guard(storage: &dyn Storage, key: &str) -> StdResult<PriceGuard> {
+    match storage.get(&[PREFIX_PRICE_GUARD, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(PriceGuard::default()),
+    }
+}
+
+pub fn store_price_source(
+    storage: &mut dyn Storage,
+    key: &str,
+    price_source: &PriceSource,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_PRICE_SOURCE, key.as_bytes()].concat(),
+        &to_vec(price_source)?,
+    );
+    Ok(())
+}
+
+pub fn read_price_source(storage: &dyn Storage, key: &str) -> StdResult<PriceSource> {
+    match storage.get(&[PREFIX_PRICE_SOURCE, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(PriceSource::Rounds),
+    }
+}
+
+pub fn store_publisher_keys(
+    storage: &mut dyn Storage,
+    key: &str,
+    publisher_keys: &PublisherKeys,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_PUBLISHER_KEYS, key.as_bytes()].concat(),
+        &to_vec(publisher_keys)?,
+    );
+    Ok(())
+}
+
+pub fn read_publisher_keys(storage: &dyn Storage, key: &str) -> StdResult<PublisherKeys> {
+    match storage.get(&[PREFIX_PUBLISHER_KEYS, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(PublisherKeys::default()),
+    }
+}
+
+pub fn store_signed_timestamp(
+    storage: &mut dyn Storage,
+    key: &str,
+    pubkey: &[u8],
+    timestamp: u64,
+) -> StdResult<()> {
+    storage.set(
+        &[PREFIX_SIGNED_TIMESTAMP, key.as_bytes(), pubkey].concat(),
+        &to_vec(&timestamp)?,
+    );
+    Ok(())
+}
+
+// timestamp of the last price of the key accepted from the publisher key
+pub fn read_signed_timestamp(storage: &dyn Storage, key: &str, pubkey: &[u8]) -> StdResult<u64> {
+    match storage.get(&[PREFIX_SIGNED_TIMESTAMP, key.as_bytes(), pubkey].concat()) {
+        Some(data) => from_slice(&data),
+        None => Ok(0u64),
+    }
+}
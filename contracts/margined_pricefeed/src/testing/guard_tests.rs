@@ -0,0 +1,196 @@
+use crate::contract::{execute, instantiate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, PriceDetailResponse, PriceGuardResponse, QueryMsg,
+};
+
+#[test]
+fn test_stale_price_queries_fail() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // missing rounds are an error rather than a zero price
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res.to_string(),
+        "Generic error: No price data for key: ETHUSD round: 0"
+    );
+
+    let msg = ExecuteMsg::UpdatePriceGuard {
+        key: "ETHUSD".to_string(),
+        heartbeat: Some(3_600u64),
+        max_deviation_bps: None,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+    assert_eq!(result.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AppendPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(500_000_000u128),
+        timestamp: env.block.time.seconds(),
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(3_600);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(500_000_000u128));
+
+    env.block.time = env.block.time.plus_seconds(1);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(res.to_string(), "Generic error: Price is stale");
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 900,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(res.to_string(), "Generic error: Price is stale");
+
+    // the detail query flags the staleness instead of failing
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetPriceDetail {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price_detail: PriceDetailResponse = from_binary(&res).unwrap();
+    assert_eq!(price_detail.price, Uint128::from(500_000_000u128));
+    assert!(price_detail.is_stale);
+}
+
+#[test]
+fn test_price_deviation_is_bounded() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // at most a 10% move between rounds
+    let msg = ExecuteMsg::UpdatePriceGuard {
+        key: "ETHUSD".to_string(),
+        heartbeat: None,
+        max_deviation_bps: Some(1_000u64),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceGuard {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price_guard: PriceGuardResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        price_guard,
+        PriceGuardResponse {
+            heartbeat: 0u64,
+            max_deviation_bps: 1_000u64,
+        }
+    );
+
+    // the first round has nothing to be compared against
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices: vec![
+            Uint128::from(500_000_000u128),
+            Uint128::from(550_000_000u128),
+        ],
+        timestamps: vec![1_000_000, 1_000_001],
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AppendPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(494_999_999u128),
+        timestamp: 1_000_002,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Price deviation is too large"
+    );
+
+    // only the owner can force the price through
+    let msg = ExecuteMsg::ForceAppendPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(300_000_000u128),
+        timestamp: 1_000_002,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(result.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(300_000_000u128));
+}
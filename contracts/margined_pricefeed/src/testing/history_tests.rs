@@ -0,0 +1,255 @@
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::{store_last_round_id, KEY_CONFIG, PRICES};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, to_vec, Order, Timestamp, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, PriceData, QueryMsg,
+};
+
+#[test]
+fn test_price_history_pagination() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // keys sharing a prefix must not leak into each other's history
+    let msg = ExecuteMsg::AppendPrice {
+        key: "ETH".to_string(),
+        price: Uint128::from(100_000_000u128),
+        timestamp: 1_000_000,
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices: (1..=5u128)
+            .map(|index| Uint128::from(index * 100_000_000u128))
+            .collect(),
+        timestamps: (0..5u64).map(|index| 1_000_000 + index * 60).collect(),
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // latest rounds first by default
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: "ETHUSD".to_string(),
+            start_after_round: None,
+            limit: Some(2u32),
+            order: None,
+        },
+    )
+    .unwrap();
+    let history: Vec<PriceData> = from_binary(&res).unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|data| data.round_id)
+            .collect::<Vec<u64>>(),
+        vec![5u64, 4u64]
+    );
+    assert_eq!(history[0].price, Uint128::from(500_000_000u128));
+    assert_eq!(history[0].timestamp, Timestamp::from_seconds(1_000_240));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: "ETHUSD".to_string(),
+            start_after_round: Some(4u64),
+            limit: None,
+            order: Some(Order::Descending.into()),
+        },
+    )
+    .unwrap();
+    let history: Vec<PriceData> = from_binary(&res).unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|data| data.round_id)
+            .collect::<Vec<u64>>(),
+        vec![3u64, 2u64, 1u64]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: "ETHUSD".to_string(),
+            start_after_round: Some(2u64),
+            limit: Some(2u32),
+            order: Some(Order::Ascending.into()),
+        },
+    )
+    .unwrap();
+    let history: Vec<PriceData> = from_binary(&res).unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|data| data.round_id)
+            .collect::<Vec<u64>>(),
+        vec![3u64, 4u64]
+    );
+
+    // paging past the last round returns nothing
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: "ETHUSD".to_string(),
+            start_after_round: Some(5u64),
+            limit: None,
+            order: Some(Order::Ascending.into()),
+        },
+    )
+    .unwrap();
+    let history: Vec<PriceData> = from_binary(&res).unwrap();
+    assert!(history.is_empty());
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: "ETH".to_string(),
+            start_after_round: None,
+            limit: None,
+            order: None,
+        },
+    )
+    .unwrap();
+    let history: Vec<PriceData> = from_binary(&res).unwrap();
+    assert_eq!(history.len(), 1usize);
+    assert_eq!(history[0].price, Uint128::from(100_000_000u128));
+}
+
+#[test]
+fn test_price_at_timestamp() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices: vec![
+            Uint128::from(400_000_000u128),
+            Uint128::from(410_000_000u128),
+            Uint128::from(420_000_000u128),
+        ],
+        timestamps: vec![1_000_000, 1_000_060, 1_000_120],
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceAt {
+            key: "ETHUSD".to_string(),
+            timestamp: 999_999,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res.to_string(),
+        "Generic error: No price data before timestamp"
+    );
+
+    // the round in effect at the timestamp is returned
+    let cases = [
+        (1_000_000u64, 1u64),
+        (1_000_059u64, 1u64),
+        (1_000_060u64, 2u64),
+        (1_000_119u64, 2u64),
+        (2_000_000u64, 3u64),
+    ];
+    for (timestamp, round_id) in cases {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetPriceAt {
+                key: "ETHUSD".to_string(),
+                timestamp,
+            },
+        )
+        .unwrap();
+        let price_data: PriceData = from_binary(&res).unwrap();
+        assert_eq!(price_data.round_id, round_id);
+    }
+}
+
+#[test]
+fn test_price_at_timestamp_of_legacy_rounds() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+
+    // rounds stored before the migration, the second round is out of order
+    let start = env.block.time.seconds();
+    let timestamps = [0u64, 100, 10, 20, 30, 40, 50];
+    for (index, offset) in timestamps.into_iter().enumerate() {
+        let price_data = PriceData {
+            round_id: index as u64 + 1,
+            price: Uint128::from(400_000_000u128),
+            timestamp: Timestamp::from_seconds(start + offset),
+        };
+        deps.storage.set(
+            &[PRICES, b"ETHUSD", &price_data.round_id.to_be_bytes()].concat(),
+            &to_vec(&price_data).unwrap(),
+        );
+    }
+    store_last_round_id(&mut deps.storage, "ETHUSD", 7u64).unwrap();
+    deps.storage.set(KEY_CONFIG, b"{}");
+
+    env.block.time = env.block.time.plus_seconds(60);
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    // the ordered rounds are searched and the earlier ones are scanned
+    let cases = [(start + 15, 3u64), (start + 55, 7u64), (start + 5, 1u64)];
+    for (timestamp, round_id) in cases {
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetPriceAt {
+                key: "ETHUSD".to_string(),
+                timestamp,
+            },
+        )
+        .unwrap();
+        let price_data: PriceData = from_binary(&res).unwrap();
+        assert_eq!(price_data.round_id, round_id);
+    }
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetPriceAt {
+            key: "ETHUSD".to_string(),
+            timestamp: start - 1,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res.to_string(),
+        "Generic error: No price data before timestamp"
+    );
+}
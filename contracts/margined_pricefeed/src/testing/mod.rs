@@ -1 +1,7 @@
+mod guard_tests;
+mod history_tests;
+mod oracle_hub_tests;
+mod publisher_tests;
+mod signed_price_tests;
 mod tests;
+mod twap_tests;
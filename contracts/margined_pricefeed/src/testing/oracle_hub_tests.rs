@@ -0,0 +1,150 @@
+use cosmwasm_std::{Addr, Uint128};
+use margined_perp::margined_pricefeed::{ExecuteMsg, InstantiateMsg, PriceSource};
+use margined_utils::contracts::helpers::PricefeedController;
+use margined_utils::create_entry_points_testing;
+use margined_utils::cw_multi_test::{App, Executor};
+use mock_oracle_hub::contract::{
+    ExecuteMsg as OracleHubExecuteMsg, InstantiateMsg as OracleHubInstantiateMsg,
+};
+
+#[test]
+fn test_prices_from_oracle_hub() {
+    let mut router = App::default();
+    let owner = Addr::unchecked("owner");
+    let executor = Addr::unchecked("executor");
+    let alice = Addr::unchecked("alice");
+
+    let hub_code_id = router.store_code(Box::new(create_entry_points_testing!(mock_oracle_hub)));
+    let oracle_hub = router
+        .instantiate_contract(
+            hub_code_id,
+            owner.clone(),
+            &OracleHubInstantiateMsg {},
+            &[],
+            "oracle_hub",
+            None,
+        )
+        .unwrap();
+
+    let code_id = router.store_code(Box::new(create_entry_points_testing!(crate)));
+    let pricefeed = PricefeedController(
+        router
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    oracle_hub_contract: oracle_hub.to_string(),
+                },
+                &[],
+                "pricefeed",
+                None,
+            )
+            .unwrap(),
+    );
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: executor.to_string(),
+    };
+    router
+        .execute_contract(owner.clone(), pricefeed.addr(), &msg, &[])
+        .unwrap();
+
+    let now = router.block_info().time.seconds();
+
+    let msg = pricefeed
+        .append_price("ETHUSD".to_string(), Uint128::from(500u128), now - 10)
+        .unwrap();
+    router.execute(executor, msg).unwrap();
+
+    let msg = OracleHubExecuteMsg::SetPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(600u128),
+        last_updated: now,
+    };
+    router
+        .execute_contract(owner.clone(), oracle_hub.clone(), &msg, &[])
+        .unwrap();
+
+    // keys use the appended rounds until they are switched to the oracle hub
+    let price = pricefeed
+        .get_price(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(price, Uint128::from(500u128));
+
+    let msg = pricefeed.sync_price("ETHUSD".to_string()).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Generic error: Price source is not the oracle hub"
+    );
+
+    let msg = pricefeed
+        .update_price_source("ETHUSD".to_string(), PriceSource::OracleHub)
+        .unwrap();
+    let err = router.execute(alice.clone(), msg.clone()).unwrap_err();
+    assert_eq!(err.root_cause().to_string(), "Caller is not admin");
+    router.execute(owner.clone(), msg).unwrap();
+
+    let source = pricefeed
+        .price_source(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(source, PriceSource::OracleHub);
+
+    let price = pricefeed
+        .get_price(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(price, Uint128::from(600u128));
+
+    let price_detail = pricefeed
+        .price_detail(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(price_detail.price, Uint128::from(600u128));
+    assert_eq!(price_detail.submissions, 0u64);
+
+    // anyone can record the hub price as a round, but only once per update
+    let msg = pricefeed.sync_price("ETHUSD".to_string()).unwrap();
+    router.execute(alice.clone(), msg.clone()).unwrap();
+
+    let err = router.execute(alice, msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Generic error: Oracle hub price has not been updated"
+    );
+
+    let price = pricefeed
+        .get_previous_price(&router.wrap(), "ETHUSD".to_string(), 0u64)
+        .unwrap();
+    assert_eq!(price, Uint128::from(600u128));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(60);
+        block.height += 1;
+    });
+
+    let msg = OracleHubExecuteMsg::SetPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(650u128),
+        last_updated: now + 60,
+    };
+    router
+        .execute_contract(owner.clone(), oracle_hub.clone(), &msg, &[])
+        .unwrap();
+
+    let price = pricefeed
+        .get_price(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(price, Uint128::from(650u128));
+
+    // the latest round is used when the hub has no price
+    let msg = OracleHubExecuteMsg::RemovePrice {
+        key: "ETHUSD".to_string(),
+    };
+    router
+        .execute_contract(owner, oracle_hub, &msg, &[])
+        .unwrap();
+
+    let price = pricefeed
+        .get_price(&router.wrap(), "ETHUSD".to_string())
+        .unwrap();
+    assert_eq!(price, Uint128::from(600u128));
+}
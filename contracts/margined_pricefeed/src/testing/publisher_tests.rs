@@ -0,0 +1,230 @@
+use crate::contract::{execute, instantiate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Addr, Timestamp, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, PriceDetailResponse, PublishersResponse, QueryMsg,
+};
+
+#[test]
+fn test_median_of_publisher_submissions() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // only the owner can set publishers
+    let msg = ExecuteMsg::UpdatePublishers {
+        key: "ETHUSD".to_string(),
+        publishers: vec![
+            "publisher0".to_string(),
+            "publisher1".to_string(),
+            "publisher2".to_string(),
+            "publisher3".to_string(),
+        ],
+        quorum: 3u64,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(result.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPublishers {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let publishers: PublishersResponse = from_binary(&res).unwrap();
+    assert_eq!(publishers.publishers.len(), 4usize);
+    assert_eq!(publishers.publishers[0], Addr::unchecked("publisher0"));
+    assert_eq!(publishers.quorum, 3u64);
+
+    // the executor can no longer set the price of the key
+    let msg = ExecuteMsg::AppendPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(500_000_000u128),
+        timestamp: 1_000_000,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Price is set by publishers"
+    );
+
+    let msg = ExecuteMsg::SubmitPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(500_000_000u128),
+        timestamp: 1_000_000,
+    };
+    let info = mock_info("not_publisher", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Unauthorized");
+
+    // no price is stored until the quorum is reached
+    let submissions = [
+        ("publisher0", 500_000_000u128),
+        ("publisher1", 900_000_000u128),
+    ];
+    for (publisher, price) in submissions {
+        let msg = ExecuteMsg::SubmitPrice {
+            key: "ETHUSD".to_string(),
+            price: Uint128::from(price),
+            timestamp: 1_000_000,
+        };
+        let info = mock_info(publisher, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::SubmitPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(500_000_000u128),
+        timestamp: 1_000_000,
+    };
+    let info = mock_info("publisher0", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Price already submitted for this round"
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetLastRoundId {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let round_id: u64 = from_binary(&res).unwrap();
+    assert_eq!(round_id, 0u64);
+
+    let msg = ExecuteMsg::SubmitPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(600_000_000u128),
+        timestamp: 1_000_001,
+    };
+    let info = mock_info("publisher2", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceDetail {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price_detail: PriceDetailResponse = from_binary(&res).unwrap();
+    assert_eq!(price_detail.price, Uint128::from(600_000_000u128));
+    assert_eq!(price_detail.timestamp, Timestamp::from_seconds(1_000_001));
+    assert_eq!(price_detail.submissions, 3u64);
+
+    // a late submission within the window updates the same round
+    let msg = ExecuteMsg::SubmitPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(700_000_000u128),
+        timestamp: 1_000_001,
+    };
+    let info = mock_info("publisher3", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceDetail {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price_detail: PriceDetailResponse = from_binary(&res).unwrap();
+    assert_eq!(price_detail.price, Uint128::from(650_000_000u128));
+    assert_eq!(price_detail.submissions, 4u64);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetLastRoundId {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let round_id: u64 = from_binary(&res).unwrap();
+    assert_eq!(round_id, 1u64);
+
+    // once the window has closed submissions open a new round
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60);
+
+    let msg = ExecuteMsg::SubmitPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(800_000_000u128),
+        timestamp: 1_000_060,
+    };
+    let info = mock_info("publisher0", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(650_000_000u128));
+}
+
+#[test]
+fn test_update_publishers_validation() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdatePublishers {
+        key: "ETHUSD".to_string(),
+        publishers: vec!["publisher0".to_string(), "publisher0".to_string()],
+        quorum: 1u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Generic error: Duplicate publisher");
+
+    for quorum in [0u64, 3u64] {
+        let msg = ExecuteMsg::UpdatePublishers {
+            key: "ETHUSD".to_string(),
+            publishers: vec!["publisher0".to_string(), "publisher1".to_string()],
+            quorum,
+        };
+        let info = mock_info("addr0000", &[]);
+        let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(result.to_string(), "Generic error: Invalid quorum");
+    }
+
+    let msg = ExecuteMsg::UpdateConfig {
+        submission_window: Some(0u64),
+        oracle_hub_contract: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Submission window must not be zero"
+    );
+}
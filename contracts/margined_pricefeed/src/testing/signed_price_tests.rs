@@ -0,0 +1,269 @@
+use crate::contract::{execute, instantiate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Binary, Timestamp, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, PriceDetailResponse, PublisherKeysResponse, QueryMsg, SignedPrice,
+};
+
+// ed25519 keys derived from the seeds [1u8; 32], [2u8; 32] and [3u8; 32]
+const PUBKEY_0: &str = "iojj3XQJ8ZX9UtstPLpdcspnCb8dlBIb83SIAbQPb1w=";
+const PUBKEY_1: &str = "gTl3Dqh9F19Wo1Rmw0x+zMuNipG07jeiXfYPW4/Js5Q=";
+const PUBKEY_2: &str = "7UkoxijRwsbq6QM4kFmVYSlZJzpcY/k2NsFGFKyHN9E=";
+
+fn signed_price(pubkey: &str, price: u128, timestamp: u64, signature: &str) -> SignedPrice {
+    SignedPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(price),
+        timestamp,
+        pubkey: Binary::from_base64(pubkey).unwrap(),
+        signature: Binary::from_base64(signature).unwrap(),
+    }
+}
+
+#[test]
+fn test_signed_prices_are_relayed() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // only the owner can register publisher keys
+    let msg = ExecuteMsg::UpdatePublisherKeys {
+        key: "ETHUSD".to_string(),
+        pubkeys: vec![
+            Binary::from_base64(PUBKEY_0).unwrap(),
+            Binary::from_base64(PUBKEY_1).unwrap(),
+        ],
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(result.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPublisherKeys {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let publisher_keys: PublisherKeysResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        publisher_keys.pubkeys,
+        vec![
+            Binary::from_base64(PUBKEY_0).unwrap(),
+            Binary::from_base64(PUBKEY_1).unwrap(),
+        ]
+    );
+
+    // prices signed by unregistered keys are rejected
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_2,
+            500_000_000u128,
+            1_000_000,
+            "MQ7uqgjg0FML5flhKFUIHHSfZeecoJ42qO8ALFG752S2KNfaEui1XR+qQmHPq0RWyrF3EHKnyzfUzgJKHGBxDA==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Unauthorized");
+
+    // the signature covers the price
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_0,
+            600_000_000u128,
+            1_000_000,
+            "1oycWjtD/57O3/WBdt140LtydAegfu3OYAGCGgfJ2JTT8il52sWpfIp3acmNyVGqbK9Yfh6N2qexzfUredY9Dg==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Generic error: Invalid signature");
+
+    // anyone can relay signed prices, each is stored as a round
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![
+            signed_price(
+                PUBKEY_0,
+                500_000_000u128,
+                1_000_000,
+                "1oycWjtD/57O3/WBdt140LtydAegfu3OYAGCGgfJ2JTT8il52sWpfIp3acmNyVGqbK9Yfh6N2qexzfUredY9Dg==",
+            ),
+            signed_price(
+                PUBKEY_0,
+                510_000_000u128,
+                1_000_100,
+                "jsQ6/6b8P79c0669GtDCNwFtjYoAM2fAiHQ5WRcNBWdsqZoY/nNEYsWub/E1tXjDgpy8bSRxBZLZfysosSnTBw==",
+            ),
+        ],
+    };
+    let info = mock_info("relayer", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetLastRoundId {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let last_round_id: u64 = from_binary(&res).unwrap();
+    assert_eq!(last_round_id, 2u64);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceDetail {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let detail: PriceDetailResponse = from_binary(&res).unwrap();
+    assert_eq!(detail.price, Uint128::from(510_000_000u128));
+    assert_eq!(detail.timestamp, Timestamp::from_seconds(1_000_100));
+
+    // replays and older prices of the same key are rejected
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_0,
+            510_000_000u128,
+            1_000_100,
+            "jsQ6/6b8P79c0669GtDCNwFtjYoAM2fAiHQ5WRcNBWdsqZoY/nNEYsWub/E1tXjDgpy8bSRxBZLZfysosSnTBw==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Signed price has already been submitted"
+    );
+
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_0,
+            505_000_000u128,
+            1_000_050,
+            "7jKIwLra7N05AuATOucxHXxfLPlfJGAhltTtd7mstWqIo8s367BsVFdcA7wwPcCy6DF8oo9dNG2zU/MehAS+Dg==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Signed price is older than the last one"
+    );
+
+    // other publisher keys are tracked separately
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_1,
+            520_000_000u128,
+            1_000_200,
+            "kp6q15p0xWn+NcuW1CZLzrjtkclN00NawLYPXFcVPNju2kdM3gVuBtNAuCRmbl1YcV3784QEIb8A11FPf4fqBA==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPrice {
+            key: "ETHUSD".to_string(),
+        },
+    )
+    .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(520_000_000u128));
+}
+
+#[test]
+fn test_invalid_publisher_keys() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdatePublisherKeys {
+        key: "ETHUSD".to_string(),
+        pubkeys: vec![Binary::from(vec![1u8; 33])],
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Generic error: Invalid public key");
+
+    let msg = ExecuteMsg::UpdatePublisherKeys {
+        key: "ETHUSD".to_string(),
+        pubkeys: vec![
+            Binary::from_base64(PUBKEY_0).unwrap(),
+            Binary::from_base64(PUBKEY_0).unwrap(),
+        ],
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Generic error: Duplicate publisher key");
+}
+
+#[test]
+fn test_signed_prices_are_bound_to_the_deployment() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdatePublisherKeys {
+        key: "ETHUSD".to_string(),
+        pubkeys: vec![Binary::from_base64(PUBKEY_0).unwrap()],
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // a price signed for another contract is rejected
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_0,
+            500_000_000u128,
+            1_000_000,
+            "99HieUXsKLDarVGpo7pFG6T8GsyrSw4SlAyUX+3t1mscBUT2x+mnGYbctiw6Dp6ze+rREvggpNG0xR1AXrsaCA==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(result.to_string(), "Generic error: Invalid signature");
+
+    // keys with publishers only take the median of their submissions
+    let msg = ExecuteMsg::UpdatePublishers {
+        key: "ETHUSD".to_string(),
+        publishers: vec!["publisher0".to_string(), "publisher1".to_string()],
+        quorum: 2u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitSignedPrices {
+        payloads: vec![signed_price(
+            PUBKEY_0,
+            500_000_000u128,
+            1_000_000,
+            "1oycWjtD/57O3/WBdt140LtydAegfu3OYAGCGgfJ2JTT8il52sWpfIp3acmNyVGqbK9Yfh6N2qexzfUredY9Dg==",
+        )],
+    };
+    let info = mock_info("relayer", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Price is set by publishers"
+    );
+}
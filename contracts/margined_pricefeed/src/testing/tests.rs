@@ -16,7 +16,13 @@ fn test_instantiation() {
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!(config, ConfigResponse {});
+    assert_eq!(
+        config,
+        ConfigResponse {
+            submission_window: 60u64,
+            oracle_hub_contract: Some(Addr::unchecked("oracle_hub0000")),
+        }
+    );
 }
 
 #[test]
@@ -0,0 +1,210 @@
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::{
+    read_cumulative_price, read_cumulative_start, store_last_round_id, KEY_CONFIG, PRICES,
+};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, to_vec, Timestamp, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, PriceData, QueryMsg,
+};
+
+#[test]
+fn test_twap_over_many_rounds() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // a round every 10 seconds with the price going up by one each round
+    let start = env.block.time.seconds();
+    let prices = (1..=50u128)
+        .map(|index| Uint128::from(index * 1_000_000u128))
+        .collect();
+    let timestamps = (0..50u64).map(|index| start + index * 10).collect();
+
+    env.block.time = env.block.time.plus_seconds(500);
+
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices,
+        timestamps,
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 100,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(45_500_000u128));
+
+    // the base time falls in the middle of a round
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 105,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(45_238_095u128));
+
+    // rounds cannot go back in time
+    let msg = ExecuteMsg::AppendPrice {
+        key: "ETHUSD".to_string(),
+        price: Uint128::from(50_000_000u128),
+        timestamp: start + 480,
+    };
+    let info = mock_info("addr0001", &[]);
+    let result = execute(deps.as_mut(), env, info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: Timestamp is earlier than the previous round"
+    );
+}
+
+#[test]
+fn test_twap_of_rounds_in_the_current_block() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateExecutor {
+        executor: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // no time has elapsed since the first round so the latest price is the twap
+    let now = env.block.time.seconds();
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices: vec![
+            Uint128::from(1_500_000_000u128),
+            Uint128::from(1_510_000_000u128),
+        ],
+        timestamps: vec![now, now],
+    };
+    let info = mock_info("addr0001", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 3_600,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(1_510_000_000u128));
+}
+
+#[test]
+fn test_migrate_cumulative_prices() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+
+    // rounds stored before cumulative prices were tracked, the first ETHUSD round is
+    // out of order and the first BTCUSD rounds are older than the twap window
+    let start = env.block.time.seconds();
+    let rounds = [
+        ("ETHUSD", 1u64, 390_000_000u128, start + 5),
+        ("ETHUSD", 2u64, 400_000_000u128, start),
+        ("ETHUSD", 3u64, 405_000_000u128, start + 15),
+        ("ETHUSD", 4u64, 410_000_000u128, start + 30),
+        ("BTCUSD", 1u64, 20_000_000_000u128, start - 1_209_600),
+        ("BTCUSD", 2u64, 21_000_000_000u128, start - 691_200),
+        ("BTCUSD", 3u64, 22_000_000_000u128, start + 30),
+    ];
+    for (key, round_id, price, timestamp) in rounds {
+        let price_data = PriceData {
+            round_id,
+            price: Uint128::from(price),
+            timestamp: Timestamp::from_seconds(timestamp),
+        };
+        deps.storage.set(
+            &[PRICES, key.as_bytes(), &round_id.to_be_bytes()].concat(),
+            &to_vec(&price_data).unwrap(),
+        );
+        store_last_round_id(&mut deps.storage, key, round_id).unwrap();
+    }
+    deps.storage.set(KEY_CONFIG, b"{}");
+
+    env.block.time = env.block.time.plus_seconds(45);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 45,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res.to_string(),
+        "Generic error: No cumulative price for key: ETHUSD round: 4"
+    );
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    // the ordered rounds are migrated so the twap is available straight away
+    assert!(read_cumulative_price(&deps.storage, "ETHUSD", 1u64).is_err());
+    assert_eq!(
+        read_cumulative_start(&deps.storage, "ETHUSD").unwrap(),
+        2u64
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 45,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(405_000_000u128));
+
+    // only the round before the window is kept from the older rounds
+    assert_eq!(
+        read_cumulative_start(&deps.storage, "BTCUSD").unwrap(),
+        2u64
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetTwapPrice {
+            key: "BTCUSD".to_string(),
+            interval: 60,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(21_250_000_000u128));
+}
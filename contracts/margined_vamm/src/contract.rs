@@ -1,7 +1,8 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Uint128,
 };
 use cw2::set_contract_version;
 use cw_controllers::{Admin, Hooks};
@@ -9,24 +10,32 @@ use margined_common::{
     integer::Integer,
     validate::{validate_assets, validate_decimal_places, validate_non_fraction, validate_ratio},
 };
-use margined_perp::margined_vamm::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use margined_perp::margined_vamm::{
+    ExecuteMsg, FundingFormula, InstantiateMsg, MarketStatus, MigrateMsg, PricingCurve, QueryMsg,
+};
 use margined_utils::contracts::helpers::PricefeedController;
 
 use crate::{
     error::ContractError,
-    handle::{add_whitelist, migrate_liquidity, remove_whitelist, repeg_price},
+    handle::{
+        add_whitelist, adjust_liquidity, auto_repeg, migrate_liquidity, prune_snapshots,
+        remove_whitelist, repeg_price,
+    },
     // handle::change_reserve,
-    state::read_config,
     utils::{TwapCalcOption, TwapInputAsset},
 };
 use crate::{
-    handle::{set_open, settle_funding, swap_input, swap_output, update_config, update_owner},
+    handle::{set_status, settle_funding, swap_input, swap_output, update_config, update_owner},
     query::{
-        query_calc_fee, query_config, query_input_amount, query_input_price,
-        query_is_over_fluctuation_limit, query_is_over_spread_limit, query_output_amount,
-        query_output_price, query_owner, query_spot_price, query_state, query_twap_price,
+        query_calc_fee, query_candles, query_config, query_curve_reserves, query_input_amount,
+        query_input_price, query_is_over_fluctuation_limit, query_is_over_spread_limit,
+        query_oldest_snapshot_index, query_output_amount, query_output_price, query_owner,
+        query_repeg_budget, query_spot_price, query_state, query_twap_price,
+    },
+    state::{
+        migrate_cumulative_prices, migrate_market_status, read_config, store_config,
+        store_reserve_snapshot, store_state, Config, ReserveSnapshot, State,
     },
-    state::{store_config, store_reserve_snapshot, store_state, Config, ReserveSnapshot, State},
 };
 
 /// Contract name that is used for migration.
@@ -43,7 +52,7 @@ pub const ONE_HOUR_IN_SECONDS: u64 = 60 * 60;
 pub const ONE_DAY_IN_SECONDS: u64 = 24 * 60 * 60;
 pub const ONE_WEEK_IN_SECONDS: u64 = 7 * 24 * 60 * 60;
 
-const FIFTEEN_MINUTES: u64 = 15 * 60;
+pub const FIFTEEN_MINUTES: u64 = 15 * 60;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -65,6 +74,14 @@ pub fn instantiate(
     validate_assets(&msg.base_asset)?;
     validate_assets(&msg.quote_asset)?;
 
+    // a concentration below one would spread the liquidity thinner than the reserves
+    let pricing_curve = msg.pricing_curve.unwrap_or_default();
+    if let PricingCurve::OracleAnchored { concentration } = pricing_curve {
+        if concentration < decimals {
+            return Err(StdError::generic_err("concentration must be at least one").into());
+        }
+    }
+
     let mut config = Config {
         margin_engine: Addr::unchecked(""), // default to nothing, must be set
         insurance_fund: Addr::unchecked(""), // default to nothing, must be set like the engine
@@ -80,6 +97,18 @@ pub fn instantiate(
         spot_price_twap_interval: ONE_HOUR_IN_SECONDS,
         funding_period: msg.funding_period,
         initial_margin_ratio: msg.initial_margin_ratio,
+        snapshot_retention: 0u64,
+        repeg_threshold: Uint128::zero(),
+        repeg_budget: Uint128::zero(),
+        min_spread_ratio: Uint128::zero(),
+        max_spread_ratio: Uint128::zero(),
+        volatility_spread_ratio: Uint128::zero(),
+        funding_formula: FundingFormula::Premium,
+        max_funding_rate: Uint128::zero(),
+        continuous_funding: false,
+        max_liquidity_change_ratio: Uint128::zero(),
+        liquidity_budget: Uint128::zero(),
+        pricing_curve,
     };
 
     // set and update margin engine
@@ -101,7 +130,7 @@ pub fn instantiate(
     validate_non_fraction(msg.quote_asset_reserve, decimals)?;
 
     let state = State {
-        open: false,
+        status: MarketStatus::Paused,
         base_asset_reserve: msg.base_asset_reserve,
         quote_asset_reserve: msg.quote_asset_reserve,
         total_position_size: Integer::zero(),
@@ -116,6 +145,9 @@ pub fn instantiate(
         quote_asset_reserve: msg.quote_asset_reserve,
         timestamp: env.block.time,
         block_height: env.block.height,
+        cumulative_price: Uint128::zero(),
+        curve_quote_asset_reserve: Uint128::zero(),
+        curve_base_asset_reserve: Uint128::zero(),
     };
 
     store_reserve_snapshot(deps.storage, &reserve)?;
@@ -139,6 +171,17 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             pricefeed,
             spot_price_twap_interval,
             initial_margin_ratio,
+            snapshot_retention,
+            repeg_threshold,
+            repeg_budget,
+            min_spread_ratio,
+            max_spread_ratio,
+            volatility_spread_ratio,
+            funding_formula,
+            max_funding_rate,
+            continuous_funding,
+            max_liquidity_change_ratio,
+            liquidity_budget,
         } => update_config(
             deps,
             info,
@@ -152,6 +195,17 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             pricefeed,
             spot_price_twap_interval,
             initial_margin_ratio,
+            snapshot_retention,
+            repeg_threshold,
+            repeg_budget,
+            min_spread_ratio,
+            max_spread_ratio,
+            volatility_spread_ratio,
+            funding_formula,
+            max_funding_rate,
+            continuous_funding,
+            max_liquidity_change_ratio,
+            liquidity_budget,
         ),
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
         ExecuteMsg::SwapInput {
@@ -185,7 +239,7 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             quote_asset_limit,
         ),
         ExecuteMsg::SettleFunding {} => settle_funding(deps, env, info),
-        ExecuteMsg::SetOpen { open } => set_open(deps, env, info, open),
+        ExecuteMsg::SetStatus { status } => set_status(deps, env, info, status),
         ExecuteMsg::MigrateLiquidity {
             fluctuation_limit_ratio,
             liquidity_multiplier,
@@ -197,8 +251,11 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             liquidity_multiplier,
         ),
         ExecuteMsg::RepegPrice { new_price } => repeg_price(deps, env, info, new_price),
+        ExecuteMsg::AutoRepeg {} => auto_repeg(deps, env, info),
+        ExecuteMsg::AdjustLiquidity { revenue } => adjust_liquidity(deps, env, info, revenue),
         ExecuteMsg::AddWhitelist { address } => add_whitelist(deps, info, address),
         ExecuteMsg::RemoveWhitelist { address } => remove_whitelist(deps, info, address),
+        ExecuteMsg::PruneSnapshots { limit } => prune_snapshots(deps, env, limit),
     }
 }
 
@@ -256,10 +313,12 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 interval,
             )?)
         }
-        QueryMsg::CalcFee { quote_asset_amount } => {
-            to_binary(&query_calc_fee(deps, quote_asset_amount)?)
-        }
+        QueryMsg::CalcFee {
+            quote_asset_amount,
+            direction,
+        } => to_binary(&query_calc_fee(deps, env, quote_asset_amount, direction)?),
         QueryMsg::SpotPrice {} => to_binary(&query_spot_price(deps)?),
+        QueryMsg::CurveReserves {} => to_binary(&query_curve_reserves(deps)?),
         QueryMsg::TwapPrice { interval } => to_binary(&query_twap_price(
             deps,
             env,
@@ -279,11 +338,29 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         )?),
         QueryMsg::IsWhitelisted { address } => to_binary(&WHITELIST.query_hook(deps, address)?),
         QueryMsg::GetWhitelist {} => to_binary(&WHITELIST.query_hooks(deps)?),
+        QueryMsg::OldestSnapshotIndex {} => to_binary(&query_oldest_snapshot_index(deps)?),
+        QueryMsg::RepegBudget {} => to_binary(&query_repeg_budget(deps, env)?),
+        QueryMsg::Candles {
+            interval,
+            start_after,
+            limit,
+            order_by,
+        } => to_binary(&query_candles(
+            deps,
+            interval,
+            start_after,
+            limit,
+            order_by,
+        )?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    migrate_cumulative_prices(deps.storage)?;
+    migrate_market_status(deps.storage)?;
+
     Ok(Response::new())
 }
@@ -1,11 +1,7 @@
-use std::ops::{Div, Mul};
-
-use cosmwasm_std::{
-    Decimal256, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128,
-};
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128};
 
 use margined_common::{integer::Integer, validate::validate_ratio};
-use margined_perp::margined_vamm::Direction;
+use margined_perp::margined_vamm::{Direction, FundingFormula, MarketStatus, PricingCurve};
 use margined_utils::{
     contracts::helpers::PricefeedController,
     tools::price_swap::{get_input_price_with_reserves, get_output_price_with_reserves},
@@ -13,14 +9,21 @@ use margined_utils::{
 
 use crate::{
     contract::{
-        ONE_DAY_IN_SECONDS, ONE_HOUR_IN_SECONDS, ONE_MINUTE_IN_SECONDS, ONE_WEEK_IN_SECONDS, OWNER,
-        WHITELIST,
+        FIFTEEN_MINUTES, ONE_DAY_IN_SECONDS, ONE_HOUR_IN_SECONDS, ONE_MINUTE_IN_SECONDS,
+        ONE_WEEK_IN_SECONDS, OWNER, WHITELIST,
     },
     query::query_twap_price,
-    state::{read_config, read_state, store_config, store_state, Config},
+    state::{
+        read_config, read_liquidity_spending, read_oldest_reserve_snapshot,
+        read_reserve_snapshot_counter, read_state, remove_reserve_snapshot, store_config,
+        store_liquidity_spending, store_oldest_reserve_snapshot, store_state, Config, State,
+        DEFAULT_PRUNE_LIMIT, MAX_PRUNE_LIMIT,
+    },
     utils::{
-        add_reserve_snapshot, check_is_over_block_fluctuation_limit,
-        price_boundaries_of_last_block, require_margin_engine, require_open, TwapCalcOption,
+        add_period_spending, add_reserve_snapshot, calc_repeg_cost,
+        check_is_over_block_fluctuation_limit, curve_reserves, find_snapshot_at,
+        liquidity_multiplier_lower_bound, price_boundaries_of_last_block, repeg_reserves,
+        require_margin_engine, require_open, update_candles, update_repeg_spending, TwapCalcOption,
     },
 };
 
@@ -38,6 +41,17 @@ pub fn update_config(
     pricefeed: Option<String>,
     spot_price_twap_interval: Option<u64>,
     initial_margin_ratio: Option<Uint128>,
+    snapshot_retention: Option<u64>,
+    repeg_threshold: Option<Uint128>,
+    repeg_budget: Option<Uint128>,
+    min_spread_ratio: Option<Uint128>,
+    max_spread_ratio: Option<Uint128>,
+    volatility_spread_ratio: Option<Uint128>,
+    funding_formula: Option<FundingFormula>,
+    max_funding_rate: Option<Uint128>,
+    continuous_funding: Option<bool>,
+    max_liquidity_change_ratio: Option<Uint128>,
+    liquidity_budget: Option<Uint128>,
 ) -> StdResult<Response> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -104,6 +118,83 @@ pub fn update_config(
         config.initial_margin_ratio = initial_margin_ratio;
     }
 
+    // change how long reserve snapshots are kept for
+    if let Some(snapshot_retention) = snapshot_retention {
+        config.snapshot_retention = snapshot_retention;
+    }
+
+    // change the twap divergence that allows an automatic repeg
+    if let Some(repeg_threshold) = repeg_threshold {
+        validate_ratio(repeg_threshold, config.decimals)?;
+        config.repeg_threshold = repeg_threshold;
+    }
+
+    // change the repeg cost the insurance fund covers per funding period
+    if let Some(repeg_budget) = repeg_budget {
+        config.repeg_budget = repeg_budget;
+    }
+
+    // change the bounds of the dynamic spread ratio
+    if let Some(min_spread_ratio) = min_spread_ratio {
+        validate_ratio(min_spread_ratio, config.decimals)?;
+        config.min_spread_ratio = min_spread_ratio;
+    }
+
+    if let Some(max_spread_ratio) = max_spread_ratio {
+        validate_ratio(max_spread_ratio, config.decimals)?;
+        config.max_spread_ratio = max_spread_ratio;
+    }
+
+    if !config.max_spread_ratio.is_zero() && config.min_spread_ratio > config.max_spread_ratio {
+        return Err(StdError::generic_err(
+            "min_spread_ratio should not be greater than max_spread_ratio",
+        ));
+    }
+
+    // change how much the realised volatility widens the spread
+    if let Some(volatility_spread_ratio) = volatility_spread_ratio {
+        config.volatility_spread_ratio = volatility_spread_ratio;
+    }
+
+    // change how the funding payment is derived from the premium
+    if let Some(funding_formula) = funding_formula {
+        if let FundingFormula::PremiumIndex {
+            interest_rate,
+            damping_band,
+        } = funding_formula
+        {
+            validate_ratio(interest_rate, config.decimals)?;
+            validate_ratio(damping_band, config.decimals)?;
+        }
+        config.funding_formula = funding_formula;
+    }
+
+    // change the cap of the funding rate per funding period
+    if let Some(max_funding_rate) = max_funding_rate {
+        validate_ratio(max_funding_rate, config.decimals)?;
+        config.max_funding_rate = max_funding_rate;
+    }
+
+    // change whether the premium fraction accrues continuously in the margin engine
+    if let Some(continuous_funding) = continuous_funding {
+        config.continuous_funding = continuous_funding;
+    }
+
+    // change the bounds of the liquidity adjustment at funding settlement
+    if let Some(max_liquidity_change_ratio) = max_liquidity_change_ratio {
+        // a full decrease would empty the reserves
+        if max_liquidity_change_ratio >= config.decimals {
+            return Err(StdError::generic_err(
+                "max_liquidity_change_ratio should be less than one",
+            ));
+        }
+        config.max_liquidity_change_ratio = max_liquidity_change_ratio;
+    }
+
+    if let Some(liquidity_budget) = liquidity_budget {
+        config.liquidity_budget = liquidity_budget;
+    }
+
     store_config(deps.storage, &config)?;
 
     Ok(Response::default().add_attribute("action", "update_config"))
@@ -138,55 +229,78 @@ pub fn remove_whitelist(deps: DepsMut, info: MessageInfo, address: String) -> St
         .map_err(|error| StdError::generic_err(error.to_string()))
 }
 
-pub fn set_open(deps: DepsMut, env: Env, info: MessageInfo, open: bool) -> StdResult<Response> {
+pub fn set_status(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    status: MarketStatus,
+) -> StdResult<Response> {
     let config = read_config(deps.storage)?;
     let mut state = read_state(deps.storage)?;
 
     // check permission and if state matches
     if (!OWNER.is_admin(deps.as_ref(), &info.sender)? && info.sender != config.insurance_fund)
-        || state.open == open
+        || state.status == status
     {
         return Err(StdError::generic_err("unauthorized"));
     }
 
-    state.open = open;
+    // a market that is being wound down can only go on to be settled
+    match (state.status, status) {
+        (MarketStatus::Settled, _) => {
+            return Err(StdError::generic_err("market is settled"));
+        }
+        (MarketStatus::Settling, MarketStatus::Settled) => {}
+        (MarketStatus::Settling, _) => {
+            return Err(StdError::generic_err("market is settling"));
+        }
+        (_, MarketStatus::Settled) => {
+            return Err(StdError::generic_err("market is not settling"));
+        }
+        _ => {}
+    }
 
-    // if state.open is true then we update the next funding time
-    if state.open {
+    // positions can not be closed once the market is settled
+    if status == MarketStatus::Settled && !state.total_position_size.is_zero() {
+        return Err(StdError::generic_err("market has open positions"));
+    }
+
+    // if the market reopens then we update the next funding time
+    if status.is_open() && !state.status.is_open() {
         state.next_funding_time = env.block.time.seconds()
             + config.funding_period / ONE_HOUR_IN_SECONDS * ONE_HOUR_IN_SECONDS;
     }
 
+    state.status = status;
+
     store_state(deps.storage, &state)?;
 
     Ok(Response::new()
-        .add_attribute("action", "set_open")
+        .add_attribute("action", "set_status")
+        .add_attribute("status", status.to_string())
         .add_attribute("vamm", &env.contract.address)
         .add_attribute("base_asset", config.base_asset)
         .add_attribute("quote_asset", config.quote_asset))
 }
 
 pub fn repeg_price(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     new_price: Option<Uint128>,
 ) -> StdResult<Response> {
-    // check permission and if state matches
-    if !WHITELIST.query_hook(deps.as_ref(), info.sender.to_string())?
-        && !OWNER.is_admin(deps.as_ref(), &info.sender)?
-    {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    let config = read_config(deps.storage)?;
+
+    // only the margin engine repegs, so the repeg cost is always settled with the insurance fund
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
 
     let new_price = match new_price {
         Some(val) => val,
         None => {
-            let config = read_config(deps.storage)?;
-            let pricefeed_controller = PricefeedController(config.pricefeed);
+            let pricefeed_controller = PricefeedController(config.pricefeed.clone());
 
             pricefeed_controller
-                .get_price(&deps.querier, config.base_asset)
+                .get_price(&deps.querier, config.base_asset.clone())
                 .unwrap_or_default()
         }
     };
@@ -195,44 +309,77 @@ pub fn repeg_price(
         return Err(StdError::generic_err("new price can't be 0"));
     }
 
-    let config = read_config(deps.storage)?;
-    let mut state = read_state(deps.storage)?;
+    let (state, repeg_cost) = repeg_reserves(deps.branch(), env.clone(), &config, new_price)?;
 
-    // base_asset * quote_asset = k
-    // new_base_asset * new_quote_asset = k
-    // new_quote_asset / new_base_asset = new_price
-    // => new_quote_asset = sqrt(k * new_price)
-    // => new_base_asset = k / new_quote_asset
+    // manual repegs are not bounded by the budget but still use it up
+    update_repeg_spending(deps.storage, &env, config.funding_period, repeg_cost)?;
 
-    let invariant_k = state
-        .quote_asset_reserve
-        .checked_mul(state.base_asset_reserve)?;
-
-    let new_quote_asset = Uint128::try_from(
-        Decimal256::from_atomics(invariant_k, 0)
-            .unwrap()
-            .checked_mul(Decimal256::from_ratio(new_price, config.decimals))?
-            .sqrt()
-            .to_uint_floor(),
-    )?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "repeg_price"),
+        ("new_price", &new_price.to_string()),
+        ("vamm", env.contract.address.as_str()),
+        ("timestamp", &env.block.time.seconds().to_string()),
+        (
+            "quote_asset_reserve",
+            &state.quote_asset_reserve.to_string(),
+        ),
+        ("base_asset_reserve", &state.base_asset_reserve.to_string()),
+        ("repeg_cost", &repeg_cost.to_string()),
+    ]))
+}
 
-    let new_base_asset = invariant_k / new_quote_asset;
+/// Repegs to the oracle price once the twap of the vamm has diverged from the
+/// oracle twap by more than the repeg threshold, the cost the insurance fund
+/// covers in a funding period is bounded by the repeg budget
+pub fn auto_repeg(mut deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let state = read_state(deps.storage)?;
+    require_open(state.status)?;
+    let config = read_config(deps.storage)?;
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
 
-    state.quote_asset_reserve = new_quote_asset;
-    state.base_asset_reserve = new_base_asset;
+    if config.repeg_threshold.is_zero() {
+        return Err(StdError::generic_err("auto repeg is disabled"));
+    }
 
-    store_state(deps.storage, &state)?;
+    let pricefeed_controller = PricefeedController(config.pricefeed.clone());
+    // twap price from oracle
+    let underlying_price = pricefeed_controller.twap_price(
+        &deps.querier,
+        config.base_asset.clone(),
+        config.spot_price_twap_interval,
+    )?;
 
-    add_reserve_snapshot(
-        deps.storage,
+    // twap price from here, i.e. the amm
+    let index_price = query_twap_price(
+        deps.as_ref(),
         env.clone(),
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
+        config.spot_price_twap_interval,
+        TwapCalcOption::Reserve,
+        None,
     )?;
 
+    let max_divergence = underlying_price.multiply_ratio(config.repeg_threshold, config.decimals);
+    if index_price.abs_diff(underlying_price) <= max_divergence {
+        return Err(StdError::generic_err("price is within the repeg threshold"));
+    }
+
+    let new_price = pricefeed_controller.get_price(&deps.querier, config.base_asset.clone())?;
+    if new_price.is_zero() {
+        return Err(StdError::generic_err("new price can't be 0"));
+    }
+
+    let (state, repeg_cost) = repeg_reserves(deps.branch(), env.clone(), &config, new_price)?;
+
+    let spending = update_repeg_spending(deps.storage, &env, config.funding_period, repeg_cost)?;
+    if spending.spent > config.repeg_budget {
+        return Err(StdError::generic_err("repeg budget exceeded"));
+    }
+
     Ok(Response::new().add_attributes(vec![
-        ("action", "repeg_price"),
+        ("action", "auto_repeg"),
         ("new_price", &new_price.to_string()),
+        ("underlying_price", &underlying_price.to_string()),
+        ("index_price", &index_price.to_string()),
         ("vamm", env.contract.address.as_str()),
         ("timestamp", &env.block.time.seconds().to_string()),
         (
@@ -240,6 +387,105 @@ pub fn repeg_price(
             &state.quote_asset_reserve.to_string(),
         ),
         ("base_asset_reserve", &state.base_asset_reserve.to_string()),
+        ("repeg_cost", &repeg_cost.to_string()),
+    ]))
+}
+
+/// Adjusts the liquidity at funding settlement, the reserves deepen when the
+/// fees the vamm collected exceeded the funding the insurance fund paid over the
+/// period and shrink otherwise. The change is the revenue relative to the quote
+/// asset reserve, capped at the max liquidity change ratio, and it is skipped if
+/// it would break the multiplier lower bound or exceed the liquidity budget
+pub fn adjust_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    revenue: Integer,
+) -> StdResult<Response> {
+    let mut state = read_state(deps.storage)?;
+    require_open(state.status)?;
+    let config = read_config(deps.storage)?;
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
+
+    if config.max_liquidity_change_ratio.is_zero() {
+        return Err(StdError::generic_err("liquidity adjustment is disabled"));
+    }
+
+    // the revenue is measured against the liquidity the swaps are priced on
+    let old_curve_reserves = curve_reserves(deps.as_ref(), &config, &state)?;
+    let change_ratio = revenue
+        .value
+        .multiply_ratio(config.decimals, old_curve_reserves.0)
+        .min(config.max_liquidity_change_ratio);
+
+    let mut liquidity_multiplier = if revenue.is_negative() {
+        config.decimals.checked_sub(change_ratio)?
+    } else {
+        config.decimals.checked_add(change_ratio)?
+    };
+
+    if liquidity_multiplier <= liquidity_multiplier_lower_bound(&state, config.decimals) {
+        liquidity_multiplier = config.decimals;
+    }
+
+    let new_quote_asset_reserve = state
+        .quote_asset_reserve
+        .multiply_ratio(liquidity_multiplier, config.decimals);
+    let new_base_asset_reserve = state
+        .base_asset_reserve
+        .multiply_ratio(liquidity_multiplier, config.decimals);
+
+    let new_curve_reserves = curve_reserves(
+        deps.as_ref(),
+        &config,
+        &State {
+            quote_asset_reserve: new_quote_asset_reserve,
+            base_asset_reserve: new_base_asset_reserve,
+            ..state.clone()
+        },
+    )?;
+    let mut liquidity_cost = calc_repeg_cost(
+        state.total_position_size,
+        old_curve_reserves,
+        new_curve_reserves,
+    )?;
+
+    let spending = add_period_spending(
+        read_liquidity_spending(deps.storage)?,
+        &env,
+        config.funding_period,
+        liquidity_cost,
+    )?;
+
+    if spending.spent > config.liquidity_budget {
+        liquidity_multiplier = config.decimals;
+        liquidity_cost = Integer::zero();
+    } else {
+        state.quote_asset_reserve = new_quote_asset_reserve;
+        state.base_asset_reserve = new_base_asset_reserve;
+
+        store_state(deps.storage, &state)?;
+        store_liquidity_spending(deps.storage, &spending)?;
+
+        add_reserve_snapshot(
+            deps.storage,
+            env,
+            state.quote_asset_reserve,
+            state.base_asset_reserve,
+            new_curve_reserves,
+        )?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "adjust_liquidity"),
+        ("revenue", &revenue.to_string()),
+        ("liquidity_multiplier", &liquidity_multiplier.to_string()),
+        (
+            "quote_asset_reserve",
+            &state.quote_asset_reserve.to_string(),
+        ),
+        ("base_asset_reserve", &state.base_asset_reserve.to_string()),
+        ("liquidity_cost", &liquidity_cost.to_string()),
     ]))
 }
 
@@ -263,15 +509,8 @@ pub fn migrate_liquidity(
     let mut state = read_state(deps.storage)?;
 
     // check liquidity multiplier limit, have lower bound if position size is positive for now.
-    if state.total_position_size.is_positive() {
-        let liquidity_multiplier_lower_bound = state
-            .total_position_size
-            .value
-            .mul(config.decimals)
-            .div(state.base_asset_reserve);
-        if liquidity_multiplier < liquidity_multiplier_lower_bound {
-            return Err(StdError::generic_err("illegal liquidity multiplier"));
-        }
+    if liquidity_multiplier < liquidity_multiplier_lower_bound(&state, config.decimals) {
+        return Err(StdError::generic_err("illegal liquidity multiplier"));
     }
 
     if let Some(fluctuation_limit_ratio) = fluctuation_limit_ratio {
@@ -332,16 +571,18 @@ pub fn swap_input(
 ) -> StdResult<Response> {
     let state = read_state(deps.storage)?;
 
-    require_open(state.open)?;
+    require_open(state.status)?;
     let config = read_config(deps.storage)?;
-    require_margin_engine(info.sender, config.margin_engine)?;
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
+
+    let curve_reserves = curve_reserves(deps.as_ref(), &config, &state)?;
 
     let base_asset_amount = if !quote_asset_amount.is_zero() {
         let base_asset_amount = get_input_price_with_reserves(
             &direction,
             quote_asset_amount,
-            state.quote_asset_reserve,
-            state.base_asset_reserve,
+            curve_reserves.0,
+            curve_reserves.1,
         )?;
 
         // If AddToAmm, exchanged base amount should be more than base_asset_limit,
@@ -368,6 +609,7 @@ pub fn swap_input(
     let response = update_reserve(
         deps.storage,
         env,
+        curve_reserves,
         direction.clone(),
         quote_asset_amount,
         base_asset_amount,
@@ -395,9 +637,11 @@ pub fn swap_output(
     quote_asset_limit: Uint128,
 ) -> StdResult<Response> {
     let state = read_state(deps.storage)?;
-    require_open(state.open)?;
+    require_open(state.status)?;
     let config = read_config(deps.storage)?;
-    require_margin_engine(info.sender, config.margin_engine)?;
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
+
+    let curve_reserves = curve_reserves(deps.as_ref(), &config, &state)?;
 
     // flip direction when updating reserve
     let update_direction = match direction {
@@ -409,8 +653,8 @@ pub fn swap_output(
         let quote_asset_amount = get_output_price_with_reserves(
             &direction,
             base_asset_amount,
-            state.quote_asset_reserve,
-            state.base_asset_reserve,
+            curve_reserves.0,
+            curve_reserves.1,
         )?;
 
         // If AddToAmm, exchanged base amount should be more than quote_asset_limit,
@@ -440,6 +684,7 @@ pub fn swap_output(
     let response = update_reserve(
         deps.storage,
         env,
+        curve_reserves,
         update_direction,
         quote_asset_amount,
         base_asset_amount,
@@ -458,7 +703,7 @@ pub fn swap_output(
 
 pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
     let mut state = read_state(deps.storage)?;
-    require_open(state.open)?;
+    require_open(state.status)?;
     let config = read_config(deps.storage)?;
     require_margin_engine(info.sender, config.margin_engine)?;
 
@@ -487,10 +732,42 @@ pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<R
     let premium =
         Integer::new_positive(index_price).checked_sub(Integer::new_positive(underlying_price))?;
 
-    let premium_fraction = premium
+    // the interest adjustment pulls the premium towards the interest rate,
+    // but never by more than the damping band
+    let interest_adjustment = match config.funding_formula {
+        FundingFormula::Premium => Integer::zero(),
+        FundingFormula::PremiumIndex {
+            interest_rate,
+            damping_band,
+        } => {
+            let interest = Integer::new_positive(
+                underlying_price.checked_mul(interest_rate)? / config.decimals,
+            );
+            let band = underlying_price.checked_mul(damping_band)? / config.decimals;
+
+            interest
+                .checked_sub(premium)?
+                .clamp(Integer::new_negative(band), Integer::new_positive(band))
+        }
+    };
+
+    let mut premium_fraction = premium
+        .checked_add(interest_adjustment)?
         .checked_mul(Integer::new_positive(config.funding_period))?
         .checked_div(Integer::new_positive(ONE_DAY_IN_SECONDS))?;
 
+    // cap the payment of a single period, so one manipulated interval can
+    // only move the funding by the max funding rate
+    let mut funding_rate_clamped = false;
+    if !config.max_funding_rate.is_zero() {
+        let max_premium_fraction =
+            underlying_price.checked_mul(config.max_funding_rate)? / config.decimals;
+        if premium_fraction.value > max_premium_fraction {
+            funding_rate_clamped = true;
+            premium_fraction.value = max_premium_fraction;
+        }
+    }
+
     // update funding rate = premiumFraction / twapIndexPrice
     state.funding_rate = premium_fraction
         .checked_mul(Integer::new_positive(config.decimals))?
@@ -517,12 +794,63 @@ pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<R
         ("underlying_price", &underlying_price.to_string()),
         ("index_price", &index_price.to_string()),
         ("next_funding_time", &state.next_funding_time.to_string()),
+        ("funding_formula", &config.funding_formula.to_string()),
+        ("premium", &premium.to_string()),
+        ("interest_adjustment", &interest_adjustment.to_string()),
+        ("funding_rate", &state.funding_rate.to_string()),
+        ("funding_rate_clamped", &funding_rate_clamped.to_string()),
+        ("continuous_funding", &config.continuous_funding.to_string()),
     ]))
 }
 
+/// Deletes the reserve snapshots that are older than the retention of the
+/// vamm, the latest snapshot before the cutoff is kept so twaps over the
+/// retention window are unchanged. Can be called by anyone.
+pub fn prune_snapshots(deps: DepsMut, env: Env, limit: Option<u32>) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as u64;
+
+    // snapshots are always kept for the longest twap window
+    let retention = config
+        .snapshot_retention
+        .max(config.spot_price_twap_interval)
+        .max(FIFTEEN_MINUTES);
+    let cutoff = env.block.time.seconds().saturating_sub(retention);
+
+    let oldest_index = read_oldest_reserve_snapshot(deps.storage)?;
+    let latest_index = read_reserve_snapshot_counter(deps.storage)?;
+
+    let mut pruned = 0u64;
+    if let Some(cutoff_index) = find_snapshot_at(
+        deps.storage,
+        oldest_index,
+        latest_index,
+        Uint128::from(cutoff),
+    )? {
+        pruned = cutoff_index.saturating_sub(oldest_index).min(limit);
+
+        for index in oldest_index..oldest_index + pruned {
+            remove_reserve_snapshot(deps.storage, index);
+        }
+        store_oldest_reserve_snapshot(deps.storage, oldest_index + pruned)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "prune_snapshots"),
+        ("pruned", &pruned.to_string()),
+        (
+            "oldest_snapshot_index",
+            &(oldest_index + pruned).to_string(),
+        ),
+    ]))
+}
+
+/// Moves the reserves by the swap, the curve reserves are the (quote, base) pair
+/// the swap was priced against
 pub fn update_reserve(
     storage: &mut dyn Storage,
     env: Env,
+    curve_reserves: (Uint128, Uint128),
     direction: Direction,
     quote_asset_amount: Uint128,
     base_asset_amount: Uint128,
@@ -530,27 +858,35 @@ pub fn update_reserve(
 ) -> StdResult<Response> {
     let config = read_config(storage)?;
     let mut state = read_state(storage)?;
+    let price_before = state
+        .quote_asset_reserve
+        .checked_mul(config.decimals)?
+        .checked_div(state.base_asset_reserve)?;
 
     check_is_over_block_fluctuation_limit(
         storage,
         env.clone(),
-        config.decimals,
-        config.fluctuation_limit_ratio,
+        &config,
         direction.clone(),
         quote_asset_amount,
         base_asset_amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
+        curve_reserves.0,
+        curve_reserves.1,
         can_go_over_fluctuation,
     )?;
 
-    match direction {
+    let (curve_quote_reserve, curve_base_reserve) = match direction {
         Direction::AddToAmm => {
             state.quote_asset_reserve =
                 state.quote_asset_reserve.checked_add(quote_asset_amount)?;
             state.base_asset_reserve = state.base_asset_reserve.checked_sub(base_asset_amount)?;
 
             state.total_position_size += Integer::from(base_asset_amount);
+
+            (
+                curve_reserves.0.checked_add(quote_asset_amount)?,
+                curve_reserves.1.checked_sub(base_asset_amount)?,
+            )
         }
         Direction::RemoveFromAmm => {
             state.base_asset_reserve = state.base_asset_reserve.checked_add(base_asset_amount)?;
@@ -558,7 +894,19 @@ pub fn update_reserve(
                 state.quote_asset_reserve.checked_sub(quote_asset_amount)?;
 
             state.total_position_size -= Integer::from(base_asset_amount);
+
+            (
+                curve_reserves.0.checked_sub(quote_asset_amount)?,
+                curve_reserves.1.checked_add(base_asset_amount)?,
+            )
         }
+    };
+
+    // the stored reserves keep the spot price the curve traded at, the quote reserve
+    // is the one that moves as the base reserve tracks the net position
+    if config.pricing_curve != PricingCurve::ConstantProduct {
+        state.quote_asset_reserve =
+            curve_quote_reserve.multiply_ratio(state.base_asset_reserve, curve_base_reserve);
     }
 
     store_state(storage, &state)?;
@@ -568,6 +916,19 @@ pub fn update_reserve(
         env.clone(),
         state.quote_asset_reserve,
         state.base_asset_reserve,
+        (curve_quote_reserve, curve_base_reserve),
+    )?;
+
+    update_candles(
+        storage,
+        env.block.time.seconds(),
+        price_before,
+        state
+            .quote_asset_reserve
+            .checked_mul(config.decimals)?
+            .checked_div(state.base_asset_reserve)?,
+        base_asset_amount,
+        quote_asset_amount,
     )?;
 
     Ok(Response::new().add_attributes(vec![
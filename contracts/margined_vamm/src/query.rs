@@ -1,7 +1,8 @@
-use cosmwasm_std::{Deps, Env, StdError, StdResult, Uint128};
+use cosmwasm_std::{Deps, Env, Order, StdError, StdResult, Uint128};
 use margined_common::integer::Integer;
 use margined_perp::margined_vamm::{
-    CalcFeeResponse, ConfigResponse, Direction, OwnerResponse, StateResponse,
+    CalcFeeResponse, Candle, CandleInterval, ConfigResponse, CurveReservesResponse, Direction,
+    OwnerResponse, RepegBudgetResponse, StateResponse,
 };
 use margined_utils::{
     contracts::helpers::PricefeedController,
@@ -10,10 +11,13 @@ use margined_utils::{
 
 use crate::{
     contract::OWNER,
-    state::{read_config, read_reserve_snapshot_counter, read_state},
+    state::{
+        read_candles, read_config, read_oldest_reserve_snapshot, read_repeg_spending,
+        read_reserve_snapshot_counter, read_state,
+    },
     utils::{
-        calc_twap, price_boundaries_of_last_block, TwapCalcOption, TwapInputAsset,
-        TwapPriceCalcParams,
+        calc_spread_ratio, calc_twap, curve_reserves, fluctuation_boundaries, TwapCalcOption,
+        TwapInputAsset, TwapPriceCalcParams,
     },
 };
 
@@ -27,7 +31,7 @@ pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
     let state = read_state(deps.storage)?;
 
     Ok(StateResponse {
-        open: state.open,
+        status: state.status,
         quote_asset_reserve: state.quote_asset_reserve,
         base_asset_reserve: state.base_asset_reserve,
         total_position_size: state.total_position_size,
@@ -49,13 +53,10 @@ pub fn query_owner(deps: Deps) -> StdResult<OwnerResponse> {
 pub fn query_input_price(deps: Deps, direction: Direction, amount: Uint128) -> StdResult<Uint128> {
     let state = read_state(deps.storage)?;
     let config = read_config(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
 
-    let output = get_input_price_with_reserves(
-        &direction,
-        amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
-    )?;
+    let output =
+        get_input_price_with_reserves(&direction, amount, quote_asset_reserve, base_asset_reserve)?;
 
     if output.is_zero() {
         return Ok(Uint128::zero());
@@ -69,12 +70,13 @@ pub fn query_input_price(deps: Deps, direction: Direction, amount: Uint128) -> S
 pub fn query_output_price(deps: Deps, direction: Direction, amount: Uint128) -> StdResult<Uint128> {
     let state = read_state(deps.storage)?;
     let config = read_config(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
 
     let output = get_output_price_with_reserves(
         &direction,
         amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
+        quote_asset_reserve,
+        base_asset_reserve,
     )?;
 
     if output.is_zero() {
@@ -87,13 +89,11 @@ pub fn query_output_price(deps: Deps, direction: Direction, amount: Uint128) ->
 /// Queries input amount
 pub fn query_input_amount(deps: Deps, direction: Direction, amount: Uint128) -> StdResult<Uint128> {
     let state = read_state(deps.storage)?;
+    let config = read_config(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
 
-    let output = get_input_price_with_reserves(
-        &direction,
-        amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
-    )?;
+    let output =
+        get_input_price_with_reserves(&direction, amount, quote_asset_reserve, base_asset_reserve)?;
 
     Ok(output)
 }
@@ -105,11 +105,14 @@ pub fn query_output_amount(
     amount: Uint128,
 ) -> StdResult<Uint128> {
     let state = read_state(deps.storage)?;
+    let config = read_config(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
+
     let output = get_output_price_with_reserves(
         &direction,
         amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
+        quote_asset_reserve,
+        base_asset_reserve,
     )?;
     Ok(output)
 }
@@ -118,15 +121,27 @@ pub fn query_output_amount(
 pub fn query_spot_price(deps: Deps) -> StdResult<Uint128> {
     let config = read_config(deps.storage)?;
     let state = read_state(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
 
-    let res = state
-        .quote_asset_reserve
+    let res = quote_asset_reserve
         .checked_mul(config.decimals)?
-        .checked_div(state.base_asset_reserve)?;
+        .checked_div(base_asset_reserve)?;
 
     Ok(res)
 }
 
+/// Queries the reserves the swaps are priced against under the pricing curve
+pub fn query_curve_reserves(deps: Deps) -> StdResult<CurveReservesResponse> {
+    let config = read_config(deps.storage)?;
+    let state = read_state(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
+
+    Ok(CurveReservesResponse {
+        quote_asset_reserve,
+        base_asset_reserve,
+    })
+}
+
 /// Queries twap price of the vAMM, using the reserve snapshots
 pub fn query_twap_price(
     deps: Deps,
@@ -145,7 +160,12 @@ pub fn query_twap_price(
 }
 
 /// Returns the total (i.e. toll + spread) fees for an amount
-pub fn query_calc_fee(deps: Deps, quote_asset_amount: Uint128) -> StdResult<CalcFeeResponse> {
+pub fn query_calc_fee(
+    deps: Deps,
+    env: Env,
+    quote_asset_amount: Uint128,
+    direction: Option<Direction>,
+) -> StdResult<CalcFeeResponse> {
     let mut res = CalcFeeResponse {
         toll_fee: Uint128::zero(),
         spread_fee: Uint128::zero(),
@@ -153,12 +173,13 @@ pub fn query_calc_fee(deps: Deps, quote_asset_amount: Uint128) -> StdResult<Calc
 
     if quote_asset_amount != Uint128::zero() {
         let config = read_config(deps.storage)?;
+        let spread_ratio = calc_spread_ratio(deps, &env, &config, quote_asset_amount, direction)?;
 
         res.toll_fee = quote_asset_amount
             .checked_mul(config.toll_ratio)?
             .checked_div(config.decimals)?;
         res.spread_fee = quote_asset_amount
-            .checked_mul(config.spread_ratio)?
+            .checked_mul(spread_ratio)?
             .checked_div(config.decimals)?;
     }
 
@@ -204,35 +225,75 @@ pub fn query_is_over_fluctuation_limit(
         return Ok(false);
     };
 
-    let (upper_limit, lower_limit) = price_boundaries_of_last_block(
-        deps.storage,
-        config.decimals,
-        config.fluctuation_limit_ratio,
-        env,
-    )?;
-
     let state = read_state(deps.storage)?;
+    let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, &config, &state)?;
+
+    let curve_price = quote_asset_reserve
+        .checked_mul(config.decimals)?
+        .checked_div(base_asset_reserve)?;
+    let (upper_limit, lower_limit) =
+        fluctuation_boundaries(deps.storage, &config, curve_price, env)?;
 
     let quote_asset_amount = get_output_price_with_reserves(
         &direction,
         base_asset_amount,
-        state.quote_asset_reserve,
-        state.base_asset_reserve,
+        quote_asset_reserve,
+        base_asset_reserve,
     )?;
 
     let price = if direction == Direction::RemoveFromAmm {
-        state
-            .quote_asset_reserve
+        quote_asset_reserve
             .checked_add(quote_asset_amount)?
             .checked_mul(config.decimals)?
-            .checked_div(state.base_asset_reserve.checked_sub(base_asset_amount)?)
+            .checked_div(base_asset_reserve.checked_sub(base_asset_amount)?)
     } else {
-        state
-            .quote_asset_reserve
+        quote_asset_reserve
             .checked_sub(quote_asset_amount)?
             .checked_mul(config.decimals)?
-            .checked_div(state.base_asset_reserve.checked_add(base_asset_amount)?)
+            .checked_div(base_asset_reserve.checked_add(base_asset_amount)?)
     }?;
 
     Ok(price > upper_limit || price < lower_limit)
 }
+
+/// Queries the index of the oldest reserve snapshot that has not been pruned
+pub fn query_oldest_snapshot_index(deps: Deps) -> StdResult<u64> {
+    read_oldest_reserve_snapshot(deps.storage)
+}
+
+/// Queries the repeg budget and how much of it has been spent in the current
+/// funding period, a new period starts with the next repeg once it has ended
+pub fn query_repeg_budget(deps: Deps, env: Env) -> StdResult<RepegBudgetResponse> {
+    let config = read_config(deps.storage)?;
+    let spending = read_repeg_spending(deps.storage)?;
+
+    let now = env.block.time.seconds();
+    let period_end = spending.period_start + config.funding_period;
+
+    if now >= period_end {
+        return Ok(RepegBudgetResponse {
+            budget: config.repeg_budget,
+            spent: Uint128::zero(),
+            period_end: now + config.funding_period,
+        });
+    }
+
+    Ok(RepegBudgetResponse {
+        budget: config.repeg_budget,
+        spent: spending.spent,
+        period_end,
+    })
+}
+
+/// Queries the candles of an interval, latest first unless the order is ascending
+pub fn query_candles(
+    deps: Deps,
+    interval: CandleInterval,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order_by: Option<i32>,
+) -> StdResult<Vec<Candle>> {
+    let order_by = order_by.and_then(|val| Order::try_from(val).ok());
+
+    read_candles(deps.storage, interval, start_after, limit, order_by)
+}
@@ -1,12 +1,38 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{from_slice, to_vec, StdError, StdResult, Storage, Timestamp, Uint128};
+use cosmwasm_std::{from_slice, to_vec, Order, StdError, StdResult, Storage, Timestamp, Uint128};
 
-use margined_perp::margined_vamm::{ConfigResponse, StateResponse};
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::{
+    Candle, CandleInterval, ConfigResponse, MarketStatus, StateResponse,
+};
 
 pub static KEY_CONFIG: &[u8] = b"config";
 pub static KEY_STATE: &[u8] = b"state";
 pub static KEY_RESERVE_SNAPSHOT: &[u8] = b"reserve_snapshot";
 pub static KEY_RESERVE_SNAPSHOT_COUNTER: &[u8] = b"reserve_snapshot_counter";
+pub static KEY_OLDEST_RESERVE_SNAPSHOT: &[u8] = b"oldest_reserve_snapshot";
+pub static KEY_CUMULATIVE_PRICE_START: &[u8] = b"cumulative_price_start";
+pub static KEY_REPEG_SPENDING: &[u8] = b"repeg_spending";
+pub static KEY_LIQUIDITY_SPENDING: &[u8] = b"liquidity_spending";
+pub static KEY_CANDLE: &[u8] = b"candle";
+pub static KEY_BLOCK_OPEN_PRICE: &[u8] = b"block_open_price";
+
+pub const DEFAULT_PRUNE_LIMIT: u32 = 100;
+pub const MAX_PRUNE_LIMIT: u32 = 500;
+
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 100;
+
+// minute and hour candles are kept for a week and a year, daily candles are kept
+pub const MINUTE_CANDLE_RETENTION: u64 = 7 * 1_440;
+pub const HOUR_CANDLE_RETENTION: u64 = 365 * 24;
+// expired candles removed as a candle opens, more than one so a backlog clears
+pub const CANDLE_PRUNE_LIMIT: usize = 2;
+
+// snapshots looked back at when measuring the realised volatility
+pub const MAX_VOLATILITY_SNAPSHOTS: u64 = 50;
+// snapshots priced when taking the twap of an input
+pub const MAX_INPUT_TWAP_SNAPSHOTS: u64 = 100;
 
 // Has the same fields
 pub type State = StateResponse;
@@ -37,12 +63,78 @@ pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
     }
 }
 
+/// State as it was stored before the market status replaced the open flag
+#[cw_serde]
+struct LegacyState {
+    open: bool,
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    total_position_size: Integer,
+    funding_rate: Integer,
+    next_funding_time: u64,
+}
+
+/// Converts the open flag of a stored state into the market status, an open
+/// market is active and a closed one paused
+pub fn migrate_market_status(storage: &mut dyn Storage) -> StdResult<()> {
+    let data = match storage.get(KEY_STATE) {
+        Some(data) => data,
+        None => return Err(StdError::generic_err("State not found")),
+    };
+
+    if from_slice::<State>(&data).is_ok() {
+        return Ok(());
+    }
+
+    let legacy: LegacyState = from_slice(&data)?;
+    store_state(
+        storage,
+        &State {
+            status: if legacy.open {
+                MarketStatus::Active
+            } else {
+                MarketStatus::Paused
+            },
+            quote_asset_reserve: legacy.quote_asset_reserve,
+            base_asset_reserve: legacy.base_asset_reserve,
+            total_position_size: legacy.total_position_size,
+            funding_rate: legacy.funding_rate,
+            next_funding_time: legacy.next_funding_time,
+        },
+    )
+}
+
 #[cw_serde]
 pub struct ReserveSnapshot {
     pub quote_asset_reserve: Uint128,
     pub base_asset_reserve: Uint128,
     pub timestamp: Timestamp,
     pub block_height: u64,
+    // sum of the reserve price times the seconds it was held up to the snapshot timestamp,
+    // zero for the snapshots stored before it was tracked
+    #[serde(default)]
+    pub cumulative_price: Uint128,
+    // reserves the swaps were priced against under the pricing curve, zero for the
+    // snapshots stored before they were tracked
+    #[serde(default)]
+    pub curve_quote_asset_reserve: Uint128,
+    #[serde(default)]
+    pub curve_base_asset_reserve: Uint128,
+}
+
+impl ReserveSnapshot {
+    /// Returns the (quote, base) reserves the swaps were priced against at the
+    /// snapshot, the stored reserves if they were not tracked yet
+    pub fn curve_reserves(&self) -> (Uint128, Uint128) {
+        if self.curve_base_asset_reserve.is_zero() {
+            (self.quote_asset_reserve, self.base_asset_reserve)
+        } else {
+            (
+                self.curve_quote_asset_reserve,
+                self.curve_base_asset_reserve,
+            )
+        }
+    }
 }
 
 pub fn read_reserve_snapshot(storage: &dyn Storage, height: u64) -> StdResult<ReserveSnapshot> {
@@ -76,6 +168,24 @@ pub fn update_current_reserve_snapshot(
     Ok(())
 }
 
+/// Starts tracking the cumulative prices of the snapshots stored before they were
+/// from the latest snapshot, so the migration does not grow with the snapshots stored
+pub fn migrate_cumulative_prices(storage: &mut dyn Storage) -> StdResult<()> {
+    let height = read_reserve_snapshot_counter(storage)?;
+
+    // the latest snapshot only has a cumulative price if they are already tracked
+    if height <= 1u64
+        || !read_reserve_snapshot(storage, height)?
+            .cumulative_price
+            .is_zero()
+    {
+        return Ok(());
+    }
+
+    storage.set(KEY_CUMULATIVE_PRICE_START, &to_vec(&height)?);
+    Ok(())
+}
+
 pub fn read_reserve_snapshot_counter(storage: &dyn Storage) -> StdResult<u64> {
     Ok(match storage.get(KEY_RESERVE_SNAPSHOT_COUNTER) {
         Some(data) => from_slice(&data)?,
@@ -89,3 +199,186 @@ pub fn increment_reserve_snapshot_counter(storage: &mut dyn Storage) -> StdResul
     storage.set(KEY_RESERVE_SNAPSHOT_COUNTER, &to_vec(&val)?);
     Ok(())
 }
+
+pub fn remove_reserve_snapshot(storage: &mut dyn Storage, height: u64) {
+    storage.remove(&[KEY_RESERVE_SNAPSHOT, &height.to_be_bytes()].concat());
+}
+
+pub fn store_oldest_reserve_snapshot(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    storage.set(KEY_OLDEST_RESERVE_SNAPSHOT, &to_vec(&height)?);
+    Ok(())
+}
+
+// snapshots are kept from the first one until they are pruned
+pub fn read_oldest_reserve_snapshot(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(match storage.get(KEY_OLDEST_RESERVE_SNAPSHOT) {
+        Some(data) => from_slice(&data)?,
+        None => 1,
+    })
+}
+
+// the twap looks back to the oldest snapshot, leaving out those stored before
+// the cumulative prices were tracked
+pub fn read_first_twap_snapshot(storage: &dyn Storage) -> StdResult<u64> {
+    let cumulative_price_start: u64 = match storage.get(KEY_CUMULATIVE_PRICE_START) {
+        Some(data) => from_slice(&data)?,
+        None => 1,
+    };
+
+    Ok(read_oldest_reserve_snapshot(storage)?.max(cumulative_price_start))
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct RepegSpending {
+    pub period_start: u64,
+    pub spent: Uint128, // repeg cost covered by the insurance fund since the period start
+}
+
+pub fn store_repeg_spending(storage: &mut dyn Storage, spending: &RepegSpending) -> StdResult<()> {
+    storage.set(KEY_REPEG_SPENDING, &to_vec(spending)?);
+    Ok(())
+}
+
+pub fn read_repeg_spending(storage: &dyn Storage) -> StdResult<RepegSpending> {
+    Ok(match storage.get(KEY_REPEG_SPENDING) {
+        Some(data) => from_slice(&data)?,
+        None => RepegSpending::default(),
+    })
+}
+
+/// Cost of the liquidity adjustments covered by the insurance fund, it is
+/// budgeted per funding period like the repeg cost
+pub type LiquiditySpending = RepegSpending;
+
+pub fn store_liquidity_spending(
+    storage: &mut dyn Storage,
+    spending: &LiquiditySpending,
+) -> StdResult<()> {
+    storage.set(KEY_LIQUIDITY_SPENDING, &to_vec(spending)?);
+    Ok(())
+}
+
+pub fn read_liquidity_spending(storage: &dyn Storage) -> StdResult<LiquiditySpending> {
+    Ok(match storage.get(KEY_LIQUIDITY_SPENDING) {
+        Some(data) => from_slice(&data)?,
+        None => LiquiditySpending::default(),
+    })
+}
+
+/// Curve price before the first swap of a block, the fluctuation limit of the
+/// anchored curve is measured from it
+#[cw_serde]
+pub struct BlockOpenPrice {
+    pub block_height: u64,
+    pub price: Uint128,
+}
+
+pub fn store_block_open_price(
+    storage: &mut dyn Storage,
+    block_open_price: &BlockOpenPrice,
+) -> StdResult<()> {
+    storage.set(KEY_BLOCK_OPEN_PRICE, &to_vec(block_open_price)?);
+    Ok(())
+}
+
+pub fn read_block_open_price(storage: &dyn Storage) -> StdResult<Option<BlockOpenPrice>> {
+    match storage.get(KEY_BLOCK_OPEN_PRICE) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+// candles are keyed by the seconds of their interval then their open time
+fn candle_key(interval: CandleInterval, open_time: u64) -> Vec<u8> {
+    [
+        KEY_CANDLE,
+        &interval.seconds().to_be_bytes(),
+        &open_time.to_be_bytes(),
+    ]
+    .concat()
+}
+
+pub fn store_candle(
+    storage: &mut dyn Storage,
+    interval: CandleInterval,
+    candle: &Candle,
+) -> StdResult<()> {
+    storage.set(&candle_key(interval, candle.open_time), &to_vec(candle)?);
+    Ok(())
+}
+
+pub fn read_candle(
+    storage: &dyn Storage,
+    interval: CandleInterval,
+    open_time: u64,
+) -> StdResult<Option<Candle>> {
+    match storage.get(&candle_key(interval, open_time)) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Removes the oldest candles of an interval that opened more than its
+/// retention before `open_time`, at most `CANDLE_PRUNE_LIMIT` of them
+pub fn prune_candles(
+    storage: &mut dyn Storage,
+    interval: CandleInterval,
+    open_time: u64,
+) -> StdResult<()> {
+    let retention = match interval {
+        CandleInterval::Minute => MINUTE_CANDLE_RETENTION,
+        CandleInterval::Hour => HOUR_CANDLE_RETENTION,
+        CandleInterval::Day => return Ok(()),
+    };
+    let cutoff = open_time.saturating_sub(retention * interval.seconds());
+
+    let keys: Vec<Vec<u8>> = storage
+        .range(
+            Some(&candle_key(interval, 0)),
+            Some(&candle_key(interval, cutoff)),
+            Order::Ascending,
+        )
+        .take(CANDLE_PRUNE_LIMIT)
+        .map(|(key, _)| key)
+        .collect();
+
+    for key in keys {
+        storage.remove(&key);
+    }
+
+    Ok(())
+}
+
+pub fn read_candles(
+    storage: &dyn Storage,
+    interval: CandleInterval,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order_by: Option<Order>,
+) -> StdResult<Vec<Candle>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // the range ends before the candles of the next interval
+    let interval_end = [KEY_CANDLE, &(interval.seconds() + 1).to_be_bytes()].concat();
+    let (start, end, order_by) = match order_by {
+        Some(Order::Ascending) => {
+            let start_time = start_after.map_or(0, |time| time.saturating_add(1));
+            (
+                candle_key(interval, start_time),
+                interval_end,
+                Order::Ascending,
+            )
+        }
+        _ => {
+            let end = start_after.map_or(interval_end, |time| candle_key(interval, time));
+            (candle_key(interval, 0), end, Order::Descending)
+        }
+    };
+
+    storage
+        .range(Some(&start), Some(&end), order_by)
+        .take(limit)
+        .map(|(_, data)| from_slice(&data))
+        .collect()
+}
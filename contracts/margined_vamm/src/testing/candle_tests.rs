@@ -0,0 +1,227 @@
+use cosmwasm_std::{testing::MockStorage, Order, Timestamp, Uint128};
+use margined_perp::margined_vamm::{Candle, CandleInterval, Direction};
+use margined_utils::{
+    cw_multi_test::Executor,
+    testing::{to_decimals, VammScenario},
+};
+
+use crate::{
+    state::{read_candles, store_candle, MINUTE_CANDLE_RETENTION},
+    testing::new_vammscenario,
+    utils::update_candles,
+};
+
+#[test]
+fn test_swaps_update_candles() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        ..
+    } = new_vammscenario();
+
+    // start at the beginning of the next day so the candles are aligned
+    let now = router.block_info().time.seconds();
+    let start = now - now % 86_400 + 86_400;
+    router.update_block(|block| {
+        block.time = Timestamp::from_seconds(start);
+        block.height += 1;
+    });
+
+    // long 10 moves the price from 10 to 10.200999999
+    let msg = vamm
+        .swap_input(
+            Direction::AddToAmm,
+            0,
+            to_decimals(10u64),
+            Uint128::zero(),
+            true,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // short 20 moves the price to 9.800999999
+    let msg = vamm
+        .swap_input(
+            Direction::RemoveFromAmm,
+            0,
+            to_decimals(20u64),
+            Uint128::zero(),
+            true,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(45);
+        block.height += 1;
+    });
+
+    // long 10 in the next minute moves the price to 9.999999999
+    let msg = vamm
+        .swap_input(
+            Direction::AddToAmm,
+            0,
+            to_decimals(10u64),
+            Uint128::zero(),
+            true,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let first_minute = Candle {
+        open_time: start,
+        open: to_decimals(10u64),
+        high: Uint128::from(10_200_999_999u128),
+        low: Uint128::from(9_800_999_999u128),
+        close: Uint128::from(9_800_999_999u128),
+        base_volume: Uint128::from(2_990_299_030u128),
+        quote_volume: to_decimals(30u64),
+    };
+    let second_minute = Candle {
+        open_time: start + 60,
+        open: Uint128::from(9_800_999_999u128),
+        high: Uint128::from(9_999_999_999u128),
+        low: Uint128::from(9_800_999_999u128),
+        close: Uint128::from(9_999_999_999u128),
+        base_volume: Uint128::from(1_010_101_010u128),
+        quote_volume: to_decimals(10u64),
+    };
+
+    // latest first by default
+    let candles = vamm
+        .candles(&router.wrap(), CandleInterval::Minute, None, None, None)
+        .unwrap();
+    assert_eq!(candles, vec![second_minute.clone(), first_minute.clone()]);
+
+    let candles = vamm
+        .candles(
+            &router.wrap(),
+            CandleInterval::Minute,
+            None,
+            Some(1),
+            Some(1),
+        )
+        .unwrap();
+    assert_eq!(candles, vec![first_minute.clone()]);
+
+    let candles = vamm
+        .candles(
+            &router.wrap(),
+            CandleInterval::Minute,
+            Some(start),
+            None,
+            Some(1),
+        )
+        .unwrap();
+    assert_eq!(candles, vec![second_minute]);
+
+    let candles = vamm
+        .candles(
+            &router.wrap(),
+            CandleInterval::Minute,
+            Some(start + 60),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(candles, vec![first_minute]);
+
+    let day = Candle {
+        open_time: start,
+        open: to_decimals(10u64),
+        high: Uint128::from(10_200_999_999u128),
+        low: Uint128::from(9_800_999_999u128),
+        close: Uint128::from(9_999_999_999u128),
+        base_volume: Uint128::from(4_000_400_040u128),
+        quote_volume: to_decimals(40u64),
+    };
+
+    let candles = vamm
+        .candles(&router.wrap(), CandleInterval::Hour, None, None, None)
+        .unwrap();
+    assert_eq!(candles, vec![day.clone()]);
+
+    let candles = vamm
+        .candles(&router.wrap(), CandleInterval::Day, None, None, None)
+        .unwrap();
+    assert_eq!(candles, vec![day]);
+}
+
+#[test]
+fn test_expired_candles_are_pruned() {
+    let mut storage = MockStorage::new();
+
+    let candle = |open_time: u64| Candle {
+        open_time,
+        open: to_decimals(10u64),
+        high: to_decimals(10u64),
+        low: to_decimals(10u64),
+        close: to_decimals(10u64),
+        base_volume: Uint128::zero(),
+        quote_volume: Uint128::zero(),
+    };
+    let open_times = |storage: &MockStorage, interval: CandleInterval| -> Vec<u64> {
+        read_candles(storage, interval, None, None, Some(Order::Ascending))
+            .unwrap()
+            .iter()
+            .map(|candle| candle.open_time)
+            .collect()
+    };
+
+    // three minute candles and a daily one from a week ago
+    for open_time in [0u64, 60, 120] {
+        store_candle(&mut storage, CandleInterval::Minute, &candle(open_time)).unwrap();
+    }
+    store_candle(&mut storage, CandleInterval::Day, &candle(0)).unwrap();
+
+    // opening a minute candle removes at most two expired ones
+    let now = MINUTE_CANDLE_RETENTION * 60 + 180;
+    update_candles(
+        &mut storage,
+        now,
+        to_decimals(10u64),
+        to_decimals(10u64),
+        Uint128::zero(),
+        Uint128::zero(),
+    )
+    .unwrap();
+    assert_eq!(open_times(&storage, CandleInterval::Minute), vec![120, now]);
+
+    // a swap in the same minute does not prune
+    update_candles(
+        &mut storage,
+        now + 30,
+        to_decimals(10u64),
+        to_decimals(10u64),
+        Uint128::zero(),
+        Uint128::zero(),
+    )
+    .unwrap();
+    assert_eq!(open_times(&storage, CandleInterval::Minute), vec![120, now]);
+
+    update_candles(
+        &mut storage,
+        now + 60,
+        to_decimals(10u64),
+        to_decimals(10u64),
+        Uint128::zero(),
+        Uint128::zero(),
+    )
+    .unwrap();
+    assert_eq!(
+        open_times(&storage, CandleInterval::Minute),
+        vec![now, now + 60]
+    );
+
+    // daily candles are kept
+    assert_eq!(
+        open_times(&storage, CandleInterval::Day),
+        vec![0, now - now % 86_400]
+    );
+}
@@ -1,7 +1,12 @@
 use crate::contract::{execute, instantiate, query};
+use crate::state::{read_reserve_snapshot, read_reserve_snapshot_counter};
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{from_binary, Uint128};
-use margined_perp::margined_vamm::{ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse};
+use cosmwasm_std::{from_binary, Response, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::{
+    Direction, ExecuteMsg, InstantiateMsg, MarketStatus, QueryMsg, RepegBudgetResponse,
+    StateResponse,
+};
 use margined_utils::testing::to_decimals;
 
 #[test]
@@ -21,6 +26,7 @@ fn test_change_reserve() {
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
         initial_margin_ratio: to_decimals(1),
+        pricing_curve: None,
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -72,6 +78,7 @@ fn test_repeg_price() {
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
         initial_margin_ratio: to_decimals(1),
+        pricing_curve: None,
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -110,7 +117,7 @@ fn test_repeg_price() {
     let info = mock_info("addr0001", &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
 
-    // whitelist can update repeg price
+    // whitelist can not update repeg price
     let msg = ExecuteMsg::AddWhitelist {
         address: "addr0001".to_string(),
     };
@@ -120,5 +127,311 @@ fn test_repeg_price() {
         new_price: Some(new_spot_price),
     };
     let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(res.to_string(), "Generic error: sender not margin engine");
+}
+
+#[test]
+fn test_repeg_price_only_margin_engine() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100_000),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("engine".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: to_decimals(1),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the owner can not move the reserves without the engine settling the repeg cost
+    let msg = ExecuteMsg::RepegPrice {
+        new_price: Some(to_decimals(5)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(res.to_string(), "Generic error: sender not margin engine");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.quote_asset_reserve, to_decimals(100_000));
+    assert_eq!(state.base_asset_reserve, to_decimals(10_000));
+
+    let msg = ExecuteMsg::RepegPrice {
+        new_price: Some(to_decimals(5)),
+    };
+    let info = mock_info("engine", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SpotPrice {}).unwrap();
+    let spot_price: Uint128 = from_binary(&res).unwrap();
+    assert!(spot_price.abs_diff(to_decimals(5)).u128() < 10);
+}
+
+#[test]
+fn test_repeg_price_cost() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(1_000),
+        base_asset_reserve: to_decimals(100),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: to_decimals(1),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("addr0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
+    )
+    .unwrap();
+
+    // net long of 37.5 worth 600
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(600),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the long is worth 682.016978267 after the repeg from 25.6 to 30
+    let msg = ExecuteMsg::RepegPrice {
+        new_price: Some(to_decimals(30)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes
+            .iter()
+            .find(|&attr| attr.key == "repeg_cost")
+            .unwrap()
+            .value,
+        "82016978267",
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::RepegBudget {}).unwrap();
+    let budget: RepegBudgetResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        budget,
+        RepegBudgetResponse {
+            budget: Uint128::zero(),
+            spent: Uint128::from(82_016_978_267u128),
+            period_end: mock_env().block.time.seconds() + 3_600u64,
+        }
+    );
+
+    // automatic repegs are disabled until a threshold is set
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AutoRepeg {}).unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: auto repeg is disabled");
+}
+
+#[test]
+fn test_adjust_liquidity() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(1_000),
+        base_asset_reserve: to_decimals(100),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: to_decimals(1),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("addr0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
+    )
+    .unwrap();
+
+    // net long of 37.5 worth 600
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(600),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AdjustLiquidity {
+        revenue: Integer::new_positive(to_decimals(8)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: liquidity adjustment is disabled"
+    );
+
+    // a full decrease would empty the reserves
+    let msg = ExecuteMsg::UpdateConfig {
+        base_asset_holding_cap: None,
+        open_interest_notional_cap: None,
+        toll_ratio: None,
+        spread_ratio: None,
+        fluctuation_limit_ratio: None,
+        margin_engine: None,
+        insurance_fund: None,
+        pricefeed: None,
+        spot_price_twap_interval: None,
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: Some(to_decimals(1)),
+        liquidity_budget: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: max_liquidity_change_ratio should be less than one"
+    );
+
+    let msg = ExecuteMsg::UpdateConfig {
+        base_asset_holding_cap: None,
+        open_interest_notional_cap: None,
+        toll_ratio: None,
+        spread_ratio: None,
+        fluctuation_limit_ratio: None,
+        margin_engine: None,
+        insurance_fund: None,
+        pricefeed: None,
+        spot_price_twap_interval: None,
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: Some(Uint128::from(100_000_000u128)), // 0.1
+        liquidity_budget: Some(to_decimals(2)),
+    };
+    let info = mock_info("addr0000", &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let attribute = |res: &Response, key: &str| {
+        res.attributes
+            .iter()
+            .find(|&attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+
+    // a revenue of 8 deepens the reserves by 8 / 1600 = 0.5%
+    let msg = ExecuteMsg::AdjustLiquidity {
+        revenue: Integer::new_positive(to_decimals(8)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(attribute(&res, "liquidity_multiplier"), "1005000000");
+    assert_eq!(attribute(&res, "liquidity_cost"), "1121495327");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.quote_asset_reserve, to_decimals(1_608));
+    assert_eq!(state.base_asset_reserve, Uint128::from(62_812_500_000u128));
+
+    // the latest snapshot is priced on the rescaled reserves
+    let height = read_reserve_snapshot_counter(&deps.storage).unwrap();
+    let snapshot = read_reserve_snapshot(&deps.storage, height).unwrap();
+    assert_eq!(snapshot.quote_asset_reserve, to_decimals(1_608));
+    assert_eq!(
+        snapshot.base_asset_reserve,
+        Uint128::from(62_812_500_000u128)
+    );
+    assert_eq!(snapshot.curve_quote_asset_reserve, to_decimals(1_608));
+    assert_eq!(
+        snapshot.curve_base_asset_reserve,
+        Uint128::from(62_812_500_000u128)
+    );
+
+    // the change is capped at 10%, whose cost is over the budget so it is skipped
+    let msg = ExecuteMsg::AdjustLiquidity {
+        revenue: Integer::new_positive(to_decimals(1_000)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(attribute(&res, "liquidity_multiplier"), "1000000000");
+    assert_eq!(attribute(&res, "liquidity_cost"), "0");
+
+    // losses of the insurance fund shrink the reserves, which it gains from
+    let msg = ExecuteMsg::AdjustLiquidity {
+        revenue: Integer::new_negative(to_decimals(8)),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(attribute(&res, "liquidity_multiplier"), "995024876");
+    assert_eq!(attribute(&res, "liquidity_cost"), "-1121495238");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        state.quote_asset_reserve,
+        Uint128::from(1_600_000_000_608u128)
+    );
+    assert_eq!(state.base_asset_reserve, Uint128::from(62_500_000_023u128));
+
+    // only the margin engine adjusts the liquidity
+    let msg = ExecuteMsg::AdjustLiquidity {
+        revenue: Integer::new_positive(to_decimals(8)),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: sender not margin engine");
 }
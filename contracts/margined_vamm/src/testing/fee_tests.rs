@@ -1,11 +1,11 @@
 use cosmwasm_std::{StdError, Uint128};
-use margined_perp::margined_vamm::CalcFeeResponse;
+use margined_perp::margined_vamm::{CalcFeeResponse, Direction};
 use margined_utils::{
     cw_multi_test::Executor,
-    testing::{to_decimals, SimpleScenario},
+    testing::{to_decimals, SimpleScenario, VammScenario},
 };
 
-use crate::testing::new_simple_scenario;
+use crate::testing::{new_simple_scenario, new_vammscenario};
 
 #[test]
 fn test_calc_fee() {
@@ -24,7 +24,7 @@ fn test_calc_fee() {
         .unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
-    let result = vamm.calc_fee(&router.wrap(), to_decimals(10)).unwrap();
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(10), None).unwrap();
 
     assert_eq!(
         result,
@@ -55,11 +55,22 @@ fn test_set_diff_fee_ratio() {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
             None
         )
         .unwrap();
     router.execute(owner.clone(), msg).unwrap();
-    let result = vamm.calc_fee(&router.wrap(), to_decimals(100)).unwrap();
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(100), None).unwrap();
 
     assert_eq!(
         result,
@@ -90,12 +101,23 @@ fn test_set_fee_ratio_zero() {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
             None
         )
         .unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
-    let result = vamm.calc_fee(&router.wrap(), to_decimals(100)).unwrap();
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(100), None).unwrap();
     assert_eq!(
         result,
         CalcFeeResponse {
@@ -109,7 +131,7 @@ fn test_set_fee_ratio_zero() {
 fn test_calc_fee_input_zero() {
     let SimpleScenario { router, vamm, .. } = new_simple_scenario();
 
-    let result = vamm.calc_fee(&router.wrap(), to_decimals(0)).unwrap();
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(0), None).unwrap();
     assert_eq!(
         result,
         CalcFeeResponse {
@@ -139,6 +161,17 @@ fn test_update_not_owner() {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
             None
         )
         .unwrap();
@@ -150,3 +183,133 @@ fn test_update_not_owner() {
         err.downcast().unwrap()
     );
 }
+
+#[test]
+fn test_calc_fee_dynamic_spread_follows_oracle_divergence() {
+    let VammScenario {
+        mut router,
+        owner,
+        pricefeed,
+        vamm,
+        ..
+    } = new_vammscenario();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // spread between 0.005 and 0.05
+    let msg = vamm
+        .set_spread_limits(
+            Uint128::from(5_000_000u128),
+            Uint128::from(50_000_000u128),
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // without a direction the spread ratio of 0.01 is used
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(1), None).unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(10_000_000u128));
+
+    // buying moves the price from 10 to 10.02001, away from the oracle price of 10
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(1), Some(Direction::AddToAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(12_001_000u128));
+
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(1), Some(Direction::RemoveFromAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(11_999_000u128));
+
+    // the spread is capped at 0.05
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(100), Some(Direction::AddToAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, to_decimals(5));
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(11), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // buying now moves the price towards the oracle price of 11
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(1), Some(Direction::AddToAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(8_180_910u128));
+
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(1), Some(Direction::RemoveFromAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(11_817_273u128));
+
+    // the spread is floored at 0.005
+    let result = vamm
+        .calc_fee(&router.wrap(), to_decimals(10), Some(Direction::AddToAmm))
+        .unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(50_000_000u128));
+}
+
+#[test]
+fn test_calc_fee_dynamic_spread_follows_volatility() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        ..
+    } = new_vammscenario();
+
+    // the whole realised volatility is added to the spread
+    let msg = vamm
+        .set_spread_limits(
+            Uint128::from(5_000_000u128),
+            Uint128::from(50_000_000u128),
+            to_decimals(1),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // no volatility yet
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(10), None).unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(100_000_000u128));
+
+    // the price moves from 10 to 10.080159999 and back to 9.999999999
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+    let msg = vamm
+        .swap_input(Direction::AddToAmm, 0, to_decimals(4), Uint128::zero(), false)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+    let msg = vamm
+        .swap_input(
+            Direction::RemoveFromAmm,
+            0,
+            to_decimals(4),
+            Uint128::zero(),
+            false,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // realised volatility of 0.008015999 + 0.007952254
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(10), None).unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(259_682_530u128));
+
+    // the swaps fall out of the one hour window
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_601);
+        block.height += 1;
+    });
+    let result = vamm.calc_fee(&router.wrap(), to_decimals(10), None).unwrap();
+    assert_eq!(result.spread_fee, Uint128::from(100_000_000u128));
+}
@@ -3,7 +3,7 @@ use cosmwasm_std::testing::{
     mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
 };
 use cosmwasm_std::{from_binary, Env, OwnedDeps, Uint128};
-use margined_perp::margined_vamm::{Direction, ExecuteMsg, InstantiateMsg, QueryMsg};
+use margined_perp::margined_vamm::{Direction, ExecuteMsg, InstantiateMsg, MarketStatus, QueryMsg};
 use margined_utils::testing::{parse_event, to_decimals};
 
 pub struct TestingEnv {
@@ -28,13 +28,16 @@ fn setup() -> TestingEnv {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
 
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::SetOpen { open: true };
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
     let info = mock_info("addr0000", &[]);
     execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
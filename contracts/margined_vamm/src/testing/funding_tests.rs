@@ -1,4 +1,6 @@
 use cosmwasm_std::{StdError, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::FundingFormula;
 use margined_utils::{cw_multi_test::Executor, testing::VammScenario};
 
 use crate::testing::new_vammscenario;
@@ -137,3 +139,157 @@ fn test_cant_settle_funding_multiple_times_at_once_even_settle_funding_delay() {
         err.downcast().unwrap()
     );
 }
+
+#[test]
+fn test_settle_funding_clamps_to_max_funding_rate() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        pricefeed,
+        ..
+    } = new_vammscenario();
+
+    // the vamm trades at 10 while the oracle is at 5
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(5_000_000_000u128),
+            1_000_000_000,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = vamm
+        .set_funding_config(FundingFormula::Premium, Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    // uncapped the funding rate would be 5 / 24 / 5 = 4.16%
+    let msg = vamm.settle_funding().unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let attribute = |key: &str| {
+        res.events
+            .iter()
+            .flat_map(|event| event.attributes.iter())
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(attribute("premium"), "5000000000");
+    assert_eq!(attribute("premium_fraction"), "50000000");
+    assert_eq!(attribute("funding_rate_clamped"), "true");
+
+    let state = vamm.state(&router.wrap()).unwrap();
+    assert_eq!(state.funding_rate, Integer::new_positive(10_000_000u128));
+}
+
+#[test]
+fn test_settle_funding_premium_index_damps_interest_adjustment() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        pricefeed,
+        ..
+    } = new_vammscenario();
+
+    let msg = vamm
+        .set_funding_config(
+            FundingFormula::PremiumIndex {
+                interest_rate: Uint128::from(300_000u128), // 0.03%
+                damping_band: Uint128::from(500_000u128),  // 0.05%
+            },
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // premium of 0.002 is within the damping band of the interest rate
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(9_998_000_000u128),
+            1_000_000_000,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    let msg = vamm.settle_funding().unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let interest_adjustment = res
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "interest_adjustment")
+        .unwrap();
+    assert_eq!(interest_adjustment.value, "999400");
+
+    // the funding rate is the interest rate per funding period
+    let state = vamm.state(&router.wrap()).unwrap();
+    assert_eq!(state.funding_rate, Integer::new_positive(12_500u128));
+
+    // premium of 0.01 is outside of the band, so the adjustment is the band
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(9_990_000_000u128),
+            1_000_003_600,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    let msg = vamm.settle_funding().unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let interest_adjustment = res
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "interest_adjustment")
+        .unwrap();
+    assert_eq!(interest_adjustment.value, "-4995000");
+
+    let state = vamm.state(&router.wrap()).unwrap();
+    assert_eq!(state.funding_rate, Integer::new_positive(20_874u128));
+}
+
+#[test]
+fn test_update_funding_config_rejects_invalid_ratio() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        ..
+    } = new_vammscenario();
+
+    let msg = vamm
+        .set_funding_config(FundingFormula::Premium, Uint128::from(2_000_000_000u128))
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Invalid ratio".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    let config = vamm.config(&router.wrap()).unwrap();
+    assert_eq!(config.funding_formula, FundingFormula::Premium);
+    assert_eq!(config.max_funding_rate, Uint128::zero());
+}
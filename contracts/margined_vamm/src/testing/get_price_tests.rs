@@ -24,7 +24,8 @@ fn test_get_input_add_to_amm() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -140,7 +141,8 @@ fn test_get_output_amount() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -211,7 +213,8 @@ fn test_get_input_and_output_price_with_reserves() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
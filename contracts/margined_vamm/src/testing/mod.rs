@@ -1,8 +1,10 @@
+mod candle_tests;
 mod fee_tests;
 mod fluctuation_tests;
 mod funding_tests;
 mod get_price_tests;
-mod set_open_tests;
+mod pricing_curve_tests;
+mod set_status_tests;
 mod spread_limit_tests;
 mod swap_input_output_tests;
 mod swap_tests;
@@ -0,0 +1,287 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{Addr, StdError, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::{
+    CurveReservesResponse, Direction, InstantiateMsg, MarketStatus, PricingCurve,
+};
+use margined_utils::{
+    contracts::helpers::VammController,
+    create_entry_points_testing,
+    cw_multi_test::{App, Executor},
+    testing::{to_decimals, VammScenario},
+};
+
+use crate::{contract::instantiate, testing::new_vammscenario};
+
+fn instantiate_msg(pricefeed: &Addr, owner: &Addr, concentration: Uint128) -> InstantiateMsg {
+    InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "USD".to_string(),
+        base_asset: "ETH".to_string(),
+        quote_asset_reserve: to_decimals(1_000),
+        base_asset_reserve: to_decimals(100),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        pricefeed: pricefeed.to_string(),
+        margin_engine: Some(owner.to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: Some(PricingCurve::OracleAnchored { concentration }),
+    }
+}
+
+fn instantiate_anchored_vamm(
+    router: &mut App,
+    pricefeed: &Addr,
+    owner: &Addr,
+    concentration: Uint128,
+) -> VammController {
+    let vamm_id = router.store_code(Box::new(create_entry_points_testing!(crate)));
+    let vamm_addr = router
+        .instantiate_contract(
+            vamm_id,
+            owner.clone(),
+            &instantiate_msg(pricefeed, owner, concentration),
+            &[],
+            "anchored vamm",
+            None,
+        )
+        .unwrap();
+    let vamm = VammController(vamm_addr);
+
+    let msg = vamm.set_status(MarketStatus::Active).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    vamm
+}
+
+#[test]
+fn test_oracle_anchored_curve() {
+    let VammScenario {
+        mut router,
+        owner,
+        pricefeed,
+        ..
+    } = new_vammscenario();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // ten times the liquidity of the reserves around the oracle price of 10
+    let vamm = instantiate_anchored_vamm(&mut router, &pricefeed.addr(), &owner, to_decimals(10));
+
+    let reserves = vamm.curve_reserves(&router.wrap()).unwrap();
+    assert_eq!(
+        reserves,
+        CurveReservesResponse {
+            quote_asset_reserve: to_decimals(10_000),
+            base_asset_reserve: to_decimals(1_000),
+        }
+    );
+    let price = vamm.spot_price(&router.wrap()).unwrap();
+    assert_eq!(price, to_decimals(10));
+
+    // long 100 buys 9.900990099 instead of the 9.090909090 of the reserves
+    let amount = vamm
+        .input_amount(&router.wrap(), Direction::AddToAmm, to_decimals(100))
+        .unwrap();
+    assert_eq!(amount, Uint128::from(9_900_990_099u128));
+
+    let msg = vamm
+        .swap_input(
+            Direction::AddToAmm,
+            0,
+            to_decimals(100),
+            Uint128::zero(),
+            false,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // the reserves keep the spot price of the curve after the swap
+    let state = vamm.state(&router.wrap()).unwrap();
+    assert_eq!(state.base_asset_reserve, Uint128::from(90_099_009_901u128));
+    assert_eq!(
+        state.quote_asset_reserve,
+        Uint128::from(919_100_000_000u128)
+    );
+    assert_eq!(
+        state.total_position_size,
+        Integer::new_positive(9_900_990_099u128)
+    );
+
+    let price = vamm.spot_price(&router.wrap()).unwrap();
+    assert_eq!(price, Uint128::from(10_200_999_999u128));
+
+    // closing at the same oracle price returns the quote paid
+    let amount = vamm
+        .output_amount(
+            &router.wrap(),
+            Direction::AddToAmm,
+            state.total_position_size.value,
+        )
+        .unwrap();
+    assert_eq!(amount, Uint128::from(99_999_999_999u128));
+
+    // the curve follows the oracle while the net position is kept
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(12), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let price = vamm.spot_price(&router.wrap()).unwrap();
+    assert_eq!(price, Uint128::from(12_241_199_999u128));
+
+    let amount = vamm
+        .output_amount(
+            &router.wrap(),
+            Direction::AddToAmm,
+            state.total_position_size.value,
+        )
+        .unwrap();
+    assert_eq!(amount, Uint128::from(119_999_999_999u128));
+
+    let msg = vamm
+        .swap_output(
+            Direction::AddToAmm,
+            0,
+            state.total_position_size.value,
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let state = vamm.state(&router.wrap()).unwrap();
+    assert_eq!(state.base_asset_reserve, to_decimals(100));
+    assert_eq!(state.total_position_size, Integer::zero());
+
+    let price = vamm.spot_price(&router.wrap()).unwrap();
+    assert_eq!(price, to_decimals(12));
+}
+
+#[test]
+fn test_oracle_anchored_curve_concentration_below_one() {
+    let mut deps = mock_dependencies();
+    let msg = instantiate_msg(
+        &Addr::unchecked("oracle"),
+        &Addr::unchecked("addr0000"),
+        Uint128::from(500_000_000u128),
+    );
+    let info = mock_info("addr0000", &[]);
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+
+    assert_eq!(
+        res.to_string(),
+        "Generic error: concentration must be at least one"
+    );
+}
+
+#[test]
+fn test_oracle_anchored_curve_twap() {
+    let VammScenario {
+        mut router,
+        owner,
+        pricefeed,
+        ..
+    } = new_vammscenario();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let vamm = instantiate_anchored_vamm(&mut router, &pricefeed.addr(), &owner, to_decimals(10));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = vamm
+        .swap_input(
+            Direction::AddToAmm,
+            0,
+            to_decimals(100),
+            Uint128::zero(),
+            false,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let state = vamm.state(&router.wrap()).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(1_000);
+        block.height += 1;
+    });
+
+    // closing is priced on the curve over the twap, not the 91.0 of the reserves
+    let amount = vamm
+        .output_twap(
+            &router.wrap(),
+            Direction::AddToAmm,
+            state.total_position_size.value,
+        )
+        .unwrap();
+    assert!(amount.abs_diff(to_decimals(100)).u128() < 10);
+}
+
+#[test]
+fn test_oracle_anchored_curve_fluctuation_limit() {
+    let VammScenario {
+        mut router,
+        owner,
+        pricefeed,
+        ..
+    } = new_vammscenario();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10), 1_000_000_000)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let vamm = instantiate_anchored_vamm(&mut router, &pricefeed.addr(), &owner, to_decimals(10));
+
+    // 5%
+    let msg = vamm
+        .set_fluctuation_limit_ratio(Uint128::from(50_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // each long of 100 moves the price by about 2% from the previous one
+    let swap = vamm
+        .swap_input(
+            Direction::AddToAmm,
+            0,
+            to_decimals(100),
+            Uint128::zero(),
+            false,
+        )
+        .unwrap();
+    router.execute(owner.clone(), swap.clone()).unwrap();
+    router.execute(owner.clone(), swap.clone()).unwrap();
+
+    // the third goes to 10.609 which is over 5% from the 10 the block opened at
+    let err = router.execute(owner.clone(), swap.clone()).unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "price is over fluctuation limit".to_string(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // the next block is bounded around the price it opens at
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+    router.execute(owner.clone(), swap).unwrap();
+}
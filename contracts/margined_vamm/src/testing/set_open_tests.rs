@@ -1,256 +0,0 @@
-use crate::contract::{execute, instantiate, query};
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{from_binary, Uint128};
-use margined_perp::margined_vamm::{
-    Direction, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse,
-};
-use margined_utils::testing::to_decimals;
-
-#[test]
-fn test_set_open_admin_open_amm() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: true };
-
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(state.open, true,);
-}
-
-#[test]
-fn test_set_open_init_next_funding_time_zero() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(state.next_funding_time, 0u64,);
-}
-
-#[test]
-fn test_set_open_admin_open_updates_next_funding_time() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: true };
-
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        state.next_funding_time,
-        mock_env().block.time.seconds() + 3_600u64,
-    );
-}
-
-#[test]
-fn test_set_open_admin_closes_amm() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: true };
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: false };
-
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(state.open, false,);
-}
-
-#[test]
-fn test_set_open_cant_do_anything_when_its_beginning() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SettleFunding {};
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-
-    let msg = ExecuteMsg::SwapInput {
-        direction: Direction::AddToAmm,
-        quote_asset_amount: to_decimals(600),
-        base_asset_limit: Uint128::zero(),
-        can_go_over_fluctuation: false,
-        position_id: 0u64
-    };
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-
-    let msg = ExecuteMsg::SwapOutput {
-        direction: Direction::AddToAmm,
-        base_asset_amount: to_decimals(600),
-        quote_asset_limit: Uint128::zero(),
-        position_id: 0u64
-    };
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-}
-
-#[test]
-fn test_set_open_cant_do_anything_when_closed() {
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {
-        decimals: 9u8,
-        quote_asset: "ETH".to_string(),
-        base_asset: "USD".to_string(),
-        quote_asset_reserve: to_decimals(100),
-        base_asset_reserve: to_decimals(10_000),
-        funding_period: 3_600_u64,
-        toll_ratio: Uint128::zero(),
-        spread_ratio: Uint128::zero(),
-        fluctuation_limit_ratio: Uint128::zero(),
-        margin_engine: Some("addr0000".to_string()),
-        insurance_fund: Some("insurance_fund".to_string()),
-        pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
-    };
-    let info = mock_info("addr0000", &[]);
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: true };
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SetOpen { open: false };
-
-    let info = mock_info("addr0000", &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::SettleFunding {};
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-
-    let msg = ExecuteMsg::SwapInput {
-        direction: Direction::AddToAmm,
-        quote_asset_amount: to_decimals(600),
-        base_asset_limit: Uint128::zero(),
-        can_go_over_fluctuation: false,
-        position_id: 0u64
-    };
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-
-    let msg = ExecuteMsg::SwapOutput {
-        direction: Direction::AddToAmm,
-        base_asset_amount: to_decimals(600),
-        quote_asset_limit: Uint128::zero(),
-        position_id: 0u64
-    };
-    let info = mock_info("addr0000", &[]);
-    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
-}
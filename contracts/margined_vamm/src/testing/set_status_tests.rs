@@ -0,0 +1,468 @@
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::{read_state, KEY_STATE};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, to_vec, Storage, Uint128};
+use margined_perp::margined_vamm::{
+    Direction, ExecuteMsg, InstantiateMsg, MarketStatus, MigrateMsg, QueryMsg, StateResponse,
+};
+use margined_utils::testing::to_decimals;
+
+#[test]
+fn test_set_status_admin_open_amm() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.status, MarketStatus::Active);
+}
+
+#[test]
+fn test_set_status_init_next_funding_time_zero() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.next_funding_time, 0u64,);
+}
+
+#[test]
+fn test_set_status_admin_open_updates_next_funding_time() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        state.next_funding_time,
+        mock_env().block.time.seconds() + 3_600u64,
+    );
+}
+
+#[test]
+fn test_set_status_admin_closes_amm() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Paused,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.status, MarketStatus::Paused);
+}
+
+#[test]
+fn test_set_status_cant_do_anything_when_its_beginning() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SettleFunding {};
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(600),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+
+    let msg = ExecuteMsg::SwapOutput {
+        direction: Direction::AddToAmm,
+        base_asset_amount: to_decimals(600),
+        quote_asset_limit: Uint128::zero(),
+        position_id: 0u64
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+}
+
+#[test]
+fn test_set_status_cant_do_anything_when_closed() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Paused,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SettleFunding {};
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(600),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+
+    let msg = ExecuteMsg::SwapOutput {
+        direction: Direction::AddToAmm,
+        base_asset_amount: to_decimals(600),
+        quote_asset_limit: Uint128::zero(),
+        position_id: 0u64
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: amm is closed".to_string()
+    );
+}
+
+#[test]
+fn test_set_status_reduce_only_can_swap_but_not_settle() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the insurance fund can move the market to reduce only
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::ReduceOnly,
+    };
+    let info = mock_info("insurance_fund", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.status, MarketStatus::ReduceOnly);
+    assert_eq!(
+        state.next_funding_time,
+        mock_env().block.time.seconds() + 3_600u64,
+    );
+
+    // the margin engine only closes positions of a reduce only market
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(10),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the market can not be settled without settling first
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Settled,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: market is not settling".to_string()
+    );
+}
+
+#[test]
+fn test_set_status_settling_market_can_only_be_settled() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Settling,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the margin engine only closes positions of a settling market
+    let msg = ExecuteMsg::SwapInput {
+        direction: Direction::AddToAmm,
+        quote_asset_amount: to_decimals(10),
+        base_asset_limit: Uint128::zero(),
+        can_go_over_fluctuation: false,
+        position_id: 0u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: market is settling".to_string()
+    );
+
+    // the market can not be settled until every position is closed
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Settled,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: market has open positions".to_string()
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    let msg = ExecuteMsg::SwapOutput {
+        direction: Direction::AddToAmm,
+        base_asset_amount: state.total_position_size.value,
+        quote_asset_limit: Uint128::zero(),
+        position_id: 0u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Settled,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Paused,
+    };
+    let info = mock_info("addr0000", &[]);
+    let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        result.to_string(),
+        "Generic error: market is settled".to_string()
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.status, MarketStatus::Settled);
+}
+
+#[test]
+fn test_migrate_open_flag_to_market_status() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the state as it was stored with the open flag
+    let state = read_state(&deps.storage).unwrap();
+    let legacy = String::from_utf8(to_vec(&state).unwrap())
+        .unwrap()
+        .replace(r#""status":"active""#, r#""open":true"#);
+    deps.storage.set(KEY_STATE, legacy.as_bytes());
+    assert!(read_state(&deps.storage).is_err());
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    assert_eq!(read_state(&deps.storage).unwrap(), state);
+}
@@ -3,7 +3,7 @@ use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_binary, Uint128};
 use margined_common::integer::Integer;
 use margined_perp::margined_vamm::{
-    Direction, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse,
+    Direction, ExecuteMsg, InstantiateMsg, MarketStatus, QueryMsg, StateResponse,
 };
 use margined_utils::testing::to_decimals;
 
@@ -23,7 +23,8 @@ fn test_use_getoutputprice_use_to_swapinput_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -34,7 +35,9 @@ fn test_use_getoutputprice_use_to_swapinput_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -105,7 +108,8 @@ fn test_use_getoutputprice_use_to_swapinput_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -116,7 +120,9 @@ fn test_use_getoutputprice_use_to_swapinput_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -187,7 +193,8 @@ fn test_use_getinputprice_long_use_to_swapoutput() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -198,7 +205,9 @@ fn test_use_getinputprice_long_use_to_swapoutput() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -268,7 +277,8 @@ fn test_use_getinputprice_short_use_to_swapoutput() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -279,7 +289,9 @@ fn test_use_getinputprice_short_use_to_swapoutput() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -349,7 +361,8 @@ fn test_swap_input_twice_short_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -360,7 +373,9 @@ fn test_swap_input_twice_short_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -392,7 +407,7 @@ fn test_swap_input_twice_short_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_000),
             base_asset_reserve: Uint128::from(100_000_000_001u128),
             total_position_size: Integer::new_negative(1u128),
@@ -418,7 +433,8 @@ fn test_swap_input_twice_long_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -429,7 +445,9 @@ fn test_swap_input_twice_long_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -461,7 +479,7 @@ fn test_swap_input_twice_long_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_000),
             base_asset_reserve: Uint128::from(100_000_000_001u128),
             total_position_size: Integer::new_negative(1u128),
@@ -487,7 +505,8 @@ fn test_swap_output_twice_short_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -498,7 +517,9 @@ fn test_swap_output_twice_short_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -528,7 +549,7 @@ fn test_swap_output_twice_short_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: Uint128::from(1_000_000_000_001u128),
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
@@ -554,7 +575,8 @@ fn test_swap_output_twice_long_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -565,7 +587,9 @@ fn test_swap_output_twice_long_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -595,7 +619,7 @@ fn test_swap_output_twice_long_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: Uint128::from(1_000_000_000_001u128),
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
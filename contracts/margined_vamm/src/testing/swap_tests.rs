@@ -5,7 +5,8 @@ use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_binary, Addr, Uint128};
 use margined_common::integer::Integer;
 use margined_perp::margined_vamm::{
-    ConfigResponse, Direction, ExecuteMsg, InstantiateMsg, OwnerResponse, QueryMsg, StateResponse,
+    ConfigResponse, Direction, ExecuteMsg, FundingFormula, InstantiateMsg, MarketStatus,
+    OwnerResponse, PricingCurve, QueryMsg, StateResponse,
 };
 use margined_utils::testing::{parse_event, to_decimals, DECIMAL_MULTIPLIER};
 
@@ -25,7 +26,8 @@ fn test_instantiation() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -48,7 +50,19 @@ fn test_instantiation() {
             pricefeed: Addr::unchecked("oracle".to_string()),
             funding_period: 3_600u64,
             spot_price_twap_interval: ONE_HOUR_IN_SECONDS,
-            initial_margin_ratio: Uint128::from(50_000u128)
+            initial_margin_ratio: Uint128::from(50_000u128),
+            snapshot_retention: 0u64,
+            repeg_threshold: Uint128::zero(),
+            repeg_budget: Uint128::zero(),
+            min_spread_ratio: Uint128::zero(),
+            max_spread_ratio: Uint128::zero(),
+            volatility_spread_ratio: Uint128::zero(),
+            funding_formula: FundingFormula::Premium,
+            max_funding_rate: Uint128::zero(),
+            continuous_funding: false,
+            max_liquidity_change_ratio: Uint128::zero(),
+            liquidity_budget: Uint128::zero(),
+            pricing_curve: PricingCurve::ConstantProduct
         }
     );
 
@@ -57,7 +71,7 @@ fn test_instantiation() {
     assert_eq!(
         state,
         StateResponse {
-            open: false,
+            status: MarketStatus::Paused,
             quote_asset_reserve: Uint128::from(100_000_000_000u128),
             base_asset_reserve: Uint128::from(10_000_000_000_000u128),
             total_position_size: Integer::default(),
@@ -83,7 +97,8 @@ fn test_update_owner() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -120,7 +135,8 @@ fn test_bad_decimals() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -148,7 +164,8 @@ fn test_bad_reserves() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -172,7 +189,8 @@ fn test_bad_reserves() {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -202,7 +220,8 @@ fn test_bad_asset_strings() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -224,7 +243,8 @@ fn test_bad_asset_strings() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
 
@@ -249,7 +269,8 @@ fn test_bad_twap_interval() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -265,7 +286,18 @@ fn test_bad_twap_interval() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: Some(59u64),
-        initial_margin_ratio: None
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: None,
+        liquidity_budget: None
     };
 
     let info = mock_info("addr0000", &[]);
@@ -287,7 +319,18 @@ fn test_bad_twap_interval() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: Some(ONE_WEEK_IN_SECONDS + 1),
-        initial_margin_ratio: None
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: None,
+        liquidity_budget: None
     };
 
     let info = mock_info("addr0000", &[]);
@@ -315,7 +358,8 @@ fn test_update_config() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -331,7 +375,18 @@ fn test_update_config() {
         insurance_fund: Some("new_insurance_fund".to_string()),
         pricefeed: None,
         spot_price_twap_interval: Some(ONE_MINUTE_IN_SECONDS),
-        initial_margin_ratio: None
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: None,
+        liquidity_budget: None
     };
 
     let info = mock_info("addr0000", &[]);
@@ -355,7 +410,19 @@ fn test_update_config() {
             pricefeed: Addr::unchecked("oracle".to_string()),
             funding_period: 3_600u64,
             spot_price_twap_interval: ONE_MINUTE_IN_SECONDS,
-            initial_margin_ratio: Uint128::from(50_000u128)
+            initial_margin_ratio: Uint128::from(50_000u128),
+            snapshot_retention: 0u64,
+            repeg_threshold: Uint128::zero(),
+            repeg_budget: Uint128::zero(),
+            min_spread_ratio: Uint128::zero(),
+            max_spread_ratio: Uint128::zero(),
+            volatility_spread_ratio: Uint128::zero(),
+            funding_formula: FundingFormula::Premium,
+            max_funding_rate: Uint128::zero(),
+            continuous_funding: false,
+            max_liquidity_change_ratio: Uint128::zero(),
+            liquidity_budget: Uint128::zero(),
+            pricing_curve: PricingCurve::ConstantProduct
         }
     );
 }
@@ -376,7 +443,8 @@ fn test_update_config_fail() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -392,7 +460,18 @@ fn test_update_config_fail() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: None,
-        initial_margin_ratio: None
+        initial_margin_ratio: None,
+        snapshot_retention: None,
+        repeg_threshold: None,
+        repeg_budget: None,
+        min_spread_ratio: None,
+        max_spread_ratio: None,
+        volatility_spread_ratio: None,
+        funding_formula: None,
+        max_funding_rate: None,
+        continuous_funding: None,
+        max_liquidity_change_ratio: None,
+        liquidity_budget: None
     };
 
     let info = mock_info("addr0000", &[]);
@@ -419,7 +498,8 @@ fn test_swap_input_zero_amount() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("addr0000".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -430,7 +510,9 @@ fn test_swap_input_zero_amount() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -450,7 +532,7 @@ fn test_swap_input_zero_amount() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_000),
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::zero(),
@@ -476,7 +558,8 @@ fn test_swap_output_zero_amount() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("addr0000".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -487,7 +570,9 @@ fn test_swap_output_zero_amount() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -506,7 +591,7 @@ fn test_swap_output_zero_amount() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_000),
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::zero(),
@@ -532,7 +617,8 @@ fn test_swap_input_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -543,7 +629,9 @@ fn test_swap_input_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -563,7 +651,7 @@ fn test_swap_input_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_600),
             base_asset_reserve: Uint128::from(62_500_000_000u128),
             total_position_size: Integer::new_positive(37_500_000_000u128),
@@ -589,7 +677,8 @@ fn test_swap_input_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -600,7 +689,9 @@ fn test_swap_input_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -620,7 +711,7 @@ fn test_swap_input_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(400),
             base_asset_reserve: to_decimals(250),
             total_position_size: Integer::new_negative(to_decimals(150)),
@@ -646,7 +737,8 @@ fn test_swap_output_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -657,7 +749,9 @@ fn test_swap_output_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -676,7 +770,7 @@ fn test_swap_output_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(400),
             base_asset_reserve: to_decimals(250),
             total_position_size: Integer::new_negative(to_decimals(150)),
@@ -702,7 +796,8 @@ fn test_swap_output_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -713,7 +808,9 @@ fn test_swap_output_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -732,7 +829,7 @@ fn test_swap_output_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(2_000),
             base_asset_reserve: to_decimals(50),
             total_position_size: Integer::new_positive(to_decimals(50)),
@@ -758,7 +855,8 @@ fn test_swap_input_short_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -769,7 +867,9 @@ fn test_swap_input_short_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -790,7 +890,7 @@ fn test_swap_input_short_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(520),
             base_asset_reserve: Uint128::from(192_307_692_308u128),
             total_position_size: Integer::new_negative(92_307_692_308u128),
@@ -815,7 +915,7 @@ fn test_swap_input_short_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1_480),
             base_asset_reserve: Uint128::from(67_567_567_568u128),
             total_position_size: Integer::new_positive(32_432_432_432u128),
@@ -841,7 +941,8 @@ fn test_swap_input_short_long_long() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -852,7 +953,9 @@ fn test_swap_input_short_long_long() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -872,7 +975,7 @@ fn test_swap_input_short_long_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(800),
             base_asset_reserve: to_decimals(125),
             total_position_size: Integer::new_negative(25_000_000_000u128),
@@ -897,7 +1000,7 @@ fn test_swap_input_short_long_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(900),
             base_asset_reserve: Uint128::from(111_111_111_112u128),
             total_position_size: Integer::new_negative(11_111_111_112u128),
@@ -922,7 +1025,7 @@ fn test_swap_input_short_long_long() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1100),
             base_asset_reserve: Uint128::from(90_909_090_910u128),
             total_position_size: Integer::new_positive(90_909_090_90u128),
@@ -948,7 +1051,8 @@ fn test_swap_input_short_long_short() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -959,7 +1063,9 @@ fn test_swap_input_short_long_short() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -979,7 +1085,7 @@ fn test_swap_input_short_long_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(800),
             base_asset_reserve: to_decimals(125),
             total_position_size: Integer::new_negative(25_000_000_000u128),
@@ -1004,7 +1110,7 @@ fn test_swap_input_short_long_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1250),
             base_asset_reserve: to_decimals(80),
             total_position_size: Integer::new_positive(20_000_000_000u128),
@@ -1029,7 +1135,7 @@ fn test_swap_input_short_long_short() {
     assert_eq!(
         state,
         StateResponse {
-            open: true,
+            status: MarketStatus::Active,
             quote_asset_reserve: to_decimals(1000),
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
@@ -1055,7 +1161,8 @@ fn test_swap_output_short_and_indivisable() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1066,7 +1173,9 @@ fn test_swap_output_short_and_indivisable() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1117,7 +1226,8 @@ fn test_swap_output_long_and_indivisable() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1128,7 +1238,9 @@ fn test_swap_output_long_and_indivisable() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1179,7 +1291,8 @@ fn test_swap_output_long_short_same_size_should_get_diff_base_asset_amount() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1190,7 +1303,9 @@ fn test_swap_output_long_short_same_size_should_get_diff_base_asset_amount() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1254,7 +1369,8 @@ fn test_force_error_swapinput_long_but_less_than_min_base_amount() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1265,7 +1381,9 @@ fn test_force_error_swapinput_long_but_less_than_min_base_amount() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1302,7 +1420,8 @@ fn test_force_error_swapinput_short_but_more_than_min_base_amount() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1313,7 +1432,9 @@ fn test_force_error_swapinput_short_but_more_than_min_base_amount() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1350,7 +1471,8 @@ fn test_swapoutput_short_slippage_limit() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1361,7 +1483,9 @@ fn test_swapoutput_short_slippage_limit() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1396,7 +1520,8 @@ fn test_swapoutput_short_at_slippage_limit() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1407,7 +1532,9 @@ fn test_swapoutput_short_at_slippage_limit() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1442,7 +1569,8 @@ fn test_swapoutput_short_force_error_min_quote_251() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1453,7 +1581,9 @@ fn test_swapoutput_short_force_error_min_quote_251() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1487,7 +1617,8 @@ fn test_swapoutput_short_force_error_min_quote_400() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1498,7 +1629,9 @@ fn test_swapoutput_short_force_error_min_quote_400() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1532,7 +1665,8 @@ fn test_swapoutput_long_slippage_limit() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1543,7 +1677,9 @@ fn test_swapoutput_long_slippage_limit() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1578,7 +1714,8 @@ fn test_swapoutput_long_at_slippage_limit() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1589,7 +1726,9 @@ fn test_swapoutput_long_at_slippage_limit() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1624,7 +1763,8 @@ fn test_swapoutput_long_force_error_min_quote_199() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1635,7 +1775,9 @@ fn test_swapoutput_long_force_error_min_quote_199() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
@@ -1669,7 +1811,8 @@ fn test_swapoutput_long_force_error_min_quote_100() {
         pricefeed: "oracle".to_string(),
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1680,7 +1823,9 @@ fn test_swapoutput_long_force_error_min_quote_100() {
         deps.as_mut(),
         mock_env(),
         info,
-        ExecuteMsg::SetOpen { open: true },
+        ExecuteMsg::SetStatus {
+            status: MarketStatus::Active,
+        },
     )
     .unwrap();
 
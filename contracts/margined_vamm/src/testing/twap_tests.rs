@@ -1,9 +1,16 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::{
+    read_first_twap_snapshot, read_reserve_snapshot, read_reserve_snapshot_counter,
+    KEY_RESERVE_SNAPSHOT, MAX_INPUT_TWAP_SNAPSHOTS,
+};
+use crate::utils::{calc_twap, TwapCalcOption, TwapInputAsset, TwapPriceCalcParams};
 use cosmwasm_std::testing::{
     mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
 };
-use cosmwasm_std::{from_binary, Env, OwnedDeps, Uint128};
-use margined_perp::margined_vamm::{Direction, ExecuteMsg, InstantiateMsg, QueryMsg};
+use cosmwasm_std::{from_binary, to_vec, Env, OwnedDeps, Storage, Uint128};
+use margined_perp::margined_vamm::{
+    Direction, ExecuteMsg, InstantiateMsg, MarketStatus, MigrateMsg, QueryMsg,
+};
 use margined_utils::testing::to_decimals;
 
 pub struct TestingEnv {
@@ -28,13 +35,16 @@ fn setup() -> TestingEnv {
         margin_engine: Some("addr0000".to_string()),
         insurance_fund: Some("insurance_fund".to_string()),
         pricefeed: "oracle".to_string(),
-        initial_margin_ratio: Uint128::from(50_000u128)
+        initial_margin_ratio: Uint128::from(50_000u128),
+        pricing_curve: None
     };
 
     let info = mock_info("addr0000", &[]);
     instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::SetOpen { open: true };
+    let msg = ExecuteMsg::SetStatus {
+        status: MarketStatus::Active,
+    };
     let info = mock_info("addr0000", &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -170,6 +180,107 @@ fn test_zero_interval() {
     assert_eq!(twap, spot);
 }
 
+#[test]
+fn test_migrate_starts_cumulative_prices_at_latest_snapshot() {
+    let mut app = setup();
+
+    // snapshots stored before the cumulative price was tracked
+    let height = read_reserve_snapshot_counter(&app.deps.storage).unwrap();
+    for index in 1..=height {
+        let mut snapshot = read_reserve_snapshot(&app.deps.storage, index).unwrap();
+        assert!(index == 1 || !snapshot.cumulative_price.is_zero());
+
+        snapshot.cumulative_price = Uint128::zero();
+        app.deps.storage.set(
+            &[KEY_RESERVE_SNAPSHOT, &index.to_be_bytes()].concat(),
+            &to_vec(&snapshot).unwrap(),
+        );
+    }
+
+    migrate(app.deps.as_mut(), app.env.clone(), MigrateMsg {}).unwrap();
+
+    // the earlier snapshots are left out so the twap starts from the latest one
+    assert_eq!(read_first_twap_snapshot(&app.deps.storage).unwrap(), height);
+
+    let res = query(app.deps.as_ref(), app.env.clone(), QueryMsg::SpotPrice {}).unwrap();
+    let spot: Uint128 = from_binary(&res).unwrap();
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env,
+        QueryMsg::TwapPrice { interval: 900 },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, spot);
+}
+
+#[test]
+fn test_prune_snapshots_keeps_twap() {
+    let mut app = setup();
+
+    // snapshots older than the one hour spot price twap interval can be pruned
+    app.env.block.time = app.env.block.time.plus_seconds(3_486);
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env.clone(),
+        QueryMsg::TwapPrice { interval: 3_600 },
+    )
+    .unwrap();
+    let twap_before: Uint128 = from_binary(&res).unwrap();
+
+    // anyone can prune, at most the limit each call
+    let msg = ExecuteMsg::PruneSnapshots { limit: Some(10u32) };
+    let info = mock_info("anyone", &[]);
+    execute(app.deps.as_mut(), app.env.clone(), info, msg).unwrap();
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env.clone(),
+        QueryMsg::OldestSnapshotIndex {},
+    )
+    .unwrap();
+    let oldest_index: u64 = from_binary(&res).unwrap();
+    assert_eq!(oldest_index, 11u64);
+    assert!(read_reserve_snapshot(&app.deps.storage, 10u64).is_err());
+
+    // the latest snapshot before the cutoff is kept
+    let msg = ExecuteMsg::PruneSnapshots { limit: None };
+    let info = mock_info("anyone", &[]);
+    execute(app.deps.as_mut(), app.env.clone(), info, msg.clone()).unwrap();
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env.clone(),
+        QueryMsg::OldestSnapshotIndex {},
+    )
+    .unwrap();
+    let oldest_index: u64 = from_binary(&res).unwrap();
+    assert_eq!(oldest_index, 23u64);
+
+    let info = mock_info("anyone", &[]);
+    execute(app.deps.as_mut(), app.env.clone(), info, msg).unwrap();
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env.clone(),
+        QueryMsg::OldestSnapshotIndex {},
+    )
+    .unwrap();
+    let oldest_index: u64 = from_binary(&res).unwrap();
+    assert_eq!(oldest_index, 23u64);
+
+    let res = query(
+        app.deps.as_ref(),
+        app.env,
+        QueryMsg::TwapPrice { interval: 3_600 },
+    )
+    .unwrap();
+    let twap_after: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap_after, twap_before);
+}
+
 #[test]
 fn test_input_twap_get_twap_price() {
     let mut app = setup();
@@ -386,3 +497,50 @@ fn test_output_twap_input_zero() {
     let twap: Uint128 = from_binary(&res).unwrap();
     assert_eq!(twap, Uint128::zero());
 }
+
+#[test]
+fn test_input_twap_prices_at_most_the_latest_snapshots() {
+    let mut app = setup();
+
+    // more snapshots within the interval than are priced
+    for i in 0..120 {
+        app.env.block.time = app.env.block.time.plus_seconds(1);
+        app.env.block.height += 1;
+
+        let direction = if i % 2 == 0 {
+            Direction::AddToAmm
+        } else {
+            Direction::RemoveFromAmm
+        };
+        let swap_msg = ExecuteMsg::SwapInput {
+            direction,
+            quote_asset_amount: to_decimals(10),
+            can_go_over_fluctuation: false,
+            base_asset_limit: Uint128::zero(),
+            position_id: 0u64,
+        };
+        let info = mock_info("addr0000", &[]);
+        execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap();
+    }
+
+    let height = read_reserve_snapshot_counter(&app.deps.storage).unwrap();
+    let params = TwapPriceCalcParams {
+        opt: TwapCalcOption::Input,
+        snapshot_index: height,
+        asset: Some(TwapInputAsset {
+            direction: Direction::AddToAmm,
+            amount: to_decimals(10),
+            quote: true,
+        }),
+    };
+    let twap = calc_twap(app.deps.as_ref(), app.env.clone(), params.clone(), 900).unwrap();
+
+    // the same as the twap over the time the latest snapshots cover
+    let first_snapshot =
+        read_reserve_snapshot(&app.deps.storage, height + 1 - MAX_INPUT_TWAP_SNAPSHOTS).unwrap();
+    let interval = app.env.block.time.seconds() - first_snapshot.timestamp.seconds();
+    assert!(interval < 900);
+
+    let expected = calc_twap(app.deps.as_ref(), app.env, params, interval).unwrap();
+    assert_eq!(twap, expected);
+}
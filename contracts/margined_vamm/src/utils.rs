@@ -1,12 +1,22 @@
-use cosmwasm_std::{Addr, Deps, Env, Response, StdError, StdResult, Storage, Uint128};
-use margined_perp::margined_vamm::Direction;
-use margined_utils::tools::price_swap::{
-    get_input_price_with_reserves, get_output_price_with_reserves,
+use cosmwasm_std::{
+    Addr, Decimal256, Deps, DepsMut, Env, Response, StdError, StdResult, Storage, Uint128,
+};
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::{Candle, CandleInterval, Direction, MarketStatus, PricingCurve};
+use margined_utils::{
+    contracts::helpers::PricefeedController,
+    tools::price_swap::{
+        get_curve_reserves, get_input_price_with_reserves, get_output_price_with_reserves,
+    },
 };
 
 use crate::state::{
-    read_config, read_reserve_snapshot, read_reserve_snapshot_counter, store_reserve_snapshot,
-    update_current_reserve_snapshot,
+    prune_candles, read_block_open_price, read_candle, read_config, read_first_twap_snapshot,
+    read_oldest_reserve_snapshot, read_repeg_spending, read_reserve_snapshot,
+    read_reserve_snapshot_counter, read_state, store_block_open_price, store_candle,
+    store_repeg_spending, store_reserve_snapshot, store_state, update_current_reserve_snapshot,
+    BlockOpenPrice, Config, RepegSpending, State, MAX_INPUT_TWAP_SNAPSHOTS,
+    MAX_VOLATILITY_SNAPSHOTS,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -38,9 +48,29 @@ pub fn require_margin_engine(sender: Addr, margin_engine: Addr) -> StdResult<()>
     Ok(())
 }
 
-pub fn require_open(open: bool) -> StdResult<()> {
-    // check that the vamm is open
-    if !open {
+/// Returns the (quote, base) reserves the swaps are priced against under the
+/// pricing curve of the vamm, the oracle is only queried by the anchored curve
+pub fn curve_reserves(deps: Deps, config: &Config, state: &State) -> StdResult<(Uint128, Uint128)> {
+    let oracle_price = match config.pricing_curve {
+        PricingCurve::ConstantProduct => Uint128::zero(),
+        PricingCurve::OracleAnchored { .. } => PricefeedController(config.pricefeed.clone())
+            .get_price(&deps.querier, config.base_asset.clone())?,
+    };
+
+    get_curve_reserves(
+        &config.pricing_curve,
+        state.quote_asset_reserve,
+        state.base_asset_reserve,
+        state.total_position_size,
+        oracle_price,
+        config.decimals,
+    )
+}
+
+pub fn require_open(status: MarketStatus) -> StdResult<()> {
+    // check that positions can be closed on the vamm, the margin engine
+    // makes sure that a reduce only market only reduces positions
+    if !status.is_open() {
         return Err(StdError::generic_err("amm is closed"));
     }
 
@@ -50,8 +80,7 @@ pub fn require_open(open: bool) -> StdResult<()> {
 pub fn check_is_over_block_fluctuation_limit(
     storage: &mut dyn Storage,
     env: Env,
-    decimals: Uint128,
-    fluctuation_limit_ratio: Uint128,
+    config: &Config,
     direction: Direction,
     quote_asset_amount: Uint128,
     base_asset_amount: Uint128,
@@ -59,17 +88,34 @@ pub fn check_is_over_block_fluctuation_limit(
     base_asset_reserve: Uint128,
     can_go_over_limit: bool,
 ) -> StdResult<Response> {
-    if fluctuation_limit_ratio.is_zero() {
+    if config.fluctuation_limit_ratio.is_zero() {
         return Ok(Response::new());
     }
 
-    let (upper_limit, lower_limit) =
-        price_boundaries_of_last_block(storage, decimals, fluctuation_limit_ratio, env)?;
-
+    let decimals = config.decimals;
     let current_price = quote_asset_reserve
         .checked_mul(decimals)?
         .checked_div(base_asset_reserve)?;
 
+    let (upper_limit, lower_limit) =
+        fluctuation_boundaries(storage, config, current_price, env.clone())?;
+
+    // the first swap of the block sets the price the anchored curve is bounded around
+    if config.pricing_curve != PricingCurve::ConstantProduct
+        && !matches!(
+            read_block_open_price(storage)?,
+            Some(open_price) if open_price.block_height == env.block.height
+        )
+    {
+        store_block_open_price(
+            storage,
+            &BlockOpenPrice {
+                block_height: env.block.height,
+                price: current_price,
+            },
+        )?;
+    }
+
     // ensure that the latest price isn't over the limit which would restrict any further
     // swaps from occurring in this block
     if current_price > upper_limit || current_price < lower_limit {
@@ -98,6 +144,41 @@ pub fn check_is_over_block_fluctuation_limit(
     Ok(Response::new())
 }
 
+/// Returns the price boundaries of the fluctuation limit. The anchored curve follows
+/// the oracle between blocks, so its boundaries are around the curve price before
+/// the first swap of the block rather than the price of the last block
+pub fn fluctuation_boundaries(
+    storage: &dyn Storage,
+    config: &Config,
+    curve_price: Uint128,
+    env: Env,
+) -> StdResult<(Uint128, Uint128)> {
+    match config.pricing_curve {
+        PricingCurve::ConstantProduct => price_boundaries_of_last_block(
+            storage,
+            config.decimals,
+            config.fluctuation_limit_ratio,
+            env,
+        ),
+        PricingCurve::OracleAnchored { .. } => {
+            // without a swap in the block yet the current price opens it
+            let open_price = match read_block_open_price(storage)? {
+                Some(open_price) if open_price.block_height == env.block.height => open_price.price,
+                _ => curve_price,
+            };
+
+            let upper_limit = open_price
+                .checked_mul(config.decimals + config.fluctuation_limit_ratio)?
+                .checked_div(config.decimals)?;
+            let lower_limit = open_price
+                .checked_mul(config.decimals - config.fluctuation_limit_ratio)?
+                .checked_div(config.decimals)?;
+
+            Ok((upper_limit, lower_limit))
+        }
+    }
+}
+
 pub fn price_boundaries_of_last_block(
     storage: &dyn Storage,
     decimals: Uint128,
@@ -132,18 +213,39 @@ pub fn add_reserve_snapshot(
     env: Env,
     quote_asset_reserve: Uint128,
     base_asset_reserve: Uint128,
+    curve_reserves: (Uint128, Uint128),
 ) -> StdResult<Response> {
     let height = read_reserve_snapshot_counter(storage)?;
     let mut snapshot = read_reserve_snapshot(storage, height)?;
 
-    snapshot.quote_asset_reserve = quote_asset_reserve;
-    snapshot.base_asset_reserve = base_asset_reserve;
+    snapshot.curve_quote_asset_reserve = curve_reserves.0;
+    snapshot.curve_base_asset_reserve = curve_reserves.1;
 
     // if there has already been an update in this block we overwrite the existing
     // else we create a new snapshot
     if snapshot.block_height == env.block.height {
+        snapshot.quote_asset_reserve = quote_asset_reserve;
+        snapshot.base_asset_reserve = base_asset_reserve;
+
         update_current_reserve_snapshot(storage, &snapshot)?;
     } else {
+        // the previous reserves were held from the previous snapshot until now
+        let decimals = read_config(storage)?.decimals;
+        let elapsed = env
+            .block
+            .time
+            .seconds()
+            .saturating_sub(snapshot.timestamp.seconds());
+        let previous_price = snapshot
+            .quote_asset_reserve
+            .checked_mul(decimals)?
+            .checked_div(snapshot.base_asset_reserve)?;
+
+        snapshot.cumulative_price = snapshot
+            .cumulative_price
+            .checked_add(previous_price.checked_mul(Uint128::from(elapsed))?)?;
+        snapshot.quote_asset_reserve = quote_asset_reserve;
+        snapshot.base_asset_reserve = base_asset_reserve;
         snapshot.timestamp = env.block.time;
         snapshot.block_height = env.block.height;
 
@@ -153,6 +255,332 @@ pub fn add_reserve_snapshot(
     Ok(Response::default())
 }
 
+/// Adds a swap that moved the spot price from `price_before` to `price` to the
+/// candles of every interval, a new candle opens at the price before the swap
+pub fn update_candles(
+    storage: &mut dyn Storage,
+    now: u64,
+    price_before: Uint128,
+    price: Uint128,
+    base_asset_amount: Uint128,
+    quote_asset_amount: Uint128,
+) -> StdResult<()> {
+    for interval in CandleInterval::ALL {
+        let open_time = now - now % interval.seconds();
+        let mut candle = match read_candle(storage, interval, open_time)? {
+            Some(candle) => candle,
+            None => {
+                // expired candles are pruned as new ones open
+                prune_candles(storage, interval, open_time)?;
+
+                Candle {
+                    open_time,
+                    open: price_before,
+                    high: price_before,
+                    low: price_before,
+                    close: price_before,
+                    base_volume: Uint128::zero(),
+                    quote_volume: Uint128::zero(),
+                }
+            }
+        };
+
+        candle.high = candle.high.max(price);
+        candle.low = candle.low.min(price);
+        candle.close = price;
+        candle.base_volume = candle.base_volume.checked_add(base_asset_amount)?;
+        candle.quote_volume = candle.quote_volume.checked_add(quote_asset_amount)?;
+
+        store_candle(storage, interval, &candle)?;
+    }
+
+    Ok(())
+}
+
+/// Moves the reserves to the new price keeping k constant, returns the new
+/// state and the repeg cost of the net open position
+pub fn repeg_reserves(
+    deps: DepsMut,
+    env: Env,
+    config: &Config,
+    new_price: Uint128,
+) -> StdResult<(State, Integer)> {
+    let mut state = read_state(deps.storage)?;
+    let old_curve_reserves = curve_reserves(deps.as_ref(), config, &state)?;
+
+    // base_asset * quote_asset = k
+    // new_base_asset * new_quote_asset = k
+    // new_quote_asset / new_base_asset = new_price
+    // => new_quote_asset = sqrt(k * new_price)
+    // => new_base_asset = k / new_quote_asset
+
+    let invariant_k = state
+        .quote_asset_reserve
+        .checked_mul(state.base_asset_reserve)?;
+
+    let new_quote_asset = Uint128::try_from(
+        Decimal256::from_atomics(invariant_k, 0)
+            .unwrap()
+            .checked_mul(Decimal256::from_ratio(new_price, config.decimals))?
+            .sqrt()
+            .to_uint_floor(),
+    )?;
+
+    let new_base_asset = invariant_k / new_quote_asset;
+
+    state.quote_asset_reserve = new_quote_asset;
+    state.base_asset_reserve = new_base_asset;
+
+    // the net position is valued on the curve the swaps are priced against
+    let new_curve_reserves = curve_reserves(deps.as_ref(), config, &state)?;
+    let repeg_cost = calc_repeg_cost(
+        state.total_position_size,
+        old_curve_reserves,
+        new_curve_reserves,
+    )?;
+
+    store_state(deps.storage, &state)?;
+
+    add_reserve_snapshot(
+        deps.storage,
+        env,
+        state.quote_asset_reserve,
+        state.base_asset_reserve,
+        new_curve_reserves,
+    )?;
+
+    Ok((state, repeg_cost))
+}
+
+/// Returns the change in value of the net open position when the reserves move from
+/// the old to the new (quote, base) pair. It is positive when the traders gain, i.e.
+/// the cost the insurance fund has to cover, and negative when they lose
+pub fn calc_repeg_cost(
+    total_position_size: Integer,
+    old_reserves: (Uint128, Uint128),
+    new_reserves: (Uint128, Uint128),
+) -> StdResult<Integer> {
+    if total_position_size.is_zero() {
+        return Ok(Integer::zero());
+    }
+
+    // a net long is closed by selling the base to the amm, a net short by buying it back
+    let direction = if total_position_size.is_positive() {
+        Direction::AddToAmm
+    } else {
+        Direction::RemoveFromAmm
+    };
+
+    let old_value = get_output_price_with_reserves(
+        &direction,
+        total_position_size.value,
+        old_reserves.0,
+        old_reserves.1,
+    )?;
+    let new_value = get_output_price_with_reserves(
+        &direction,
+        total_position_size.value,
+        new_reserves.0,
+        new_reserves.1,
+    )?;
+
+    let value_change =
+        Integer::new_positive(new_value).checked_sub(Integer::new_positive(old_value))?;
+
+    // shorts owe the value of their position so they gain when it falls
+    if total_position_size.is_positive() {
+        Ok(value_change)
+    } else {
+        Ok(value_change.invert_sign())
+    }
+}
+
+/// Adds the repeg cost to the spending of the current funding period, a new period
+/// starts with the first repeg after the previous one has ended. Gains of the
+/// insurance fund are not counted against the budget
+pub fn update_repeg_spending(
+    storage: &mut dyn Storage,
+    env: &Env,
+    funding_period: u64,
+    repeg_cost: Integer,
+) -> StdResult<RepegSpending> {
+    let spending = add_period_spending(
+        read_repeg_spending(storage)?,
+        env,
+        funding_period,
+        repeg_cost,
+    )?;
+
+    store_repeg_spending(storage, &spending)?;
+
+    Ok(spending)
+}
+
+/// Adds a cost to the spending, resetting it if its funding period has ended
+pub fn add_period_spending(
+    mut spending: RepegSpending,
+    env: &Env,
+    funding_period: u64,
+    cost: Integer,
+) -> StdResult<RepegSpending> {
+    let now = env.block.time.seconds();
+    if now >= spending.period_start + funding_period {
+        spending = RepegSpending {
+            period_start: now,
+            spent: Uint128::zero(),
+        };
+    }
+
+    if cost.is_positive() {
+        spending.spent = spending.spent.checked_add(cost.value)?;
+    }
+
+    Ok(spending)
+}
+
+/// Returns the smallest liquidity multiplier that keeps the base asset reserve
+/// above a net long position, zero if there is none
+pub fn liquidity_multiplier_lower_bound(state: &State, decimals: Uint128) -> Uint128 {
+    if state.total_position_size.is_positive() {
+        state
+            .total_position_size
+            .value
+            .multiply_ratio(decimals, state.base_asset_reserve)
+    } else {
+        Uint128::zero()
+    }
+}
+
+/// Returns the spread ratio of a trade of the quote asset amount. The spread
+/// widens by how much further the trade moves the price away from the oracle
+/// price and narrows by how much closer it moves it, then a share of the
+/// realised volatility is added and it is kept within the min and max ratios.
+/// Without a max spread ratio the configured spread ratio is used
+pub fn calc_spread_ratio(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    quote_asset_amount: Uint128,
+    direction: Option<Direction>,
+) -> StdResult<Uint128> {
+    if config.max_spread_ratio.is_zero() {
+        return Ok(config.spread_ratio);
+    }
+
+    let mut spread_ratio = Integer::new_positive(config.spread_ratio);
+
+    if let Some(direction) = direction {
+        let state = read_state(deps.storage)?;
+
+        let oracle_price = PricefeedController(config.pricefeed.clone())
+            .get_price(&deps.querier, config.base_asset.clone())?;
+        if oracle_price.is_zero() {
+            return Err(StdError::generic_err("underlying price is 0"));
+        }
+
+        let (quote_asset_reserve, base_asset_reserve) = curve_reserves(deps, config, &state)?;
+
+        let invariant_k = quote_asset_reserve.checked_mul(base_asset_reserve)?;
+        let quote_asset_after = match direction {
+            Direction::AddToAmm => quote_asset_reserve.checked_add(quote_asset_amount)?,
+            Direction::RemoveFromAmm => quote_asset_reserve.checked_sub(quote_asset_amount)?,
+        };
+        let base_asset_after = invariant_k.checked_div(quote_asset_after)?;
+
+        let price_before = quote_asset_reserve
+            .checked_mul(config.decimals)?
+            .checked_div(base_asset_reserve)?;
+        let price_after = quote_asset_after
+            .checked_mul(config.decimals)?
+            .checked_div(base_asset_after)?;
+
+        let divergence_before = price_before
+            .abs_diff(oracle_price)
+            .checked_mul(config.decimals)?
+            .checked_div(oracle_price)?;
+        let divergence_after = price_after
+            .abs_diff(oracle_price)
+            .checked_mul(config.decimals)?
+            .checked_div(oracle_price)?;
+
+        spread_ratio = spread_ratio
+            .checked_add(Integer::new_positive(divergence_after))?
+            .checked_sub(Integer::new_positive(divergence_before))?;
+    }
+
+    if !config.volatility_spread_ratio.is_zero() {
+        let volatility = calc_realised_volatility(
+            deps.storage,
+            env,
+            config.spot_price_twap_interval,
+            config.decimals,
+        )?;
+
+        spread_ratio = spread_ratio.checked_add(Integer::new_positive(
+            volatility
+                .checked_mul(config.volatility_spread_ratio)?
+                .checked_div(config.decimals)?,
+        ))?;
+    }
+
+    let spread_ratio = if spread_ratio.is_negative() {
+        Uint128::zero()
+    } else {
+        spread_ratio.value
+    };
+
+    Ok(spread_ratio
+        .max(config.min_spread_ratio)
+        .min(config.max_spread_ratio))
+}
+
+/// Returns the sum of the absolute price changes, as a ratio of the previous
+/// price, between the reserve snapshots taken within the interval. At most
+/// MAX_VOLATILITY_SNAPSHOTS are looked back at
+pub fn calc_realised_volatility(
+    storage: &dyn Storage,
+    env: &Env,
+    interval: u64,
+    decimals: Uint128,
+) -> StdResult<Uint128> {
+    let oldest_index = read_oldest_reserve_snapshot(storage)?;
+    let base_timestamp = env.block.time.seconds().saturating_sub(interval);
+
+    let mut index = read_reserve_snapshot_counter(storage)?;
+    let mut snapshot = read_reserve_snapshot(storage, index)?;
+    let mut volatility = Uint128::zero();
+    let mut count = 0u64;
+
+    while index > oldest_index
+        && count < MAX_VOLATILITY_SNAPSHOTS
+        && snapshot.timestamp.seconds() >= base_timestamp
+    {
+        let previous = read_reserve_snapshot(storage, index - 1)?;
+
+        let price = snapshot
+            .quote_asset_reserve
+            .checked_mul(decimals)?
+            .checked_div(snapshot.base_asset_reserve)?;
+        let previous_price = previous
+            .quote_asset_reserve
+            .checked_mul(decimals)?
+            .checked_div(previous.base_asset_reserve)?;
+
+        volatility = volatility.checked_add(
+            price
+                .abs_diff(previous_price)
+                .checked_mul(decimals)?
+                .checked_div(previous_price)?,
+        )?;
+
+        snapshot = previous;
+        index -= 1;
+        count += 1;
+    }
+
+    Ok(volatility)
+}
+
 pub fn get_price_with_specific_snapshot(
     deps: Deps,
     params: TwapPriceCalcParams,
@@ -178,19 +606,22 @@ pub fn get_price_with_specific_snapshot(
                 return Ok(Uint128::zero());
             }
 
+            // priced on the curve the swaps at the snapshot were priced against
+            let (quote_asset_reserve, base_asset_reserve) = snapshot.curve_reserves();
+
             if asset.quote {
                 return get_input_price_with_reserves(
                     &asset.direction,
                     asset.amount,
-                    snapshot.quote_asset_reserve,
-                    snapshot.base_asset_reserve,
+                    quote_asset_reserve,
+                    base_asset_reserve,
                 );
             } else {
                 return get_output_price_with_reserves(
                     &asset.direction,
                     asset.amount,
-                    snapshot.quote_asset_reserve,
-                    snapshot.base_asset_reserve,
+                    quote_asset_reserve,
+                    base_asset_reserve,
                 );
             }
         }
@@ -198,11 +629,14 @@ pub fn get_price_with_specific_snapshot(
     Ok(Uint128::zero())
 }
 
-/// Calculates the TWAP of the AMM reserves
+/// Calculates the TWAP of the AMM reserves, the snapshot at the start of the
+/// interval is found by binary search. Reserve prices are taken from the
+/// cumulative prices of the snapshots, input prices are weighted over the
+/// snapshots within the interval.
 pub fn calc_twap(
     deps: Deps,
     env: Env,
-    mut params: TwapPriceCalcParams,
+    params: TwapPriceCalcParams,
     interval: u64,
 ) -> StdResult<Uint128> {
     let current_price = get_price_with_specific_snapshot(deps, params.clone())?;
@@ -216,45 +650,127 @@ pub fn calc_twap(
 
     let base_timestamp = block_time.checked_sub(interval)?;
     let reserve_snapshot_length = read_reserve_snapshot_counter(deps.storage)?;
-    let mut current_snapshot = read_reserve_snapshot(deps.storage, params.snapshot_index)?;
+    let current_snapshot = read_reserve_snapshot(deps.storage, params.snapshot_index)?;
 
-    let mut previous_timestamp = Uint128::from(current_snapshot.timestamp.seconds());
+    let current_timestamp = Uint128::from(current_snapshot.timestamp.seconds());
 
-    if reserve_snapshot_length == 1 || previous_timestamp <= base_timestamp {
+    if reserve_snapshot_length == 1 || current_timestamp <= base_timestamp {
         return Ok(current_price);
     }
 
-    let mut period = block_time.checked_sub(previous_timestamp)?;
-    let mut weighted_price = current_price.checked_mul(period)?;
-
-    loop {
-        params.snapshot_index -= 1;
-
-        // if snapshot history is too short
-        if params.snapshot_index == 0 {
-            return Ok(weighted_price.checked_div(period)?);
+    // if snapshot history is too short the twap is over the available history
+    let oldest_index = read_first_twap_snapshot(deps.storage)?;
+    let (start_index, start_timestamp, period) = match find_snapshot_at(
+        deps.storage,
+        oldest_index,
+        params.snapshot_index,
+        base_timestamp,
+    )? {
+        Some(index) => (index, base_timestamp, interval),
+        None => {
+            let oldest_snapshot = read_reserve_snapshot(deps.storage, oldest_index)?;
+            let oldest_timestamp = Uint128::from(oldest_snapshot.timestamp.seconds());
+
+            (
+                oldest_index,
+                oldest_timestamp,
+                block_time.checked_sub(oldest_timestamp)?,
+            )
+        }
+    };
+
+    let (weighted_price, period) = match params.opt {
+        TwapCalcOption::Reserve => {
+            let cumulative_now = current_snapshot.cumulative_price.checked_add(
+                current_price.checked_mul(block_time.checked_sub(current_timestamp)?)?,
+            )?;
+
+            let start_snapshot = read_reserve_snapshot(deps.storage, start_index)?;
+            let start_price = get_price_with_specific_snapshot(
+                deps,
+                TwapPriceCalcParams {
+                    snapshot_index: start_index,
+                    ..params.clone()
+                },
+            )?;
+            let cumulative_start = start_snapshot.cumulative_price.checked_add(
+                start_price.checked_mul(
+                    start_timestamp
+                        .checked_sub(Uint128::from(start_snapshot.timestamp.seconds()))?,
+                )?,
+            )?;
+
+            (cumulative_now.checked_sub(cumulative_start)?, period)
         }
+        TwapCalcOption::Input => {
+            // the price of the input depends on the reserves so each snapshot is priced,
+            // an interval with more snapshots is averaged over the time the latest cover
+            let capped_index = params
+                .snapshot_index
+                .saturating_sub(MAX_INPUT_TWAP_SNAPSHOTS - 1);
+            let first_index = start_index.max(capped_index);
+            let (start_timestamp, period) = if first_index > start_index {
+                let first_snapshot = read_reserve_snapshot(deps.storage, first_index)?;
+                let first_timestamp = Uint128::from(first_snapshot.timestamp.seconds());
+
+                (first_timestamp, block_time.checked_sub(first_timestamp)?)
+            } else {
+                (start_timestamp, period)
+            };
+
+            let mut weighted_price = Uint128::zero();
+            let mut next_timestamp = block_time;
+
+            for snapshot_index in (first_index..=params.snapshot_index).rev() {
+                let snapshot = read_reserve_snapshot(deps.storage, snapshot_index)?;
+                let price = get_price_with_specific_snapshot(
+                    deps,
+                    TwapPriceCalcParams {
+                        snapshot_index,
+                        ..params.clone()
+                    },
+                )?;
+                let timestamp = Uint128::from(snapshot.timestamp.seconds()).max(start_timestamp);
+
+                weighted_price = weighted_price
+                    .checked_add(price.checked_mul(next_timestamp.checked_sub(timestamp)?)?)?;
+                next_timestamp = timestamp;
+            }
 
-        current_snapshot = read_reserve_snapshot(deps.storage, params.snapshot_index)?;
-        let current_price = get_price_with_specific_snapshot(deps, params.clone())?;
-        let current_timestamp = Uint128::from(current_snapshot.timestamp.seconds());
+            (weighted_price, period)
+        }
+    };
 
-        // time to break
-        if current_timestamp <= base_timestamp {
-            let delta_timestamp = previous_timestamp.checked_sub(base_timestamp)?;
+    if period.is_zero() {
+        return Ok(current_price);
+    }
 
-            weighted_price =
-                weighted_price.checked_add(current_price.checked_mul(delta_timestamp)?)?;
+    Ok(weighted_price.checked_div(period)?)
+}
 
-            break;
+/// Binary searches the latest snapshot between the given indexes with a
+/// timestamp not after the given time
+pub fn find_snapshot_at(
+    storage: &dyn Storage,
+    first_index: u64,
+    last_index: u64,
+    timestamp: Uint128,
+) -> StdResult<Option<u64>> {
+    let mut found = None;
+    let mut low = first_index;
+    let mut high = last_index;
+
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        let snapshot = read_reserve_snapshot(storage, mid)?;
+
+        if Uint128::from(snapshot.timestamp.seconds()) <= timestamp {
+            low = mid + 1;
+            found = Some(mid);
+        } else {
+            high = mid - 1;
         }
-
-        let delta_timestamp = previous_timestamp.checked_sub(current_timestamp)?;
-        weighted_price = weighted_price.checked_add(current_price.checked_mul(delta_timestamp)?)?;
-
-        period = period.checked_add(delta_timestamp)?;
-        previous_timestamp = current_timestamp;
     }
 
-    Ok(weighted_price.checked_div(interval)?)
+    Ok(found)
 }
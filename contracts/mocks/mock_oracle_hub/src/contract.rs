@@ -0,0 +1,172 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_slice, to_vec};
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+
+pub static KEY_CONFIG: &[u8] = b"config";
+pub static PREFIX_PRICES: &[u8] = b"prices";
+
+#[cw_serde]
+pub struct InstantiateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    SetPrice {
+        key: String,
+        price: Uint128,
+        last_updated: u64,
+    },
+    RemovePrice {
+        key: String,
+    },
+}
+
+#[cw_serde]
+pub enum QueryMsg {
+    Config {},
+    GetPrice { key: String },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub owner: Addr,
+}
+
+#[cw_serde]
+pub struct PriceResponse {
+    pub price: Uint128,
+    pub last_updated: u64,
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let config = Config { owner: info.sender };
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetPrice {
+            key,
+            price,
+            last_updated,
+        } => set_price(deps, info, key, price, last_updated),
+        ExecuteMsg::RemovePrice { key } => remove_price(deps, info, key),
+    }
+}
+
+/// sets the price the hub reports for a key, as an oracle would
+#[cfg(not(tarpaulin_include))]
+pub fn set_price(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    price: Uint128,
+    last_updated: u64,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    store_price(
+        deps.storage,
+        &key,
+        &PriceResponse {
+            price,
+            last_updated,
+        },
+    )?;
+
+    Ok(Response::default())
+}
+
+/// removes the price of a key so queries for it fail, as an unavailable oracle would
+#[cfg(not(tarpaulin_include))]
+pub fn remove_price(deps: DepsMut, info: MessageInfo, key: String) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    deps.storage
+        .remove(&[PREFIX_PRICES, key.as_bytes()].concat());
+
+    Ok(Response::default())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::GetPrice { key } => to_binary(&query_get_price(deps, key)?),
+    }
+}
+
+/// Queries contract Config
+#[cfg(not(tarpaulin_include))]
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    read_config(deps.storage)
+}
+
+/// Queries the price for the key and when it was last updated
+#[cfg(not(tarpaulin_include))]
+pub fn query_get_price(deps: Deps, key: String) -> StdResult<PriceResponse> {
+    read_price(deps.storage, &key)
+}
+
+pub type Config = ConfigResponse;
+
+#[cfg(not(tarpaulin_include))]
+pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
+    storage.set(KEY_CONFIG, &to_vec(config)?);
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    match storage.get(KEY_CONFIG) {
+        Some(data) => from_slice(&data),
+        None => Err(StdError::generic_err("Config not found")),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn store_price(storage: &mut dyn Storage, key: &str, price: &PriceResponse) -> StdResult<()> {
+    storage.set(&[PREFIX_PRICES, key.as_bytes()].concat(), &to_vec(price)?);
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn read_price(storage: &dyn Storage, key: &str) -> StdResult<PriceResponse> {
+    match storage.get(&[PREFIX_PRICES, key.as_bytes()].concat()) {
+        Some(data) => from_slice(&data),
+        None => Err(StdError::generic_err("Price not found")),
+    }
+}
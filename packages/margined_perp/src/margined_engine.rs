@@ -33,6 +33,42 @@ pub enum PositionFilter {
     None,           // no filter
 }
 
+#[cw_serde]
+pub enum FeeRecipient {
+    InsuranceFund,
+    FeePool,
+    Address(String),
+}
+
+#[cw_serde]
+pub struct FeeShare {
+    pub recipient: FeeRecipient,
+    pub weight: Uint128, // share of the fee, all weights of a fee type sum to the decimals
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeDistribution {
+    pub spread_fee: Vec<FeeShare>,
+    pub toll_fee: Vec<FeeShare>,
+}
+
+impl FeeDistribution {
+    /// Sends the whole spread fee to the insurance fund and the whole toll fee to the fee pool
+    pub fn new(decimals: Uint128) -> Self {
+        FeeDistribution {
+            spread_fee: vec![FeeShare {
+                recipient: FeeRecipient::InsuranceFund,
+                weight: decimals,
+            }],
+            toll_fee: vec![FeeShare {
+                recipient: FeeRecipient::FeePool,
+                weight: decimals,
+            }],
+        }
+    }
+}
+
 #[cw_serde]
 pub struct InstantiateMsg {
     pub pauser: String,
@@ -109,6 +145,13 @@ pub enum ExecuteMsg {
     PayFunding {
         vamm: String,
     },
+    RepegPrice {
+        vamm: String,
+        new_price: Option<Uint128>,
+    },
+    AutoRepeg {
+        vamm: String,
+    },
     DepositMargin {
         vamm: String,
         position_id: u64,
@@ -122,6 +165,21 @@ pub enum ExecuteMsg {
     SetPause {
         pause: bool,
     },
+    UpdateReferralConfig {
+        reward_ratio: Option<Uint128>,
+        discount_ratio: Option<Uint128>,
+    },
+    RegisterReferralCode {
+        code: String,
+    },
+    BindReferralCode {
+        code: String,
+    },
+    ClaimReferralRewards {},
+    UpdateFeeDistribution {
+        spread_fee: Option<Vec<FeeShare>>,
+        toll_fee: Option<Vec<FeeShare>>,
+    },
 }
 
 #[cw_serde]
@@ -200,6 +258,23 @@ pub enum QueryMsg {
     IsLiquidated { vamm: String, position_id: u64 },
     #[returns(LastPositionIdResponse)]
     LastPositionId {},
+    #[returns(ReferralConfigResponse)]
+    ReferralConfig {},
+    #[returns(ReferrerStatsResponse)]
+    ReferrerStats { referrer: String },
+    #[returns(ReferralResponse)]
+    Referral { trader: String },
+    #[returns(Uint128)]
+    ReferralRewards { address: String },
+    #[returns(SimulateOpenPositionResponse)]
+    SimulateOpenPosition {
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+    },
+    #[returns(SimulateClosePositionResponse)]
+    SimulateClosePosition { vamm: String, position_id: u64 },
 }
 
 #[cw_serde]
@@ -215,6 +290,9 @@ pub struct ConfigResponse {
     pub tp_sl_spread: Uint128,
     pub liquidation_fee: Uint128,
     pub operator: Option<Addr>,
+    // empty for configs stored before the fee distribution, filled in by migrate
+    #[serde(default)]
+    pub fee_distribution: FeeDistribution,
 }
 
 #[cw_serde]
@@ -234,6 +312,30 @@ pub struct LastPositionIdResponse {
     pub last_position_id: u64,
 }
 
+#[cw_serde]
+#[derive(Default)]
+pub struct ReferralConfigResponse {
+    pub reward_ratio: Uint128, // share of the toll fee credited to the referrer
+    pub discount_ratio: Uint128, // share of the toll fee rebated to the trader
+}
+
+#[cw_serde]
+pub struct ReferrerStatsResponse {
+    pub referrer: Addr,
+    pub code: Option<String>,
+    pub referred_traders: u64,
+    pub total_rewards: Uint128,
+    pub claimable_rewards: Uint128,
+}
+
+#[cw_serde]
+pub struct ReferralResponse {
+    pub trader: Addr,
+    pub referrer: Option<Addr>,
+    pub code: Option<String>,
+    pub total_discount: Uint128,
+}
+
 #[cw_serde]
 pub struct TickResponse {
     pub entry_price: Uint128,
@@ -311,6 +413,33 @@ pub struct PositionUnrealizedPnlResponse {
     pub unrealized_pnl: Integer,
 }
 
+#[cw_serde]
+pub struct SimulateOpenPositionResponse {
+    pub spread_fee: Uint128,
+    pub toll_fee: Uint128,
+    pub margin: Uint128, // margin left once the fees are paid
+    pub notional: Uint128,
+    pub size: Integer,
+    pub entry_price: Uint128,
+    pub price_impact: Uint128, // relative difference between entry and spot price
+    pub liquidation_price: Uint128,
+    pub is_over_fluctuation_limit: bool,
+    pub is_over_open_interest_cap: bool,
+    pub is_over_base_asset_holding_cap: bool,
+}
+
+#[cw_serde]
+pub struct SimulateClosePositionResponse {
+    pub quote_asset_amount: Uint128,
+    pub exit_price: Uint128,
+    pub realized_pnl: Integer,
+    pub funding_payment: Integer, // positive if paid by the trader
+    pub spread_fee: Uint128,
+    pub toll_fee: Uint128,
+    pub bad_debt: Uint128,
+    pub withdraw_amount: Uint128,
+}
+
 #[cw_serde]
 pub struct RemainMarginResponse {
     pub funding_payment: Integer,
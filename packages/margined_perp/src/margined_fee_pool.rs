@@ -2,6 +2,10 @@ use cosmwasm_schema::{cw_serde, QueryResponses};
 
 use cosmwasm_std::{Addr, Uint128};
 use margined_common::asset::AssetInfo;
+
+/// Default share of new fees allocated to the stakers, the rest is kept by the protocol
+pub const DEFAULT_STAKER_SHARE_BPS: u64 = 10_000;
+
 #[cw_serde]
 pub struct InstantiateMsg {}
 
@@ -21,6 +25,26 @@ pub enum ExecuteMsg {
         amount: Uint128,
         recipient: String,
     },
+    UpdateConfig {
+        staking_token: Option<String>,
+        staker_share_bps: Option<u64>,
+    },
+    Stake {
+        amount: Uint128,
+    },
+    Unstake {
+        amount: Uint128,
+    },
+    Claim {},
+    UpdateDistribution {
+        recipients: Option<Vec<DistributionShare>>,
+        interval: Option<u64>,
+    },
+    SetMinReserve {
+        token: String,
+        amount: Uint128,
+    },
+    Distribute {},
 }
 
 #[cw_serde]
@@ -36,13 +60,35 @@ pub enum QueryMsg {
     GetTokenLength {},
     #[returns(AllTokenResponse)]
     GetTokenList { limit: Option<u32> },
+    #[returns(StakingStateResponse)]
+    GetStakingState {},
+    #[returns(PendingRewardsResponse)]
+    GetPendingRewards { staker: String },
+    #[returns(DistributionResponse)]
+    GetDistribution {},
+    #[returns(LastDistributionResponse)]
+    GetLastDistribution {},
 }
 
 #[cw_serde]
 pub struct MigrateMsg {}
 
 #[cw_serde]
-pub struct ConfigResponse {}
+pub struct DistributionShare {
+    pub recipient: String,
+    pub weight: Uint128,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub staking_token: Option<Addr>, // cw20 governance token staked to earn the fees
+    #[serde(default = "default_staker_share_bps")]
+    pub staker_share_bps: u64, // share of new fees allocated to the stakers in bps
+}
+
+fn default_staker_share_bps() -> u64 {
+    DEFAULT_STAKER_SHARE_BPS
+}
 
 #[cw_serde]
 pub struct OwnerResponse {
@@ -63,3 +109,35 @@ pub struct AllTokenResponse {
 pub struct TokenLengthResponse {
     pub length: usize,
 }
+
+#[cw_serde]
+pub struct StakingStateResponse {
+    pub total_staked: Uint128,
+}
+
+#[cw_serde]
+pub struct RewardResponse {
+    pub token: AssetInfo,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    pub staker: Addr,
+    pub staked: Uint128,
+    pub rewards: Vec<RewardResponse>,
+}
+
+#[cw_serde]
+pub struct DistributionResponse {
+    pub recipients: Vec<(Addr, Uint128)>,
+    pub interval: u64, // minimum seconds between distributions
+    pub min_reserves: Vec<(AssetInfo, Uint128)>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct LastDistributionResponse {
+    pub time: u64,
+    pub distributed: Vec<(AssetInfo, Uint128)>,
+}
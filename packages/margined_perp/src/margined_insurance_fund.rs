@@ -1,7 +1,15 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use margined_common::asset::AssetInfo;
 
+use crate::margined_vamm::MarketStatus;
+
 use cosmwasm_std::{Addr, Uint128};
+
+/// Default time stakers wait before their stake is released
+pub const DEFAULT_UNSTAKE_COOLDOWN: u64 = 7 * 86_400;
+/// Default time a proposed withdrawal is timelocked for
+pub const DEFAULT_WITHDRAWAL_DELAY: u64 = 2 * 86_400;
+
 #[cw_serde]
 pub struct InstantiateMsg {
     pub engine: String,
@@ -9,12 +17,48 @@ pub struct InstantiateMsg {
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    UpdateOwner { owner: String },
-    AddVamm { vamm: String },
-    RemoveVamm { vamm: String },
-    Withdraw { token: AssetInfo, amount: Uint128 },
+    UpdateOwner {
+        owner: String,
+    },
+    AddVamm {
+        vamm: String,
+    },
+    RemoveVamm {
+        vamm: String,
+    },
+    Withdraw {
+        token: AssetInfo,
+        amount: Uint128,
+        vamm: Option<String>,
+    },
     ShutdownVamms {},
-    WithdrawFund { token: AssetInfo, amount: Uint128 },
+    ProposeWithdrawal {
+        token: AssetInfo,
+        amount: Uint128,
+        recipient: String,
+    },
+    ExecuteWithdrawal {
+        id: u64,
+    },
+    CancelWithdrawal {
+        id: u64,
+    },
+    UpdateConfig {
+        unstake_cooldown: Option<u64>,
+        shared_pool_fallback: Option<bool>,
+        withdrawal_delay: Option<u64>,
+    },
+    Stake {
+        amount: Uint128,
+    },
+    Unstake {
+        shares: Uint128,
+    },
+    ClaimUnstaked {},
+    CreditMarketFund {
+        vamm: String,
+        amount: Uint128,
+    },
 }
 
 #[cw_serde]
@@ -32,6 +76,19 @@ pub enum QueryMsg {
     GetAllVammStatus { limit: Option<u32> },
     #[returns(VammStatusResponse)]
     GetVammStatus { vamm: String },
+    #[returns(StakingStateResponse)]
+    GetStakingState {},
+    #[returns(StakerResponse)]
+    GetStaker { staker: String },
+    #[returns(MarketFundResponse)]
+    GetMarketFund { vamm: String },
+    #[returns(AllMarketFundsResponse)]
+    GetAllMarketFunds { limit: Option<u32> },
+    #[returns(PendingWithdrawalsResponse)]
+    GetPendingWithdrawals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -40,6 +97,25 @@ pub struct MigrateMsg {}
 #[cw_serde]
 pub struct ConfigResponse {
     pub engine: Addr,
+    #[serde(default = "default_unstake_cooldown")]
+    pub unstake_cooldown: u64,
+    #[serde(default = "default_shared_pool_fallback")]
+    pub shared_pool_fallback: bool, // cover a market's shortfall from the shared pool
+    #[serde(default = "default_withdrawal_delay")]
+    pub withdrawal_delay: u64, // seconds before a proposed withdrawal can be executed
+}
+
+fn default_unstake_cooldown() -> u64 {
+    DEFAULT_UNSTAKE_COOLDOWN
+}
+
+// configs stored before the sub-funds drew everything from the shared pool
+fn default_shared_pool_fallback() -> bool {
+    true
+}
+
+fn default_withdrawal_delay() -> u64 {
+    DEFAULT_WITHDRAWAL_DELAY
 }
 
 #[cw_serde]
@@ -54,7 +130,7 @@ pub struct VammResponse {
 
 #[cw_serde]
 pub struct VammStatusResponse {
-    pub vamm_status: bool,
+    pub vamm_status: MarketStatus,
 }
 
 #[cw_serde]
@@ -64,5 +140,47 @@ pub struct AllVammResponse {
 
 #[cw_serde]
 pub struct AllVammStatusResponse {
-    pub vamm_list_status: Vec<(Addr, bool)>,
+    pub vamm_list_status: Vec<(Addr, MarketStatus)>,
+}
+
+#[cw_serde]
+pub struct StakingStateResponse {
+    pub total_shares: Uint128,
+    pub staked_value: Uint128,   // collateral attributable to the stakers
+    pub protocol_value: Uint128, // collateral attributable to the protocol
+}
+
+#[cw_serde]
+pub struct StakerResponse {
+    pub staker: Addr,
+    pub shares: Uint128,
+    pub value: Uint128,
+    pub unstaking_shares: Uint128,
+    pub release_time: u64,
+}
+
+#[cw_serde]
+pub struct MarketFundResponse {
+    pub vamm: Addr,
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct AllMarketFundsResponse {
+    pub market_funds: Vec<MarketFundResponse>,
+    pub shared_pool: Uint128, // collateral not allocated to any market
+}
+
+#[cw_serde]
+pub struct WithdrawalResponse {
+    pub id: u64,
+    pub token: AssetInfo,
+    pub amount: Uint128,
+    pub recipient: Addr,
+    pub unlock_time: u64,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalsResponse {
+    pub withdrawals: Vec<WithdrawalResponse>,
 }
@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Timestamp, Uint128};
+use cosmwasm_std::{Addr, Binary, Timestamp, Uint128};
 
 #[cw_serde]
 #[derive(Default)]
@@ -9,6 +9,24 @@ pub struct PriceData {
     pub timestamp: Timestamp,
 }
 
+#[cw_serde]
+pub enum PriceSource {
+    Rounds,    // rounds appended by the executor or publishers
+    OracleHub, // the oracle hub, falling back to the rounds
+}
+
+/// Price signed off-chain by a publisher, the signature is an ed25519
+/// signature over the key bytes followed by the big endian price (u128)
+/// and timestamp (u64)
+#[cw_serde]
+pub struct SignedPrice {
+    pub key: String,
+    pub price: Uint128,
+    pub timestamp: u64,
+    pub pubkey: Binary,
+    pub signature: Binary,
+}
+
 #[cw_serde]
 pub enum Direction {
     AddToAmm,
@@ -41,6 +59,44 @@ pub enum ExecuteMsg {
     UpdateExecutor {
         executor: String,
     },
+    UpdateConfig {
+        submission_window: Option<u64>,
+        oracle_hub_contract: Option<String>,
+    },
+    UpdatePublishers {
+        key: String,
+        publishers: Vec<String>,
+        quorum: u64,
+    },
+    SubmitPrice {
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    },
+    UpdatePriceGuard {
+        key: String,
+        heartbeat: Option<u64>,
+        max_deviation_bps: Option<u64>,
+    },
+    ForceAppendPrice {
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    },
+    UpdatePriceSource {
+        key: String,
+        source: PriceSource,
+    },
+    SyncPrice {
+        key: String,
+    },
+    UpdatePublisherKeys {
+        key: String,
+        pubkeys: Vec<Binary>,
+    },
+    SubmitSignedPrices {
+        payloads: Vec<SignedPrice>,
+    },
 }
 
 #[cw_serde]
@@ -62,10 +118,42 @@ pub enum QueryMsg {
     GetExecutor {},
     #[returns(PriceDetailResponse)]
     GetPriceDetail { key: String },
+    #[returns(PublishersResponse)]
+    GetPublishers { key: String },
+    #[returns(PriceGuardResponse)]
+    GetPriceGuard { key: String },
+    #[returns(PriceSource)]
+    GetPriceSource { key: String },
+    #[returns(PublisherKeysResponse)]
+    GetPublisherKeys { key: String },
+    #[returns(Vec<PriceData>)]
+    GetPriceHistory {
+        key: String,
+        start_after_round: Option<u64>,
+        limit: Option<u32>,
+        order: Option<i32>,
+    },
+    #[returns(PriceData)]
+    GetPriceAt { key: String, timestamp: u64 },
 }
 
+/// Queries the pricefeed makes to the oracle hub
 #[cw_serde]
-pub struct ConfigResponse {}
+pub enum OracleHubQueryMsg {
+    GetPrice { key: String },
+}
+
+#[cw_serde]
+pub struct OracleHubPriceResponse {
+    pub price: Uint128,
+    pub last_updated: u64,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub submission_window: u64, // seconds a round accepts submissions after the first one
+    pub oracle_hub_contract: Option<Addr>,
+}
 
 #[cw_serde]
 pub struct OwnerResponse {
@@ -81,4 +169,26 @@ pub struct ExecutorResponse {
 pub struct PriceDetailResponse {
     pub price: Uint128,
     pub timestamp: Timestamp,
+    pub submissions: u64, // number of publisher submissions aggregated into the price
+    pub is_stale: bool,   // true if the price is older than the heartbeat of the key
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PublishersResponse {
+    pub publishers: Vec<Addr>,
+    pub quorum: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PriceGuardResponse {
+    pub heartbeat: u64, // max age of the latest price in seconds, zero disables
+    pub max_deviation_bps: u64, // max change from the previous round in bps, zero disables
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PublisherKeysResponse {
+    pub pubkeys: Vec<Binary>,
 }
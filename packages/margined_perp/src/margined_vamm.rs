@@ -21,6 +21,79 @@ impl Direction {
     }
 }
 
+/// Trading status of the market, funding is only settled while positions can be
+/// closed and a settled market can no longer change status. Opening a position
+/// on the opposite side opens a new position rather than reducing one, so it is
+/// refused like any other open unless the market is active
+#[cw_serde]
+#[derive(Copy, Eq, Display)]
+pub enum MarketStatus {
+    Active,     // positions can be opened and closed, margin deposited and withdrawn
+    ReduceOnly, // positions can only be closed or liquidated and margin deposited
+    Paused,     // no trading until the market is resumed
+    Settling,   // as reduce only while the market is wound down
+    Settled,    // the market is wound down for good once no position is left
+}
+
+impl MarketStatus {
+    /// Returns whether positions can be closed and funding settled
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            MarketStatus::Active | MarketStatus::ReduceOnly | MarketStatus::Settling
+        )
+    }
+}
+
+#[cw_serde]
+#[derive(Copy, Eq, Display)]
+pub enum CandleInterval {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 3] = [
+        CandleInterval::Minute,
+        CandleInterval::Hour,
+        CandleInterval::Day,
+    ];
+
+    pub fn seconds(&self) -> u64 {
+        match self {
+            CandleInterval::Minute => 60,
+            CandleInterval::Hour => 3_600,
+            CandleInterval::Day => 86_400,
+        }
+    }
+}
+
+/// How the funding payment is derived from the twap premium, rates are
+/// daily ratios in the decimals of the vamm
+#[cw_serde]
+#[derive(Default, Display)]
+pub enum FundingFormula {
+    #[default]
+    Premium, // vamm twap minus the oracle twap
+    PremiumIndex {
+        interest_rate: Uint128, // added to the premium inside the damping band
+        damping_band: Uint128,  // max distance of the interest adjustment from the premium
+    },
+}
+
+/// The curve swaps are priced on, the concentration is a multiplier of the
+/// liquidity around the oracle price in the decimals of the vamm
+#[cw_serde]
+#[derive(Default, Display)]
+pub enum PricingCurve {
+    #[default]
+    ConstantProduct, // x * y = k on the vamm reserves
+    OracleAnchored {
+        concentration: Uint128, // one is constant product pegged to the oracle price
+    },
+}
+
 #[cw_serde]
 pub struct MigrateMsg {}
 
@@ -39,6 +112,7 @@ pub struct InstantiateMsg {
     pub spread_ratio: Uint128,
     pub fluctuation_limit_ratio: Uint128,
     pub initial_margin_ratio: Uint128,
+    pub pricing_curve: Option<PricingCurve>,
 }
 
 #[cw_serde]
@@ -55,6 +129,17 @@ pub enum ExecuteMsg {
         pricefeed: Option<String>,
         spot_price_twap_interval: Option<u64>,
         initial_margin_ratio: Option<Uint128>,
+        snapshot_retention: Option<u64>,
+        repeg_threshold: Option<Uint128>,
+        repeg_budget: Option<Uint128>,
+        min_spread_ratio: Option<Uint128>,
+        max_spread_ratio: Option<Uint128>,
+        volatility_spread_ratio: Option<Uint128>,
+        funding_formula: Option<FundingFormula>,
+        max_funding_rate: Option<Uint128>,
+        continuous_funding: Option<bool>,
+        max_liquidity_change_ratio: Option<Uint128>,
+        liquidity_budget: Option<Uint128>,
     },
     UpdateOwner {
         owner: String,
@@ -73,8 +158,8 @@ pub enum ExecuteMsg {
         quote_asset_limit: Uint128,
     },
     SettleFunding {},
-    SetOpen {
-        open: bool,
+    SetStatus {
+        status: MarketStatus,
     },
     MigrateLiquidity {
         fluctuation_limit_ratio: Option<Uint128>,
@@ -83,12 +168,19 @@ pub enum ExecuteMsg {
     RepegPrice {
         new_price: Option<Uint128>,
     },
+    AutoRepeg {},
+    AdjustLiquidity {
+        revenue: Integer,
+    },
     AddWhitelist {
         address: String,
     },
     RemoveWhitelist {
         address: String,
     },
+    PruneSnapshots {
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -132,6 +224,8 @@ pub enum QueryMsg {
     },
     #[returns(Uint128)]
     SpotPrice {},
+    #[returns(CurveReservesResponse)]
+    CurveReserves {},
     #[returns(Uint128)]
     TwapPrice { interval: u64 },
     #[returns(Uint128)]
@@ -139,7 +233,10 @@ pub enum QueryMsg {
     #[returns(Uint128)]
     UnderlyingTwapPrice { interval: u64 },
     #[returns(CalcFeeResponse)]
-    CalcFee { quote_asset_amount: Uint128 },
+    CalcFee {
+        quote_asset_amount: Uint128,
+        direction: Option<Direction>,
+    },
     #[returns(bool)]
     IsOverSpreadLimit {},
     #[returns(bool)]
@@ -151,6 +248,17 @@ pub enum QueryMsg {
     IsWhitelisted { address: String },
     #[returns(cw_controllers::HooksResponse)]
     GetWhitelist {},
+    #[returns(u64)]
+    OldestSnapshotIndex {},
+    #[returns(RepegBudgetResponse)]
+    RepegBudget {},
+    #[returns(Vec<Candle>)]
+    Candles {
+        interval: CandleInterval,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order_by: Option<i32>,
+    },
 }
 
 #[cw_serde]
@@ -169,6 +277,40 @@ pub struct ConfigResponse {
     pub funding_period: u64,
     pub spot_price_twap_interval: u64,
     pub initial_margin_ratio: Uint128,
+    // max age of the reserve snapshots in seconds, never less than the twap windows
+    #[serde(default)]
+    pub snapshot_retention: u64,
+    // divergence of the twap from the oracle twap, as a ratio, that allows an automatic repeg
+    #[serde(default)]
+    pub repeg_threshold: Uint128,
+    // max repeg cost the insurance fund covers in a funding period
+    #[serde(default)]
+    pub repeg_budget: Uint128,
+    // bounds of the dynamic spread ratio, a zero max keeps the spread ratio fixed
+    #[serde(default)]
+    pub min_spread_ratio: Uint128,
+    #[serde(default)]
+    pub max_spread_ratio: Uint128,
+    // share of the realised volatility that is added to the dynamic spread ratio
+    #[serde(default)]
+    pub volatility_spread_ratio: Uint128,
+    #[serde(default)]
+    pub funding_formula: FundingFormula,
+    // max funding rate per funding period, as a ratio, zero leaves the rate uncapped
+    #[serde(default)]
+    pub max_funding_rate: Uint128,
+    // the settled premium fraction accrues every second over the next period instead of at once
+    #[serde(default)]
+    pub continuous_funding: bool,
+    // max change of the reserves by the liquidity adjustment at each funding settlement, zero disables it
+    #[serde(default)]
+    pub max_liquidity_change_ratio: Uint128,
+    // max cost of liquidity adjustments the insurance fund covers in a funding period
+    #[serde(default)]
+    pub liquidity_budget: Uint128,
+    // curve the swaps are priced on, selected at instantiation
+    #[serde(default)]
+    pub pricing_curve: PricingCurve,
 }
 
 #[cw_serde]
@@ -178,7 +320,7 @@ pub struct OwnerResponse {
 
 #[cw_serde]
 pub struct StateResponse {
-    pub open: bool,
+    pub status: MarketStatus,
     pub quote_asset_reserve: Uint128,
     pub base_asset_reserve: Uint128,
     pub total_position_size: Integer,
@@ -186,8 +328,34 @@ pub struct StateResponse {
     pub next_funding_time: u64,
 }
 
+#[cw_serde]
+pub struct CurveReservesResponse {
+    pub quote_asset_reserve: Uint128,
+    pub base_asset_reserve: Uint128,
+}
+
+#[cw_serde]
+pub struct RepegBudgetResponse {
+    pub budget: Uint128,
+    pub spent: Uint128, // repeg cost covered by the insurance fund in the current period
+    pub period_end: u64,
+}
+
 #[cw_serde]
 pub struct CalcFeeResponse {
     pub toll_fee: Uint128,
     pub spread_fee: Uint128,
 }
+
+/// Prices and volumes of the swaps in an interval starting at `open_time`,
+/// the open price is the spot price before the first swap
+#[cw_serde]
+pub struct Candle {
+    pub open_time: u64,
+    pub open: Uint128,
+    pub high: Uint128,
+    pub low: Uint128,
+    pub close: Uint128,
+    pub base_volume: Uint128,
+    pub quote_volume: Uint128,
+}
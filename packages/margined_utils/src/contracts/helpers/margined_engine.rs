@@ -1,8 +1,10 @@
 use cosmwasm_schema::cw_serde;
 use cw_controllers::HooksResponse;
 use margined_perp::margined_engine::{
-    ConfigResponse, ExecuteMsg, PnlCalcOption, Position, PositionFilter, PositionTpSlResponse,
-    PositionUnrealizedPnlResponse, QueryMsg, Side, StateResponse, TickResponse, TicksResponse,
+    ConfigResponse, ExecuteMsg, FeeShare, PnlCalcOption, Position, PositionFilter,
+    PositionTpSlResponse, PositionUnrealizedPnlResponse, QueryMsg, ReferralConfigResponse,
+    ReferralResponse, ReferrerStatsResponse, Side, SimulateClosePositionResponse,
+    SimulateOpenPositionResponse, StateResponse, TickResponse, TicksResponse,
 };
 
 use cosmwasm_std::{Addr, Coin, CosmosMsg, QuerierWrapper, StdResult, Uint128};
@@ -134,6 +136,18 @@ impl EngineController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn update_fee_distribution(
+        &self,
+        spread_fee: Option<Vec<FeeShare>>,
+        toll_fee: Option<Vec<FeeShare>>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateFeeDistribution {
+            spread_fee,
+            toll_fee,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     pub fn set_pause(&self, pause: bool) -> StdResult<CosmosMsg> {
         let msg = ExecuteMsg::SetPause { pause };
         wasm_execute(&self.0, &msg, vec![])
@@ -195,6 +209,16 @@ impl EngineController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn repeg_price(&self, vamm: String, new_price: Option<Uint128>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RepegPrice { vamm, new_price };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn auto_repeg(&self, vamm: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::AutoRepeg { vamm };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     pub fn deposit_margin(
         &self,
         vamm: String,
@@ -280,6 +304,33 @@ impl EngineController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn update_referral_config(
+        &self,
+        reward_ratio: Option<Uint128>,
+        discount_ratio: Option<Uint128>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateReferralConfig {
+            reward_ratio,
+            discount_ratio,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn register_referral_code(&self, code: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RegisterReferralCode { code };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn bind_referral_code(&self, code: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::BindReferralCode { code };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn claim_referral_rewards(&self) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ClaimReferralRewards {};
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     /// get margin engine configuration
     pub fn config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
         let msg = QueryMsg::Config {};
@@ -476,4 +527,75 @@ impl EngineController {
 
         querier.query_wasm_smart(&self.0, &msg)
     }
+
+    /// get referral reward and discount ratios
+    pub fn referral_config(&self, querier: &QuerierWrapper) -> StdResult<ReferralConfigResponse> {
+        let msg = QueryMsg::ReferralConfig {};
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the stats of a referrer
+    pub fn get_referrer_stats(
+        &self,
+        querier: &QuerierWrapper,
+        referrer: String,
+    ) -> StdResult<ReferrerStatsResponse> {
+        let msg = QueryMsg::ReferrerStats { referrer };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the referral bound by a trader
+    pub fn get_referral(
+        &self,
+        querier: &QuerierWrapper,
+        trader: String,
+    ) -> StdResult<ReferralResponse> {
+        let msg = QueryMsg::Referral { trader };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the referral rewards claimable by an address
+    pub fn get_referral_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        address: String,
+    ) -> StdResult<Uint128> {
+        let msg = QueryMsg::ReferralRewards { address };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// simulate opening a position without executing it
+    pub fn simulate_open_position(
+        &self,
+        querier: &QuerierWrapper,
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+    ) -> StdResult<SimulateOpenPositionResponse> {
+        let msg = QueryMsg::SimulateOpenPosition {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+        };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// simulate closing a position without executing it
+    pub fn simulate_close_position(
+        &self,
+        querier: &QuerierWrapper,
+        vamm: String,
+        position_id: u64,
+    ) -> StdResult<SimulateClosePositionResponse> {
+        let msg = QueryMsg::SimulateClosePosition { vamm, position_id };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
 }
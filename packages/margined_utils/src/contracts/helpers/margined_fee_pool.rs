@@ -1,6 +1,8 @@
 use cosmwasm_schema::cw_serde;
 use margined_perp::margined_fee_pool::{
-    AllTokenResponse, ConfigResponse, ExecuteMsg, QueryMsg, TokenLengthResponse, TokenResponse,
+    AllTokenResponse, ConfigResponse, DistributionResponse, DistributionShare, ExecuteMsg,
+    LastDistributionResponse, PendingRewardsResponse, QueryMsg, StakingStateResponse,
+    TokenLengthResponse, TokenResponse,
 };
 
 use cosmwasm_std::{Addr, CosmosMsg, QuerierWrapper, StdResult, Uint128};
@@ -51,6 +53,55 @@ impl FeePoolController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn update_config(
+        &self,
+        staking_token: Option<String>,
+        staker_share_bps: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            staking_token,
+            staker_share_bps,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn stake(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Stake { amount };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn unstake(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Unstake { amount };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn claim(&self) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Claim {};
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn update_distribution(
+        &self,
+        recipients: Option<Vec<DistributionShare>>,
+        interval: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateDistribution {
+            recipients,
+            interval,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_min_reserve(&self, token: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetMinReserve { token, amount };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn distribute(&self) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Distribute {};
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     //////////////////////
     /// Query Messages ///
     //////////////////////
@@ -86,4 +137,39 @@ impl FeePoolController {
 
         querier.query_wasm_smart(&self.0, &msg)
     }
+
+    /// get the total amount of governance tokens staked
+    pub fn staking_state(&self, querier: &QuerierWrapper) -> StdResult<StakingStateResponse> {
+        let msg = QueryMsg::GetStakingState {};
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the stake and claimable rewards of a staker
+    pub fn pending_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        staker: String,
+    ) -> StdResult<PendingRewardsResponse> {
+        let msg = QueryMsg::GetPendingRewards { staker };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the distribution recipients, interval and reserves
+    pub fn distribution(&self, querier: &QuerierWrapper) -> StdResult<DistributionResponse> {
+        let msg = QueryMsg::GetDistribution {};
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the time and amounts of the last distribution
+    pub fn last_distribution(
+        &self,
+        querier: &QuerierWrapper,
+    ) -> StdResult<LastDistributionResponse> {
+        let msg = QueryMsg::GetLastDistribution {};
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
 }
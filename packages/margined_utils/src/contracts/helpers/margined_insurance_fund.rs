@@ -1,12 +1,13 @@
 use cosmwasm_schema::cw_serde;
 use margined_perp::margined_insurance_fund::{
-    AllVammResponse, AllVammStatusResponse, ConfigResponse, ExecuteMsg, QueryMsg, VammResponse,
-    VammStatusResponse,
+    AllMarketFundsResponse, AllVammResponse, AllVammStatusResponse, ConfigResponse, ExecuteMsg,
+    MarketFundResponse, PendingWithdrawalsResponse, QueryMsg, StakerResponse, StakingStateResponse,
+    VammResponse, VammStatusResponse,
 };
 
-use cosmwasm_std::{Addr, CosmosMsg, QuerierWrapper, StdResult};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, QuerierWrapper, StdResult, Uint128};
 
-use margined_common::messages::wasm_execute;
+use margined_common::{asset::AssetInfo, messages::wasm_execute};
 
 /// InsuranceFundController is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
@@ -43,6 +44,64 @@ impl InsuranceFundController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn update_config(
+        &self,
+        unstake_cooldown: Option<u64>,
+        shared_pool_fallback: Option<bool>,
+        withdrawal_delay: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            unstake_cooldown,
+            shared_pool_fallback,
+            withdrawal_delay,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn stake(&self, amount: Uint128, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Stake { amount };
+        wasm_execute(&self.0, &msg, funds)
+    }
+
+    pub fn unstake(&self, shares: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Unstake { shares };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn claim_unstaked(&self) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ClaimUnstaked {};
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn credit_market_fund(&self, vamm: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::CreditMarketFund { vamm, amount };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn propose_withdrawal(
+        &self,
+        token: AssetInfo,
+        amount: Uint128,
+        recipient: String,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ProposeWithdrawal {
+            token,
+            amount,
+            recipient,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn execute_withdrawal(&self, id: u64) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ExecuteWithdrawal { id };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn cancel_withdrawal(&self, id: u64) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::CancelWithdrawal { id };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     //////////////////////
     /// Query Messages ///
     //////////////////////
@@ -90,4 +149,45 @@ impl InsuranceFundController {
         let res: VammResponse = querier.query_wasm_smart(&self.0, &QueryMsg::IsVamm { vamm })?;
         Ok(res.is_vamm)
     }
+
+    /// get the total shares and the split of the collateral
+    pub fn staking_state(&self, querier: &QuerierWrapper) -> StdResult<StakingStateResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::GetStakingState {})
+    }
+
+    /// get the shares of a staker
+    pub fn staker(&self, querier: &QuerierWrapper, staker: String) -> StdResult<StakerResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::GetStaker { staker })
+    }
+
+    /// get the balance of a market's sub-fund
+    pub fn market_fund(
+        &self,
+        querier: &QuerierWrapper,
+        vamm: String,
+    ) -> StdResult<MarketFundResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::GetMarketFund { vamm })
+    }
+
+    /// get the balances of all the market sub-funds and the shared pool
+    pub fn all_market_funds(
+        &self,
+        querier: &QuerierWrapper,
+        limit: Option<u32>,
+    ) -> StdResult<AllMarketFundsResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::GetAllMarketFunds { limit })
+    }
+
+    /// get the withdrawals that are waiting for their timelock
+    pub fn pending_withdrawals(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PendingWithdrawalsResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &QueryMsg::GetPendingWithdrawals { start_after, limit },
+        )
+    }
 }
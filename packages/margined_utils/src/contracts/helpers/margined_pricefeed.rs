@@ -1,7 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use margined_perp::margined_pricefeed::{ConfigResponse, ExecuteMsg, QueryMsg};
+use margined_perp::margined_pricefeed::{
+    ConfigResponse, ExecuteMsg, PriceData, PriceDetailResponse, PriceGuardResponse, PriceSource,
+    PublisherKeysResponse, PublishersResponse, QueryMsg, SignedPrice,
+};
 
-use cosmwasm_std::{Addr, CosmosMsg, QuerierWrapper, StdResult, Uint128};
+use cosmwasm_std::{Addr, Binary, CosmosMsg, QuerierWrapper, StdResult, Uint128};
 
 use margined_common::messages::wasm_execute;
 
@@ -43,6 +46,82 @@ impl PricefeedController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn update_publishers(
+        &self,
+        key: String,
+        publishers: Vec<String>,
+        quorum: u64,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdatePublishers {
+            key,
+            publishers,
+            quorum,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn submit_price(
+        &self,
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SubmitPrice {
+            key,
+            price,
+            timestamp,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn update_price_guard(
+        &self,
+        key: String,
+        heartbeat: Option<u64>,
+        max_deviation_bps: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdatePriceGuard {
+            key,
+            heartbeat,
+            max_deviation_bps,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn force_append_price(
+        &self,
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ForceAppendPrice {
+            key,
+            price,
+            timestamp,
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn update_price_source(&self, key: String, source: PriceSource) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdatePriceSource { key, source };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn sync_price(&self, key: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SyncPrice { key };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn update_publisher_keys(&self, key: String, pubkeys: Vec<Binary>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdatePublisherKeys { key, pubkeys };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn submit_signed_prices(&self, payloads: Vec<SignedPrice>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SubmitSignedPrices { payloads };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     /// get margined pricefeed configuration
     pub fn config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
         let msg = QueryMsg::Config {};
@@ -83,4 +162,86 @@ impl PricefeedController {
 
         querier.query_wasm_smart(&self.0, &msg)
     }
+
+    /// get a page of the rounds of a key
+    pub fn price_history(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+        start_after_round: Option<u64>,
+        limit: Option<u32>,
+        order: Option<i32>,
+    ) -> StdResult<Vec<PriceData>> {
+        let msg = QueryMsg::GetPriceHistory {
+            key,
+            start_after_round,
+            limit,
+            order,
+        };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the round that was the latest price at a timestamp
+    pub fn price_at(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+        timestamp: u64,
+    ) -> StdResult<PriceData> {
+        let msg = QueryMsg::GetPriceAt { key, timestamp };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the latest price with its timestamp and number of submissions
+    pub fn price_detail(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+    ) -> StdResult<PriceDetailResponse> {
+        let msg = QueryMsg::GetPriceDetail { key };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the publishers of a key
+    pub fn publishers(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+    ) -> StdResult<PublishersResponse> {
+        let msg = QueryMsg::GetPublishers { key };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the heartbeat and max deviation of a key
+    pub fn price_guard(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+    ) -> StdResult<PriceGuardResponse> {
+        let msg = QueryMsg::GetPriceGuard { key };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get where the price of a key is taken from
+    pub fn price_source(&self, querier: &QuerierWrapper, key: String) -> StdResult<PriceSource> {
+        let msg = QueryMsg::GetPriceSource { key };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
+
+    /// get the public keys whose signed prices are accepted for a key
+    pub fn publisher_keys(
+        &self,
+        querier: &QuerierWrapper,
+        key: String,
+    ) -> StdResult<PublisherKeysResponse> {
+        let msg = QueryMsg::GetPublisherKeys { key };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
 }
@@ -1,11 +1,12 @@
 use cosmwasm_schema::cw_serde;
 use margined_perp::margined_vamm::{
-    CalcFeeResponse, ConfigResponse, Direction, ExecuteMsg, QueryMsg, StateResponse,
+    CalcFeeResponse, Candle, CandleInterval, ConfigResponse, CurveReservesResponse, Direction,
+    ExecuteMsg, FundingFormula, MarketStatus, QueryMsg, RepegBudgetResponse, StateResponse,
 };
 
 use cosmwasm_std::{Addr, CosmosMsg, QuerierWrapper, StdResult, Uint128};
 
-use margined_common::messages::wasm_execute;
+use margined_common::{integer::Integer, messages::wasm_execute};
 
 /// VammController is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
@@ -30,6 +31,17 @@ impl VammController {
         pricefeed: Option<String>,
         spot_price_twap_interval: Option<u64>,
         initial_margin_ratio: Option<Uint128>,
+        snapshot_retention: Option<u64>,
+        repeg_threshold: Option<Uint128>,
+        repeg_budget: Option<Uint128>,
+        min_spread_ratio: Option<Uint128>,
+        max_spread_ratio: Option<Uint128>,
+        volatility_spread_ratio: Option<Uint128>,
+        funding_formula: Option<FundingFormula>,
+        max_funding_rate: Option<Uint128>,
+        continuous_funding: Option<bool>,
+        max_liquidity_change_ratio: Option<Uint128>,
+        liquidity_budget: Option<Uint128>,
     ) -> StdResult<CosmosMsg> {
         wasm_execute(
             &self.0,
@@ -43,7 +55,18 @@ impl VammController {
                 insurance_fund,
                 pricefeed,
                 spot_price_twap_interval,
-                initial_margin_ratio
+                initial_margin_ratio,
+                snapshot_retention,
+                repeg_threshold,
+                repeg_budget,
+                min_spread_ratio,
+                max_spread_ratio,
+                volatility_spread_ratio,
+                funding_formula,
+                max_funding_rate,
+                continuous_funding,
+                max_liquidity_change_ratio,
+                liquidity_budget
             },
             vec![],
         )
@@ -64,7 +87,18 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
-            initial_margin_ratio: None
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
         };
         wasm_execute(&self.0, &msg, vec![])
     }
@@ -80,7 +114,18 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
-            initial_margin_ratio: None
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
         };
         wasm_execute(&self.0, &msg, vec![])
     }
@@ -99,7 +144,18 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
-            initial_margin_ratio: None
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
         };
         wasm_execute(&self.0, &msg, vec![])
     }
@@ -118,7 +174,18 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
-            initial_margin_ratio: None
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
         };
         wasm_execute(&self.0, &msg, vec![])
     }
@@ -137,13 +204,24 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
-            initial_margin_ratio: None
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
         };
         wasm_execute(&self.0, &msg, vec![])
     }
 
-    pub fn set_open(&self, open: bool) -> StdResult<CosmosMsg> {
-        let msg = ExecuteMsg::SetOpen { open };
+    pub fn set_status(&self, status: MarketStatus) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetStatus { status };
         wasm_execute(&self.0, &msg, vec![])
     }
 
@@ -186,6 +264,173 @@ impl VammController {
         wasm_execute(&self.0, &msg, vec![])
     }
 
+    pub fn prune_snapshots(&self, limit: Option<u32>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::PruneSnapshots { limit };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn repeg_price(&self, new_price: Option<Uint128>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RepegPrice { new_price };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn adjust_liquidity(&self, revenue: Integer) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::AdjustLiquidity { revenue };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_repeg_config(
+        &self,
+        repeg_threshold: Uint128,
+        repeg_budget: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: Some(repeg_threshold),
+            repeg_budget: Some(repeg_budget),
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_spread_limits(
+        &self,
+        min_spread_ratio: Uint128,
+        max_spread_ratio: Uint128,
+        volatility_spread_ratio: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: Some(min_spread_ratio),
+            max_spread_ratio: Some(max_spread_ratio),
+            volatility_spread_ratio: Some(volatility_spread_ratio),
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_funding_config(
+        &self,
+        funding_formula: FundingFormula,
+        max_funding_rate: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: Some(funding_formula),
+            max_funding_rate: Some(max_funding_rate),
+            continuous_funding: None,
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_continuous_funding(&self, continuous_funding: bool) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: Some(continuous_funding),
+            max_liquidity_change_ratio: None,
+            liquidity_budget: None
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
+    pub fn set_liquidity_adjustment(
+        &self,
+        max_liquidity_change_ratio: Uint128,
+        liquidity_budget: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            initial_margin_ratio: None,
+            snapshot_retention: None,
+            repeg_threshold: None,
+            repeg_budget: None,
+            min_spread_ratio: None,
+            max_spread_ratio: None,
+            volatility_spread_ratio: None,
+            funding_formula: None,
+            max_funding_rate: None,
+            continuous_funding: None,
+            max_liquidity_change_ratio: Some(max_liquidity_change_ratio),
+            liquidity_budget: Some(liquidity_budget)
+        };
+        wasm_execute(&self.0, &msg, vec![])
+    }
+
     /// get margin vamm configuration
     pub fn config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
         querier.query_wasm_smart(&self.0, &QueryMsg::Config {})
@@ -205,6 +450,16 @@ impl VammController {
     ) -> StdResult<Uint128> {
         querier.query_wasm_smart(&self.0, &QueryMsg::InputPrice { direction, amount })
     }
+
+    /// get input amount
+    pub fn input_amount(
+        &self,
+        querier: &QuerierWrapper,
+        direction: Direction,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::InputAmount { direction, amount })
+    }
     
     /// get output price
     pub fn output_price(
@@ -221,6 +476,11 @@ impl VammController {
         querier.query_wasm_smart(&self.0, &QueryMsg::SpotPrice {})
     }
 
+    /// get the reserves the swaps are priced against under the pricing curve
+    pub fn curve_reserves(&self, querier: &QuerierWrapper) -> StdResult<CurveReservesResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::CurveReserves {})
+    }
+
     /// get twap price
     pub fn twap_price(&self, querier: &QuerierWrapper, interval: u64) -> StdResult<Uint128> {
         let msg = QueryMsg::TwapPrice { interval };
@@ -233,8 +493,14 @@ impl VammController {
         &self,
         querier: &QuerierWrapper,
         quote_asset_amount: Uint128,
+        direction: Option<Direction>,
     ) -> StdResult<CalcFeeResponse> {
-        querier.query_wasm_smart(&self.0, &QueryMsg::CalcFee { quote_asset_amount })
+        let msg = QueryMsg::CalcFee {
+            quote_asset_amount,
+            direction,
+        };
+
+        querier.query_wasm_smart(&self.0, &msg)
     }
 
     /// returns bool if vamm is over spread limit
@@ -284,4 +550,33 @@ impl VammController {
             },
         )
     }
+
+    /// get the index of the oldest reserve snapshot
+    pub fn oldest_snapshot_index(&self, querier: &QuerierWrapper) -> StdResult<u64> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::OldestSnapshotIndex {})
+    }
+
+    /// get the repeg budget of the current funding period
+    pub fn repeg_budget(&self, querier: &QuerierWrapper) -> StdResult<RepegBudgetResponse> {
+        querier.query_wasm_smart(&self.0, &QueryMsg::RepegBudget {})
+    }
+
+    /// get the candles of an interval
+    pub fn candles(
+        &self,
+        querier: &QuerierWrapper,
+        interval: CandleInterval,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order_by: Option<i32>,
+    ) -> StdResult<Vec<Candle>> {
+        let msg = QueryMsg::Candles {
+            interval,
+            start_after,
+            limit,
+            order_by,
+        };
+
+        querier.query_wasm_smart(&self.0, &msg)
+    }
 }
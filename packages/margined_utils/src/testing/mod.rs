@@ -18,7 +18,7 @@ use margined_perp::margined_pricefeed::{
     ExecuteMsg as PricefeedExecuteMsg, InstantiateMsg as PricefeedInstantiateMsg,
 };
 use margined_perp::margined_vamm::{
-    ExecuteMsg as VammExecuteMsg, InstantiateMsg as VammInstantiateMsg,
+    ExecuteMsg as VammExecuteMsg, InstantiateMsg as VammInstantiateMsg, MarketStatus,
 };
 
 pub mod test_tube;
@@ -216,6 +216,7 @@ impl NativeTokenScenario {
                     margin_engine: None,
                     insurance_fund: Some(insurance_fund.0.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm",
@@ -240,13 +241,24 @@ impl NativeTokenScenario {
                     pricefeed: None,
                     spot_price_twap_interval: None,
                     initial_margin_ratio: None,
+                    snapshot_retention: None,
+                    repeg_threshold: None,
+                    repeg_budget: None,
+                    min_spread_ratio: None,
+                    max_spread_ratio: None,
+                    volatility_spread_ratio: None,
+                    funding_formula: None,
+                    max_funding_rate: None,
+                    continuous_funding: None,
+                    max_liquidity_change_ratio: None,
+                    liquidity_budget: None,
                 },
                 &[],
             )
             .unwrap();
 
         // set open and register
-        let msg = vamm.set_open(true).unwrap();
+        let msg = vamm.set_status(MarketStatus::Active).unwrap();
         router.execute(owner.clone(), msg).unwrap();
 
         let msg = insurance_fund.add_vamm(vamm.0.to_string()).unwrap();
@@ -510,6 +522,7 @@ impl SimpleScenario {
                     margin_engine: None,
                     insurance_fund: Some(insurance_fund_addr.to_string()),
                     initial_margin_ratio: Uint128::from(50_000_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm",
@@ -534,13 +547,24 @@ impl SimpleScenario {
                     pricefeed: None,
                     spot_price_twap_interval: None,
                     initial_margin_ratio: None,
+                    snapshot_retention: None,
+                    repeg_threshold: None,
+                    repeg_budget: None,
+                    min_spread_ratio: None,
+                    max_spread_ratio: None,
+                    volatility_spread_ratio: None,
+                    funding_formula: None,
+                    max_funding_rate: None,
+                    continuous_funding: None,
+                    max_liquidity_change_ratio: None,
+                    liquidity_budget: None,
                 },
                 &[],
             )
             .unwrap();
 
         // set open and register
-        let msg = vamm.set_open(true).unwrap();
+        let msg = vamm.set_status(MarketStatus::Active).unwrap();
         router.execute(owner.clone(), msg).unwrap();
 
         let msg = insurance_fund.add_vamm(vamm.0.to_string()).unwrap();
@@ -745,6 +769,7 @@ impl VammScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some("insurance_fund".to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm",
@@ -753,7 +778,7 @@ impl VammScenario {
             .unwrap();
         let vamm = VammController(vamm_addr);
 
-        let msg = vamm.set_open(true).unwrap();
+        let msg = vamm.set_status(MarketStatus::Active).unwrap();
         router.execute(owner.clone(), msg).unwrap();
 
         Self {
@@ -867,6 +892,7 @@ impl ShutdownScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some(insurance_fund_addr.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm1",
@@ -875,7 +901,7 @@ impl ShutdownScenario {
             .unwrap();
         let vamm1 = VammController(vamm1_addr);
 
-        let msg = vamm1.set_open(true).unwrap();
+        let msg = vamm1.set_status(MarketStatus::Active).unwrap();
         router.execute(insurance_fund_addr.clone(), msg).unwrap();
 
         let vamm2_addr = router
@@ -896,6 +922,7 @@ impl ShutdownScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some(insurance_fund_addr.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm2",
@@ -904,7 +931,7 @@ impl ShutdownScenario {
             .unwrap();
         let vamm2 = VammController(vamm2_addr);
 
-        let msg = vamm2.set_open(true).unwrap();
+        let msg = vamm2.set_status(MarketStatus::Active).unwrap();
         router.execute(insurance_fund_addr.clone(), msg).unwrap();
 
         let vamm3_addr = router
@@ -925,6 +952,7 @@ impl ShutdownScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some(insurance_fund_addr.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm3",
@@ -933,7 +961,7 @@ impl ShutdownScenario {
             .unwrap();
         let vamm3 = VammController(vamm3_addr);
 
-        let msg = vamm3.set_open(true).unwrap();
+        let msg = vamm3.set_status(MarketStatus::Active).unwrap();
         router.execute(insurance_fund_addr.clone(), msg).unwrap();
 
         let vamm4_addr = router
@@ -954,6 +982,7 @@ impl ShutdownScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some(insurance_fund.0.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm4",
@@ -962,7 +991,7 @@ impl ShutdownScenario {
             .unwrap();
         let vamm4 = VammController(vamm4_addr);
 
-        let msg = vamm4.set_open(true).unwrap();
+        let msg = vamm4.set_status(MarketStatus::Active).unwrap();
         router.execute(insurance_fund_addr, msg).unwrap();
 
         let vamm5_addr = router
@@ -983,6 +1012,7 @@ impl ShutdownScenario {
                     margin_engine: Some(owner.to_string()),
                     insurance_fund: Some(insurance_fund.0.to_string()),
                     initial_margin_ratio: Uint128::from(500_000u128),
+                    pricing_curve: None,
                 },
                 &[],
                 "vamm5",
@@ -15,7 +15,7 @@ use margined_perp::margined_pricefeed::{
     ExecuteMsg as PricefeedExecuteMsg, InstantiateMsg as PricefeedInstantiateMsg,
 };
 use margined_perp::margined_vamm::{
-    ExecuteMsg as VammExecuteMsg, InstantiateMsg as VammInstantiateMsg,
+    ExecuteMsg as VammExecuteMsg, InstantiateMsg as VammInstantiateMsg, MarketStatus,
 };
 use osmosis_test_tube::{Module, OraichainTestApp, Wasm};
 use test_tube::{cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse, Account};
@@ -259,6 +259,7 @@ impl TestTubeScenario {
                     margin_engine: None,
                     insurance_fund: Some(insurance_fund_addr.to_string()),
                     initial_margin_ratio: Uint128::from(50_000u128),
+                    pricing_curve: None,
                 },
                 Some(&owner.address()),
                 Some("vamm"),
@@ -284,6 +285,17 @@ impl TestTubeScenario {
                 pricefeed: None,
                 spot_price_twap_interval: None,
                 initial_margin_ratio: None,
+                snapshot_retention: None,
+                repeg_threshold: None,
+                repeg_budget: None,
+                min_spread_ratio: None,
+                max_spread_ratio: None,
+                volatility_spread_ratio: None,
+                funding_formula: None,
+                max_funding_rate: None,
+                continuous_funding: None,
+                max_liquidity_change_ratio: None,
+                liquidity_budget: None,
             },
             &[],
             owner,
@@ -291,7 +303,7 @@ impl TestTubeScenario {
         .unwrap();
 
         // set open and register
-        let msg = vamm.set_open(true).unwrap();
+        let msg = vamm.set_status(MarketStatus::Active).unwrap();
         router
             .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[msg], owner)
             .unwrap();
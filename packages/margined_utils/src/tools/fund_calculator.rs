@@ -28,7 +28,9 @@ pub fn calculate_funds_needed(
     let vamm_controller = VammController(vamm.clone());
 
     // pull the fees for the vamm that the position will be taken on; note that this will be shifted however many digits
-    let fee_amount = vamm_controller.calc_fee(querier, new_notional)?.toll_fee;
+    let fee_amount = vamm_controller
+        .calc_fee(querier, new_notional, None)?
+        .toll_fee;
     let margin_owed = Integer::new_positive(quote_asset_amount);
     let funds_owed = if margin_owed.is_positive() {
         margin_owed.value
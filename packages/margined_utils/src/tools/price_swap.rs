@@ -1,5 +1,44 @@
 use cosmwasm_std::{StdResult, Uint128};
-use margined_perp::margined_vamm::Direction;
+use margined_common::integer::Integer;
+use margined_perp::margined_vamm::{Direction, PricingCurve};
+
+/// Returns the (quote, base) reserves the next swap is priced against, the input
+/// and output prices are then the constant product on these reserves.
+///
+/// The oracle anchored curve prices the swaps on the constant product whose spot
+/// price is the oracle price when the net position is zero. The depth at the peg
+/// is the base reserve of a zero net position scaled by the concentration, so the
+/// price impact of a trade shrinks as the concentration grows.
+pub fn get_curve_reserves(
+    pricing_curve: &PricingCurve,
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    total_position_size: Integer,
+    oracle_price: Uint128,
+    decimals: Uint128,
+) -> StdResult<(Uint128, Uint128)> {
+    match pricing_curve {
+        PricingCurve::ConstantProduct => Ok((quote_asset_reserve, base_asset_reserve)),
+        PricingCurve::OracleAnchored { concentration } => {
+            // the base reserve once every position is closed
+            let peg_base_reserve = if total_position_size.is_positive() {
+                base_asset_reserve.checked_add(total_position_size.value)?
+            } else {
+                base_asset_reserve.checked_sub(total_position_size.value)?
+            };
+
+            let depth = peg_base_reserve.multiply_ratio(*concentration, decimals);
+            let curve_base_reserve = base_asset_reserve
+                .checked_add(depth)?
+                .checked_sub(peg_base_reserve)?;
+            let curve_quote_reserve = depth
+                .multiply_ratio(oracle_price, decimals)
+                .multiply_ratio(depth, curve_base_reserve);
+
+            Ok((curve_quote_reserve, curve_base_reserve))
+        }
+    }
+}
 
 pub fn get_input_price_with_reserves(
     direction: &Direction,